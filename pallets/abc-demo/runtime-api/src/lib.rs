@@ -0,0 +1,107 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use sp_std::prelude::*;
+
+/// On chain values the offchain worker of the abc pallet runs with. Node local settings kept
+/// in offchain local storage (dry run, service auth token, metrics) can't be read by the
+/// runtime and are not included.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct OffchainConfig<BlockNumber> {
+    /// Timeout of task requests in milliseconds.
+    pub send_task_timeout: u64,
+    /// Prefix of service urls, hosts are the net addresses registered by clients.
+    pub service_url_prefix: Vec<u8>,
+    /// Number of blocks between two polls of errand results.
+    pub poll_interval: BlockNumber,
+    /// Number of blocks after queueing within which a task can be initialized.
+    pub max_init_errand_delay: u32,
+    /// Whether errand results are polled at all.
+    pub polling_enabled: bool,
+}
+
+/// Outcome of a finished errand reported by its worker, finer grained than the done and
+/// failed errand statuses.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Success,
+    Timeout,
+    Refused,
+    Error,
+}
+
+sp_api::decl_runtime_apis! {
+    pub trait AbcApi<AccountId, BlockNumber> where
+        AccountId: Codec,
+        BlockNumber: Codec,
+    {
+        /// Ids of the errands initialized with the given description cid.
+        fn errands_by_cid(cid: Vec<u8>) -> Vec<Vec<u8>>;
+
+        /// Ids and results of the done errands of `account`, at most `limit` of them.
+        fn completed_errands_of(account: AccountId, limit: u32) -> Vec<(Vec<u8>, Vec<u8>)>;
+
+        /// Ids of the errands labeled with `label` when queued.
+        fn errands_by_label(label: Vec<u8>) -> Vec<Vec<u8>>;
+
+        /// Status code of each errand in `cids`: 0 not found, 1 processing, 2 done,
+        /// 3 failed and 4 disputed. Only the first 256 cids are queried.
+        fn errand_statuses(cids: Vec<Vec<u8>>) -> Vec<u8>;
+
+        /// Offchain worker configuration currently in effect.
+        fn offchain_config() -> OffchainConfig<BlockNumber>;
+
+        /// Comments of the errand with description `cid` as (author, comment cid, height).
+        fn errand_comments(cid: Vec<u8>) -> Vec<(AccountId, Vec<u8>, BlockNumber)>;
+
+        /// Ids and creation heights of at most `limit` errands created from height `from`
+        /// to `to` inclusive, for indexers. At most 1024 blocks and 100 errands are returned.
+        fn errands_created_between(
+            from: BlockNumber,
+            to: BlockNumber,
+            limit: u32,
+        ) -> Vec<(Vec<u8>, BlockNumber)>;
+
+        /// Outcome of the finished errand with description `cid`, `None` if it is unknown or
+        /// still processing.
+        fn errand_outcome(cid: Vec<u8>) -> Option<Outcome>;
+
+        /// Errand id of the task `account` queues with `salt` by `begin_task_with_salt`.
+        fn derive_errand_id(account: AccountId, salt: Vec<u8>) -> Vec<u8>;
+
+        /// Status codes of the errand with `errand_id` and the heights it entered them, in
+        /// transition order. Only the last 16 transitions are kept.
+        fn errand_status_log(errand_id: Vec<u8>) -> Vec<(u8, BlockNumber)>;
+
+        /// Worker of the done errand with description `cid` and its signature over
+        /// `(b"abc-demo::completion", errand id, result hash)`, `None` if the errand was
+        /// completed without a proof.
+        fn completion_proof(cid: Vec<u8>) -> Option<(AccountId, Vec<u8>)>;
+
+        /// Latest result of the errands with description `cid` and its completion height,
+        /// `None` while the errand is private.
+        fn latest_result(cid: Vec<u8>) -> Option<(BlockNumber, Vec<u8>)>;
+
+        /// Results of the errands with description `cid` and their completion heights,
+        /// oldest first, empty while the errand is private. Only the last 4 results are kept.
+        fn result_versions(cid: Vec<u8>) -> Vec<(BlockNumber, Vec<u8>)>;
+
+        /// Id of the errand queued by the extrinsic at `index` of block `block`.
+        fn errand_by_extrinsic(block: BlockNumber, index: u32) -> Option<Vec<u8>>;
+
+        /// Numbers of (processing, done, failed, expired) errands of `account`, errands
+        /// whose worker timed out count as expired.
+        fn account_status_summary(account: AccountId) -> (u32, u32, u32, u32);
+
+        /// Number of tasks stored in `Tasks` over all heights, read from a counter.
+        fn total_pending_tasks() -> u64;
+
+        /// Whether `begin_task` accepts `cid` as description cid, so clients can check cids
+        /// by the same rules as the chain.
+        fn is_valid_cid(cid: Vec<u8>) -> bool;
+
+        /// Rolling average milliseconds of the task requests timed by workers and the number
+        /// of samples it stands for, at most 1000.
+        fn service_latency() -> (u32, u32);
+    }
+}
@@ -0,0 +1,133 @@
+use codec::{Compact, Decode, Encode, EncodeLike, Input};
+use frame_support::traits::Get;
+use sp_std::{convert::TryFrom, fmt, marker::PhantomData, ops::Deref, prelude::*};
+
+/// A `Vec` of at most `S::get()` items, encoded the same as a `Vec` so it can replace one
+/// in storage without a migration. Decoding more items than the bound fails, so a storage
+/// read of an oversized value returns the default instead of the oversized value.
+pub struct BoundedVec<T, S>(Vec<T>, PhantomData<S>);
+
+impl<T, S: Get<u32>> BoundedVec<T, S> {
+    /// Maximum number of items.
+    pub fn bound() -> usize {
+        S::get() as usize
+    }
+
+    /// Appends `item`, handing it back if the vector is full.
+    pub fn try_push(&mut self, item: T) -> Result<(), T> {
+        if self.0.len() >= Self::bound() {
+            return Err(item);
+        }
+        self.0.push(item);
+        Ok(())
+    }
+}
+
+impl<T, S> BoundedVec<T, S> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        self.0.remove(index)
+    }
+
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        self.0.retain(f)
+    }
+}
+
+impl<T, S: Get<u32>> TryFrom<Vec<T>> for BoundedVec<T, S> {
+    type Error = Vec<T>;
+
+    fn try_from(items: Vec<T>) -> Result<Self, Vec<T>> {
+        if items.len() > Self::bound() {
+            return Err(items);
+        }
+        Ok(BoundedVec(items, PhantomData))
+    }
+}
+
+impl<T, S> Default for BoundedVec<T, S> {
+    fn default() -> Self {
+        BoundedVec(Vec::new(), PhantomData)
+    }
+}
+
+impl<T: Clone, S> Clone for BoundedVec<T, S> {
+    fn clone(&self) -> Self {
+        BoundedVec(self.0.clone(), PhantomData)
+    }
+}
+
+impl<T: PartialEq, S> PartialEq for BoundedVec<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq, S> Eq for BoundedVec<T, S> {}
+
+impl<T: PartialEq, S> PartialEq<Vec<T>> for BoundedVec<T, S> {
+    fn eq(&self, other: &Vec<T>) -> bool {
+        &self.0 == other
+    }
+}
+
+impl<T: PartialEq, S> PartialEq<BoundedVec<T, S>> for Vec<T> {
+    fn eq(&self, other: &BoundedVec<T, S>) -> bool {
+        self == &other.0
+    }
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for BoundedVec<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T, S> Deref for BoundedVec<T, S> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.0
+    }
+}
+
+impl<T, S> IntoIterator for BoundedVec<T, S> {
+    type Item = T;
+    type IntoIter = sp_std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T: Encode, S> Encode for BoundedVec<T, S> {
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+
+    fn using_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        self.0.using_encoded(f)
+    }
+}
+
+impl<T: Encode, S> EncodeLike for BoundedVec<T, S> {}
+
+impl<T: Encode, S> EncodeLike<Vec<T>> for BoundedVec<T, S> {}
+
+impl<T: Decode, S: Get<u32>> Decode for BoundedVec<T, S> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        // check the length prefix first so oversized values are not decoded at all
+        let len = <Compact<u32>>::decode(input)?.0 as usize;
+        if len > Self::bound() {
+            return Err("BoundedVec exceeds its bound".into());
+        }
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(BoundedVec(items, PhantomData))
+    }
+}
@@ -5,8 +5,13 @@ use alt_serde::Deserialize;
 use codec::{Decode, Encode};
 use frame_support::debug;
 use sp_core::crypto::AccountId32;
+use sp_runtime::offchain::storage::StorageValueRef;
 
-const APPLY_DELEGATE: &'static str = "/api/be_my_delegate";
+const APPLY_DELEGATE: &'static str = "/be_my_delegate";
+
+/// Path prefix of the TEA service actions, `DEFAULT_SERVICE_PATH_PREFIX` if not set.
+pub const LOCAL_STORAGE_SERVICE_PATH_PREFIX_KEY: &'static str = "abc-demo::service-path-prefix";
+const DEFAULT_SERVICE_PATH_PREFIX: &'static str = "/api";
 
 const LOCAL_STORAGE_CLIENT_KEY_PREFIX: &'static str = "local-storage::client-";
 const LOCAL_STORAGE_CLIENT_LOCK_PREFIX: &'static str = "local-storage::client-lock-";
@@ -44,6 +49,19 @@ pub fn get_url(net_address: &NetAddress) -> anyhow::Result<String> {
     }
 }
 
+/// Path of the service `action` under the configured service path prefix.
+pub fn action_path(action: &str) -> String {
+    let prefix = StorageValueRef::persistent(LOCAL_STORAGE_SERVICE_PATH_PREFIX_KEY.as_bytes());
+    let prefix = match prefix.get::<Vec<u8>>() {
+        Some(Some(prefix)) => String::from_utf8(prefix).unwrap_or_else(|e| {
+            debug::error!("invalid service path prefix: {}", e);
+            DEFAULT_SERVICE_PATH_PREFIX.into()
+        }),
+        _ => DEFAULT_SERVICE_PATH_PREFIX.into(),
+    };
+    format!("{}{}", prefix, action)
+}
+
 pub fn request_single_delegate(account: AccountId32, net_address: &NetAddress) -> bool {
     let client = format!("{}", account);
     let proto_msg = actor_delegate_proto::BeMyDelegateRequest {
@@ -63,7 +81,12 @@ pub fn request_single_delegate(account: AccountId32, net_address: &NetAddress) -
     match encode_protobuf(proto_msg) {
         Ok(buf) => {
             let content = base64::encode(buf);
-            let request_url = format!("{}{}?content={}", service_url, APPLY_DELEGATE, &content);
+            let request_url = format!(
+                "{}{}?content={}",
+                service_url,
+                action_path(APPLY_DELEGATE),
+                &content
+            );
 
             match crate::http::http_post(&request_url) {
                 Ok(resp) => {
@@ -94,7 +117,7 @@ fn parse_delegate_response(resp: Vec<u8>, client: &str) -> anyhow::Result<()> {
     save_delegate_info(client, &result_info)
 }
 
-fn save_delegate_info(client: &str, delegate_info: &DelegateInfo) -> anyhow::Result<()> {
+pub fn save_delegate_info(client: &str, delegate_info: &DelegateInfo) -> anyhow::Result<()> {
     let key = [LOCAL_STORAGE_CLIENT_KEY_PREFIX, client]
         .concat()
         .as_bytes()
@@ -11,6 +11,9 @@ pub enum AbcError {
     #[error("http response failed, details: `{0}`")]
     HttpResponseError(String),
 
+    #[error("response body is longer than {0} bytes")]
+    ResultTooLarge(usize),
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
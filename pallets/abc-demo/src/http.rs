@@ -1,13 +1,22 @@
 use crate::error::AbcError;
+use crate::HttpMethod;
 use alt_serde::Deserialize;
 use codec::{Decode, Encode};
 use frame_support::debug;
 use sp_core::offchain::HttpError;
-use sp_runtime::offchain::{self as rt_offchain};
+use sp_runtime::offchain::{self as rt_offchain, storage::StorageValueRef};
 
 const USER_AGENT: &'static str = "tearust";
+pub const DEFAULT_POST_BODY: &'static [u8] = b"post body";
 const HTTP_POST_TIMEOUT: u64 = 180000; // post timeout set to 3 minutes.
 
+/// Hard cap on the bytes read from any http response whatever the caller allows, so a
+/// malicious service can't exhaust the memory of the worker. Defaults to
+/// `DEFAULT_MAX_HTTP_RESPONSE_BYTES`.
+pub const LOCAL_STORAGE_MAX_HTTP_RESPONSE_BYTES_KEY: &'static str =
+    "abc-demo::max-http-response-bytes";
+pub const DEFAULT_MAX_HTTP_RESPONSE_BYTES: u32 = 1024 * 1024;
+
 #[serde(crate = "alt_serde")]
 #[derive(Encode, Decode, Deserialize, Clone)]
 struct ResponseResult {
@@ -15,14 +24,187 @@ struct ResponseResult {
 }
 
 pub fn http_post(url: &str) -> anyhow::Result<Vec<u8>> {
-    let post_body = vec![b"post body"];
+    http_post_with_headers(url, &[])
+}
+
+/// Same as `http_post` with `headers` sent in addition to the user agent.
+pub fn http_post_with_headers(url: &str, headers: &[(&str, &str)]) -> anyhow::Result<Vec<u8>> {
+    http_post_with_body(url, headers, DEFAULT_POST_BODY)
+}
+
+pub fn http_post_with_body(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    Ok(http_post_for_response(url, headers, body, usize::max_value())?.data)
+}
+
+/// Sends a request with `method` and returns the `data` of the response body. Bodyless
+/// POST and PUT requests carry `DEFAULT_POST_BODY`, GET requests never carry a body.
+pub fn http_request_with_method(
+    method: HttpMethod,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: Option<&[u8]>,
+) -> anyhow::Result<Vec<u8>> {
+    let body = match method {
+        HttpMethod::Get => {
+            if body.is_some() {
+                return Err(anyhow::anyhow!("GET requests can't carry a body"));
+            }
+            &[][..]
+        }
+        HttpMethod::Post | HttpMethod::Put => body.unwrap_or(DEFAULT_POST_BODY),
+    };
+    let response = send_request(method, url, headers, body)?;
+    Ok(response_data(url, response, usize::max_value())?.0)
+}
+
+/// JSON object of the string `fields` with its keys in byte order, so the same fields give
+/// the same bytes whatever order they are passed in and services can verify signatures over
+/// the body.
+pub fn canonical_json(fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut fields = fields.to_vec();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    let mut json = String::from("{");
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        push_json_string(&mut json, key);
+        json.push(':');
+        push_json_string(&mut json, value);
+    }
+    json.push('}');
+    json.into_bytes()
+}
+
+fn push_json_string(json: &mut String, value: &str) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+pub struct HttpResponse {
+    /// The `data` field of the response body.
+    pub data: Vec<u8>,
+    /// Value of the `Content-Type` response header, if any.
+    pub content_type: Option<String>,
+}
+
+/// Same as `http_post_with_body`, also returning the response headers callers care about.
+/// Response bodies longer than `max_body_length` fail with `AbcError::ResultTooLarge`.
+pub fn http_post_for_response(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    max_body_length: usize,
+) -> anyhow::Result<HttpResponse> {
+    let response = send_post(url, headers, body)?;
+    let (data, content_type) = response_data(url, response, max_body_length)?;
+    Ok(HttpResponse { data, content_type })
+}
+
+// parses the `data` field of a TEA service response, also returning its content type
+fn response_data(
+    url: &str,
+    response: rt_offchain::http::Response,
+    max_body_length: usize,
+) -> anyhow::Result<(Vec<u8>, Option<String>)> {
+    let content_type = response_header(&response, "Content-Type");
+    let res_body = String::from_utf8(read_body(&response, max_body_length)?)?;
+    let response_result: ResponseResult = serde_json::from_str::<ResponseResult>(&res_body)
+        .map_err(|e| AbcError::Common(format!("{}", e)))?;
+    debug::info!(
+        "end of http request ({}), response is {}",
+        url,
+        &response_result.data,
+    );
+    Ok((response_result.data.as_bytes().to_vec(), content_type))
+}
+
+/// Posts `body` to a non TEA service and returns the response body as is.
+pub fn http_post_raw(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    read_body(&send_post(url, headers, body)?, usize::max_value())
+}
+
+/// Reads the body of `response` until its end, however many chunks it arrives in. Fails
+/// with `AbcError::ResultTooLarge` as soon as more than `max_length` bytes, or more than
+/// `max_http_response_bytes` if lower, were read.
+pub fn read_body(
+    response: &rt_offchain::http::Response,
+    max_length: usize,
+) -> anyhow::Result<Vec<u8>> {
+    let max_length = max_length.min(max_http_response_bytes());
+    let mut body = response.body();
+    let mut bytes = Vec::new();
+    for byte in body.by_ref() {
+        if bytes.len() >= max_length {
+            return Err(AbcError::ResultTooLarge(max_length).into());
+        }
+        bytes.push(byte);
+    }
+    // the body iterator also ends on errors, a truncated body must not pass as complete
+    if let Some(e) = body.error() {
+        return Err(AbcError::HttpResponseError(format!("read body error: {:?}", e)).into());
+    }
+    Ok(bytes)
+}
+
+/// Posts `body` to a non TEA service, only the status code of the response is checked.
+pub fn http_post_notification(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> anyhow::Result<()> {
+    send_post(url, headers, body)?;
+    Ok(())
+}
+
+fn send_post(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> anyhow::Result<rt_offchain::http::Response> {
+    send_request(HttpMethod::Post, url, headers, body)
+}
+
+// sends a request and waits for a response with status code 200
+fn send_request(
+    method: HttpMethod,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> anyhow::Result<rt_offchain::http::Response> {
+    let method = match method {
+        HttpMethod::Post => rt_offchain::http::Method::Post,
+        HttpMethod::Get => rt_offchain::http::Method::Get,
+        HttpMethod::Put => rt_offchain::http::Method::Put,
+    };
+    let request_body = if body.is_empty() { vec![] } else { vec![body] };
 
-    debug::info!("begin to send http post request, url is {}", url);
-    let request = rt_offchain::http::Request::post(url, post_body);
+    debug::info!("begin to send http {:?} request, url is {}", method, url);
+    let mut request = rt_offchain::http::Request::post(url, request_body)
+        .method(method)
+        .add_header("User-Agent", USER_AGENT);
+    for (name, value) in headers {
+        request = request.add_header(name, value);
+    }
     let timeout =
         sp_io::offchain::timestamp().add(rt_offchain::Duration::from_millis(HTTP_POST_TIMEOUT));
     let pending = request
-        .add_header("User-Agent", USER_AGENT)
         .deadline(timeout)
         .send()
         .map_err(|e| match e {
@@ -48,14 +230,26 @@ pub fn http_post(url: &str) -> anyhow::Result<Vec<u8>> {
             response.code
         ));
     }
+    Ok(response)
+}
 
-    let res_body = String::from_utf8(response.body().collect::<Vec<u8>>())?;
-    let response_result: ResponseResult = serde_json::from_str::<ResponseResult>(&res_body)
-        .map_err(|e| AbcError::Common(format!("{}", e)))?;
-    debug::info!(
-        "end of http request ({}), response is {}",
-        url,
-        &response_result.data,
-    );
-    Ok(response_result.data.as_bytes().to_vec())
+/// The configured `LOCAL_STORAGE_MAX_HTTP_RESPONSE_BYTES_KEY`.
+pub fn max_http_response_bytes() -> usize {
+    let max = StorageValueRef::persistent(LOCAL_STORAGE_MAX_HTTP_RESPONSE_BYTES_KEY.as_bytes());
+    match max.get::<u32>() {
+        Some(Some(max)) => max as usize,
+        _ => DEFAULT_MAX_HTTP_RESPONSE_BYTES as usize,
+    }
+}
+
+// header names are case insensitive
+fn response_header(response: &rt_offchain::http::Response, name: &str) -> Option<String> {
+    let mut headers = response.headers().into_iter();
+    while headers.next() {
+        let (key, value) = headers.current();
+        if key.eq_ignore_ascii_case(name) {
+            return Some(value.to_string());
+        }
+    }
+    None
 }
@@ -0,0 +1,69 @@
+use crate::error::AbcError;
+use crate::http::http_post_raw;
+use crate::Cid;
+use alt_serde::Deserialize;
+use frame_support::debug;
+use sp_runtime::offchain::storage::StorageValueRef;
+
+/// Base url of the IPFS HTTP API results are uploaded to, defaults to `DEFAULT_IPFS_API_URL`.
+pub const LOCAL_STORAGE_IPFS_API_URL_KEY: &'static str = "abc-demo::ipfs-api-url";
+pub const DEFAULT_IPFS_API_URL: &'static str = "http://127.0.0.1:5001";
+
+const ADD_ACTION: &'static str = "/api/v0/add";
+const MULTIPART_BOUNDARY: &'static str = "abc-demo-boundary";
+
+#[serde(crate = "alt_serde")]
+#[derive(Deserialize)]
+struct AddResponse {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+/// Uploads `content` to IPFS and returns its CID, `None` if the upload failed.
+pub fn upload(content: &[u8]) -> Option<Cid> {
+    match add_content(content) {
+        Ok(cid) => Some(cid),
+        Err(e) => {
+            debug::error!("upload to ipfs got error: {}", e);
+            None
+        }
+    }
+}
+
+fn add_content(content: &[u8]) -> anyhow::Result<Cid> {
+    let url = format!("{}{}", ipfs_api_url(), ADD_ACTION);
+    let content_type = format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY);
+    let res = http_post_raw(
+        &url,
+        &[("Content-Type", content_type.as_str())],
+        &upload_body(content),
+    )?;
+    let res_str = String::from_utf8(res)?;
+    let response: AddResponse = serde_json::from_str::<AddResponse>(&res_str)
+        .map_err(|e| AbcError::Common(format!("{}", e)))?;
+    Ok(response.hash.into_bytes())
+}
+
+/// Multipart body of an add request carrying `content` as its only file.
+pub fn upload_body(content: &[u8]) -> Vec<u8> {
+    [
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            MULTIPART_BOUNDARY
+        )
+        .as_bytes(),
+        content,
+        format!("\r\n--{}--\r\n", MULTIPART_BOUNDARY).as_bytes(),
+    ]
+    .concat()
+}
+
+fn ipfs_api_url() -> String {
+    let url = StorageValueRef::persistent(LOCAL_STORAGE_IPFS_API_URL_KEY.as_bytes());
+    match url.get::<Vec<u8>>() {
+        Some(Some(url)) if !url.is_empty() => {
+            String::from_utf8(url).unwrap_or_else(|_| DEFAULT_IPFS_API_URL.into())
+        }
+        _ => DEFAULT_IPFS_API_URL.into(),
+    }
+}
@@ -2,21 +2,34 @@
 
 use alt_serde::{Deserialize, Deserializer};
 use codec::{Decode, Encode};
-use core::convert::TryInto;
-use frame_support::traits::{BalanceStatus, ReservableCurrency};
+use frame_support::traits::{BalanceStatus, Currency, ExistenceRequirement, ReservableCurrency};
+use bounded::BoundedVec;
 use frame_support::{
-    debug, decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, traits::Randomness,
-    StorageMap,
+    debug, decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, parameter_types,
+    traits::{Get, Randomness},
+    weights::{Weight, WithPostDispatchInfo},
+    IterableStorageMap, StorageMap, StorageValue,
 };
 use frame_system::{
-    ensure_signed,
-    offchain::{AppCrypto, CreateSignedTransaction, ForAll, SendSignedTransaction, Signer},
+    ensure_none, ensure_root, ensure_signed,
+    offchain::{
+        AppCrypto, CreateSignedTransaction, ForAll, SendSignedTransaction, SignedPayload,
+        Signer, SigningTypes,
+    },
 };
+use pallet_abc_runtime_api::{OffchainConfig, Outcome};
 use sp_core::crypto::{AccountId32, KeyTypeId};
-use sp_io::hashing::blake2_128;
+use sp_io::hashing::{blake2_128, blake2_256, keccak_256};
 use sp_runtime::offchain::storage::StorageValueRef;
-use sp_runtime::traits::IdentifyAccount;
-use sp_runtime::RuntimeAppPublic;
+use sp_runtime::traits::{
+    AccountIdConversion, Hash, IdentifyAccount, One, Saturating, UniqueSaturatedInto, Zero,
+};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionPriority, TransactionSource, TransactionValidity,
+    ValidTransaction,
+};
+use sp_runtime::{ModuleId, RuntimeAppPublic};
+use sp_std::collections::btree_map::BTreeMap;
 use sp_std::prelude::*;
 use sp_std::str;
 use uuid::{Builder, Uuid, Variant, Version};
@@ -27,6 +40,9 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+mod bounded;
+mod migration;
+
 #[cfg(feature = "std")]
 mod delegate;
 #[cfg(feature = "std")]
@@ -34,18 +50,143 @@ mod error;
 #[cfg(feature = "std")]
 mod http;
 #[cfg(feature = "std")]
+mod ipfs;
+#[cfg(feature = "std")]
+mod metrics;
+#[cfg(feature = "std")]
+pub mod rpc;
+#[cfg(feature = "std")]
 mod storage;
 #[cfg(feature = "std")]
 mod task;
+#[cfg(feature = "std")]
+mod webhook;
 
 pub const SERVICE_BASE_URL: &'static str = "http://localhost:8000";
 pub const SERVICE_BASE_URL_PREFIX: &'static str = "http://";
 
 pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"demo");
 pub const TEA_SEND_TASK_TIMEOUT_PERIOD: u64 = 3000;
+pub const METRICS_LOG_INTERVAL: u32 = 100;
+/// Number of blocks between the latency reports of the offchain worker.
+pub const LATENCY_REPORT_INTERVAL: u32 = 100;
+/// Number of samples `ServiceLatency` averages over at most, older samples lose weight as
+/// new ones are reported.
+pub const LATENCY_WINDOW_SAMPLES: u32 = 1000;
+/// Number of blocks an unsigned `init_errand_unsigned` stays valid in the pool, its task
+/// can't be initialized any later.
+pub const UNSIGNED_INIT_LONGEVITY: u64 = MAX_INIT_ERRAND_DELAY as u64;
+
+/// Weight charged for a `begin_task` rejected by validation before any storage write.
+pub const BEGIN_TASK_VALIDATION_WEIGHT: Weight = 1_000;
+
+pub const MAX_FAIL_REASON_LENGTH: usize = 256;
+pub const MAX_CID_LENGTH: usize = 128;
+pub const MAX_RESULT_LENGTH: usize = 1024;
+pub const MAX_ERRANDS_PER_CID: usize = 16;
+pub const MAX_RECONCILE_ACCOUNTS: usize = 64;
+pub const MAX_FEE_MULTIPLIER_PERCENT: u32 = 1_000;
+pub const MAX_PAGE_SIZE: u32 = 100;
+pub const MAX_LABELS: usize = 8;
+pub const MAX_LABEL_LENGTH: usize = 32;
+pub const MAX_ERRANDS_PER_LABEL: usize = 1024;
+pub const MAX_RETENTION_SWEEP_PER_BLOCK: usize = 64;
+pub const MAX_STATUS_BATCH: usize = 256;
+pub const MAX_INIT_ERRAND_DELAY: u32 = 100;
+pub const MAX_BIDS_PER_TICK: usize = 16;
+pub const KIND_TICK_BUDGET: usize = 4;
+pub const KIND_BACKOFF_BLOCKS: u32 = 10;
+pub const RESULT_CACHE_TTL_BLOCKS: u32 = 100;
+pub const DEFAULT_KIND: KindId = 0;
+/// Service action of `DEFAULT_KIND` tasks, which are accepted without registration.
+pub const DEFAULT_KIND_ACTION_PATH: &'static str = "/service";
+pub const MAX_ACTION_PATH_LENGTH: usize = 128;
+pub const MAX_COMMENTS_PER_ERRAND: usize = 32;
+pub const MAX_CONTENT_TYPE_LENGTH: usize = 128;
+/// Content type of results the service sent without one.
+pub const DEFAULT_CONTENT_TYPE: &'static [u8] = b"application/octet-stream";
+pub const MAX_INDEX_BLOCK_RANGE: u32 = 1024;
+pub const MAX_REGISTERED_WORKERS: usize = 64;
+pub const MAX_WEBHOOK_URL_LENGTH: usize = 256;
+pub const MAX_STATUS_LOG_LENGTH: usize = 16;
+pub const MAX_RESULT_VERSIONS: usize = 4;
+pub const MAX_SALT_LENGTH: usize = 64;
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 64;
+pub const MAX_REQUIRED_CONFIRMATIONS: u8 = 8;
+/// Number of errands of the sender `reclaim_stuck_reserves` looks at.
+pub const MAX_RECLAIM_SCAN: usize = 64;
+/// Fail reason of the errands `reclaim_stuck_reserves` expires.
+pub const STUCK_ERRAND_FAIL_REASON: &'static [u8] = b"deadline passed";
+/// Number of blocks a `begin_task` idempotency key is remembered.
+pub const IDEMPOTENCY_KEY_LIFETIME: u32 = 600;
+
+pub const DELEGATION_MESSAGE_PREFIX: &'static [u8] = b"abc-demo::delegation";
+pub const ERRAND_ID_SALT_PREFIX: &'static [u8] = b"abc-demo::errand-id";
+pub const COMPLETION_MESSAGE_PREFIX: &'static [u8] = b"abc-demo::completion";
+pub const ERRAND_ID_MODE: ErrandIdMode = ErrandIdMode::Uuid;
+
+/// How errand ids are derived from their seed.
+///
+/// `Uuid` ids keep 122 random bits of a `blake2_128` seed and are 36 bytes long, `Wide` ids
+/// keep the whole `blake2_256` seed for a far lower collision chance but are 64 bytes long.
+/// Errand ids are part of many storage keys and indexes, so wide ids make every errand cost
+/// more storage. Only change the mode on a fresh chain, ids of both modes don't mix well in
+/// indexers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrandIdMode {
+    /// Lower case hyphenated version 4 UUID of a `blake2_128` seed.
+    Uuid,
+    /// Lower case hex of a `blake2_256` seed.
+    Wide,
+}
+
+impl ErrandIdMode {
+    /// Length in bytes of the ids of this mode.
+    pub fn id_length(&self) -> usize {
+        match self {
+            ErrandIdMode::Uuid => 36,
+            ErrandIdMode::Wide => 64,
+        }
+    }
+}
 
 pub const LOCAL_STORAGE_TASKS_RESULTS_KEY: &'static str = "local-storage::tasks_results";
 pub const LOCAL_STORAGE_TASKS_RESULTS_LOCK: &'static str = "local-storage::tasks_results-lock";
+/// When set to `true` the offchain worker only finishes the work already started, tasks
+/// queued on chain are sent and fetched results submitted, but no delegates are applied, no
+/// claims reaped and no results polled. Unlike a zero `PollInterval` it is node local and
+/// meant for maintenance.
+pub const LOCAL_STORAGE_DRAINING_KEY: &'static str = "abc-demo::draining";
+/// Prefix of the offchain local storage keys of employer webhook urls, followed by the
+/// encoded employer account. Written by `register_webhook` through offchain indexing.
+pub const LOCAL_STORAGE_WEBHOOK_PREFIX: &'static str = "abc-demo::webhook-";
+/// Prefix of the local storage keys holding the height up to which the service of a kind,
+/// suffixed as SCALE encoded kind id, is skipped after a failed send.
+pub const LOCAL_STORAGE_KIND_BACKOFF_PREFIX: &'static str = "abc-demo::kind-backoff-";
+/// Prefix of the local storage keys caching the result of a description cid for
+/// `RESULT_CACHE_TTL_BLOCKS`, followed by the cid. Services are assumed deterministic, a task
+/// of a cached cid completes with the cached result without being sent.
+pub const LOCAL_STORAGE_RESULT_CACHE_PREFIX: &'static str = "abc-demo::result-cache-";
+/// Prefix of the local storage keys marking a task as sent but its errand not yet
+/// initialized, followed by the errand id. A worker restarted between the two skips
+/// sending the task again.
+pub const LOCAL_STORAGE_IN_FLIGHT_PREFIX: &'static str = "abc-demo::in-flight-";
+/// Completions the worker failed to submit, e.g. while the chain was unreachable. They are
+/// submitted again on the next ticks until the submission succeeds or the errand is no
+/// longer processing.
+pub const LOCAL_STORAGE_PENDING_COMPLETIONS_KEY: &'static str = "abc-demo::pending-completions";
+pub const MAX_PENDING_COMPLETIONS: usize = 256;
+/// Prefix of the local storage keys holding the claim expiry an errand, suffixed by its
+/// description cid, was last warned about. A deadline extended later is warned about again.
+pub const LOCAL_STORAGE_EXPIRY_WARNED_PREFIX: &'static str = "abc-demo::expiry-warned-";
+/// Raw public key of the only local key the offchain worker signs with, for nodes holding
+/// several worker keys. All keys sign if it is unset or no registered worker key matches.
+pub const LOCAL_STORAGE_SIGNER_PUBKEY_KEY: &'static str = "abc-demo::signer-pubkey";
+/// Number of shards and the shards among them this worker sends the tasks of, as
+/// `(u32, Vec<u32>)`. Tasks fall into shards by the hash of their errand id, so fleets
+/// configured with the same number of shards split the tasks between them without sending
+/// any twice. All tasks are sent if it is unset.
+pub const LOCAL_STORAGE_TASK_SHARDS_KEY: &'static str = "abc-demo::task-shards";
 
 #[serde(crate = "alt_serde")]
 #[derive(Encode, Decode, Deserialize, Clone)]
@@ -54,6 +195,78 @@ struct ErrandResultInfo {
     #[serde(deserialize_with = "de_string_to_bytes")]
     result_cid: Vec<u8>,
     failed_count: u32,
+    #[serde(default)]
+    status: ErrandResultStatus,
+    #[serde(default, deserialize_with = "de_string_to_bytes")]
+    reason: Vec<u8>,
+    // taken from the `Content-Type` header of the response, not its body
+    #[serde(skip)]
+    content_type: Vec<u8>,
+}
+
+/// Arguments of an `update_errand` waiting in `LOCAL_STORAGE_PENDING_COMPLETIONS_KEY`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+struct PendingCompletion {
+    description_cid: Cid,
+    result: Vec<u8>,
+    content_type: Vec<u8>,
+    result_is_cid: bool,
+    proof: Option<Vec<u8>>,
+}
+
+/// Result status reported by the TEA service in the `status` field of the query response.
+#[serde(crate = "alt_serde")]
+#[serde(rename_all = "lowercase")]
+#[derive(Encode, Decode, Deserialize, Clone, PartialEq, Eq, Debug)]
+enum ErrandResultStatus {
+    Success,
+    Partial,
+    Error,
+}
+
+impl Default for ErrandResultStatus {
+    // services that do not report a status only answer with completed results
+    fn default() -> Self {
+        ErrandResultStatus::Success
+    }
+}
+
+impl ErrandResultStatus {
+    /// Returns the final errand status for this result, or `None` if the errand
+    /// should keep processing (partial results).
+    fn errand_status(&self) -> Option<ErrandStatus> {
+        match self {
+            ErrandResultStatus::Success => Some(ErrandStatus::Done),
+            ErrandResultStatus::Error => Some(ErrandStatus::Failed),
+            ErrandResultStatus::Partial => None,
+        }
+    }
+}
+
+/// Decimal ascii digits of `n`, for building urls and log lines without `format!` in
+/// `no_std` code.
+pub fn u64_to_ascii(mut n: u64) -> Vec<u8> {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(b'0' + (n % 10) as u8);
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    digits
+}
+
+/// Lower case hex ascii digits of `bytes`, the `hex` crate needs `std` for this.
+pub fn bytes_to_hex_ascii(bytes: &[u8]) -> Vec<u8> {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = Vec::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(DIGITS[(byte >> 4) as usize]);
+        hex.push(DIGITS[(byte & 0x0f) as usize]);
+    }
+    hex
 }
 
 pub fn de_string_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
@@ -94,14 +307,176 @@ pub mod crypto {
     }
 }
 
+/// ed25519 variant of `crypto` for runtimes that keep ed25519 offchain keys.
+pub mod ed25519_crypto {
+    use crate::KEY_TYPE;
+    use sp_core::ed25519::Signature as Ed25519Signature;
+    use sp_runtime::{
+        app_crypto::{app_crypto, ed25519},
+        traits::Verify,
+        MultiSignature, MultiSigner,
+    };
+
+    app_crypto!(ed25519, KEY_TYPE);
+
+    pub struct AuthId;
+
+    // implemented for ocw-runtime
+    impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for AuthId {
+        type RuntimeAppPublic = Public;
+        type GenericPublic = sp_core::ed25519::Public;
+        type GenericSignature = sp_core::ed25519::Signature;
+    }
+
+    // implemented for mock runtime in test
+    impl frame_system::offchain::AppCrypto<<Ed25519Signature as Verify>::Signer, Ed25519Signature>
+        for AuthId
+    {
+        type RuntimeAppPublic = Public;
+        type GenericPublic = sp_core::ed25519::Public;
+        type GenericSignature = sp_core::ed25519::Signature;
+    }
+}
+
 pub trait Trait: frame_system::Trait + CreateSignedTransaction<Call<Self>> {
     type Currency: ReservableCurrency<Self::AccountId>;
     type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
 
     type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
     type Call: From<Call<Self>>;
+
+    /// Number of blocks between two polls of errand results.
+    type PollInterval: Get<Self::BlockNumber>;
+
+    /// Source of the randomness errand ids are generated from, usually the randomness
+    /// collective flip pallet. See `BlockHashRandomness` for runtimes without it.
+    type Randomness: Randomness<Self::Hash>;
+
+    /// Number of blocks a completed errand keeps its inline result before only the result
+    /// hash is kept. Zero keeps results forever.
+    type RetentionBlocks: Get<Self::BlockNumber>;
+
+    /// Maximum number of processing errands a worker can have claimed at the same time.
+    type MaxClaimsPerWorker: Get<u32>;
+
+    /// Number of blocks a worker has to complete a claimed errand, afterwards anyone can
+    /// expire the claim and the task is queued again.
+    type ClaimTimeout: Get<Self::BlockNumber>;
+
+    /// Maximum number of blocks ahead of the current one the employer can extend the
+    /// deadline of a claimed errand to.
+    type MaxDeadline: Get<Self::BlockNumber>;
+
+    /// Maximum number of errands, of any status, stored for an account. Finished errands
+    /// can be archived to make room for new ones.
+    type MaxErrandsPerAccount: Get<u32>;
+
+    /// Results longer than this many bytes are uploaded to IPFS by the offchain worker, only
+    /// their CID is stored on chain.
+    type InlineResultThreshold: Get<u32>;
+
+    /// Number of blocks built on top of the height a task is queued at before the offchain
+    /// worker sends it, so tasks of blocks that may still reorg are not acted on.
+    type ConfirmationDepth: Get<Self::BlockNumber>;
+
+    /// Called when an errand completes, `()` if no other pallet needs to know.
+    type OnErrandCompleted: OnErrandCompleted;
+
+    /// Maximum number of tasks queued at a height, sender of further tasks have to wait
+    /// for the next block.
+    type MaxTasksPerBlock: Get<u32>;
+
+    /// Maximum balance reserved for the fees of an account at the same time, so a buggy
+    /// client can't lock all funds of its user.
+    type AccountReserveCap: Get<BalanceOf<Self>>;
+
+    /// Number of blocks an employer has to be inactive before its recovery account can
+    /// claim its errands.
+    type RecoveryDelay: Get<Self::BlockNumber>;
+
+    /// Check results fetched from the service have to pass before they are submitted, `()`
+    /// accepts all of them.
+    type ResultAcceptance: ResultAcceptance;
+
+    /// Whether errands initialized by the offchain worker are only reported by the
+    /// `ErrandsBatchProcessed` event of their block, instead of also emitting
+    /// `TaskDispatched` and `ErrandInited` each.
+    type BatchInitEvents: Get<bool>;
+
+    /// Pool priority of unsigned `init_errand_unsigned` transactions.
+    type UnsignedPriority: Get<TransactionPriority>;
+
+    /// Number of blocks before the claim of a processing errand expires its employer is
+    /// warned by the offchain worker. Zero disables the warnings.
+    type ExpiryWarningBlocks: Get<Self::BlockNumber>;
+
+    /// Id of the pallet, listed errands are claimed by sub-accounts of it until they are
+    /// bought.
+    type ModuleId: Get<ModuleId>;
+
+    /// Algorithm results are hashed with for `ErrandResultHashes` and completion proofs.
+    type ResultHashAlgo: Get<HashAlgo>;
+
+    /// Milliseconds the offchain worker delays sending the tasks of a tick by at most, so
+    /// the workers of many validators don't hit the service at once. Zero sends them right
+    /// away.
+    type MaxSendJitter: Get<u64>;
+
+    /// Blocks the task of an expired claim is queued again after by at most, derived from
+    /// its errand id so tasks whose claims expired together don't all retry at the same
+    /// height. Zero queues them again right away.
+    type MaxRetryJitter: Get<u32>;
+
+    /// When task fees are reserved, see `ReserveMode`.
+    type ReserveMode: Get<ReserveMode>;
+}
+
+parameter_types! {
+    pub const MaxErrandsPerCid: u32 = MAX_ERRANDS_PER_CID as u32;
+}
+
+/// Lets other pallets react to completed errands.
+pub trait OnErrandCompleted {
+    /// Called once errand `errand_id` is done with `result` stored on chain, which is the
+    /// IPFS CID of the result for results above `InlineResultThreshold`.
+    fn on_completed(errand_id: &[u8], result: &[u8]);
 }
 
+impl OnErrandCompleted for () {
+    fn on_completed(_errand_id: &[u8], _result: &[u8]) {}
+}
+
+/// Lets operators reject results failing a basic check, e.g. empty ones.
+pub trait ResultAcceptance {
+    /// Whether `result` fetched by the offchain worker can complete its errand. Rejected
+    /// results are dropped and their errands stay processing, so the result is fetched
+    /// again at the next poll.
+    fn result_acceptable(result: &[u8]) -> bool;
+}
+
+impl ResultAcceptance for () {
+    fn result_acceptable(_result: &[u8]) -> bool {
+        true
+    }
+}
+
+/// Randomness derived from the parent block hash, for demo runtimes without a randomness
+/// pallet.
+///
+/// WARNING: block authors know and influence the parent hash, so the output is predictable
+/// and must not be used where security depends on it.
+pub struct BlockHashRandomness<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: frame_system::Trait> Randomness<T::Hash> for BlockHashRandomness<T> {
+    fn random(subject: &[u8]) -> T::Hash {
+        let parent_hash = frame_system::Module::<T>::parent_hash();
+        (parent_hash, subject).using_encoded(T::Hashing::hash)
+    }
+}
+
+type BalanceOf<T> =
+    <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
+
 type ClientAccountId = Vec<u8>;
 type SenderAccountId = Vec<u8>;
 
@@ -111,13 +486,137 @@ type Cid = Vec<u8>;
 
 type NetAddress = Vec<u8>;
 
+type Label = Vec<u8>;
+
+/// Kind of an errand, tasks of different kinds may be served by different services.
+type KindId = u32;
+
 #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
 enum ErrandStatus {
     Processing,
     Done,
+    Failed,
+    /// Workers of a quorum errand submitted different results.
+    Disputed,
+}
+
+impl Default for ErrandStatus {
+    fn default() -> Self {
+        ErrandStatus::Processing
+    }
+}
+
+impl ErrandStatus {
+    /// Status code reported by the runtime api, `0` is reserved for unknown errands.
+    fn code(&self) -> u8 {
+        match self {
+            ErrandStatus::Processing => 1,
+            ErrandStatus::Done => 2,
+            ErrandStatus::Failed => 3,
+            ErrandStatus::Disputed => 4,
+        }
+    }
+}
+
+/// HTTP method tasks of a kind are sent to their service with.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HttpMethod {
+    Post,
+    /// The content stays in the url, tasks too long for it fail to send.
+    Get,
+    Put,
+}
+
+impl Default for HttpMethod {
+    fn default() -> Self {
+        HttpMethod::Post
+    }
+}
+
+/// Hash algorithm of result commitments, keccak256 suits clients verifying results on EVM
+/// chains.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HashAlgo {
+    Blake2_256,
+    Keccak256,
+}
+
+impl Default for HashAlgo {
+    fn default() -> Self {
+        HashAlgo::Blake2_256
+    }
+}
+
+impl HashAlgo {
+    pub fn hash(&self, data: &[u8]) -> [u8; 32] {
+        match self {
+            HashAlgo::Blake2_256 => blake2_256(data),
+            HashAlgo::Keccak256 => keccak_256(data),
+        }
+    }
+}
+
+/// When the fee of a task is reserved from its client.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReserveMode {
+    /// In `begin_task`, tasks of clients unable to pay are never queued.
+    OnSubmit,
+    /// When its errand is initialized, tasks of clients unable to pay by then are dropped.
+    OnDispatch,
+}
+
+impl Default for ReserveMode {
+    fn default() -> Self {
+        ReserveMode::OnSubmit
+    }
 }
 
+/// Payload of `init_errand_unsigned`, signed with the offchain key of the client sender.
 #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct InitErrandPayload<Public, AccountId> {
+    pub public: Public,
+    pub client: AccountId,
+    pub errand_id: ErrandId,
+    pub description_cid: Cid,
+}
+
+impl<T: SigningTypes> SignedPayload<T> for InitErrandPayload<T::Public, T::AccountId> {
+    fn public(&self) -> T::Public {
+        self.public.clone()
+    }
+}
+
+/// Metadata of a registered errand kind.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct KindInfo {
+    /// Service action tasks of the kind are sent to, e.g. `/service`.
+    pub action_path: Vec<u8>,
+    /// Fee of tasks of the kind queued with a zero fee.
+    pub default_fee: u32,
+    /// Percent of the fee of its tasks the kind adds on top of it, e.g. 50 makes its tasks
+    /// cost 1.5 times their fee. At most `MAX_FEE_MULTIPLIER_PERCENT`.
+    pub fee_multiplier_percent: u32,
+    pub http_method: HttpMethod,
+    /// Result format tasks of the kind ask the service for in their `Accept` header, e.g.
+    /// `application/json`. Empty sends no `Accept` header.
+    pub accept: Vec<u8>,
+}
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+enum Releases {
+    V1_0_0,
+    V2_0_0,
+    V3_0_0,
+    V4_0_0,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1_0_0
+    }
+}
+
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq, Debug)]
 pub struct Errand {
     account_id: ClientAccountId,
     errand_id: ErrandId,
@@ -126,6 +625,18 @@ pub struct Errand {
     result: Cid,
 }
 
+impl Errand {
+    /// Creates a processing errand without result.
+    fn new(account_id: ClientAccountId, errand_id: ErrandId, description_cid: Cid) -> Self {
+        Errand {
+            account_id,
+            errand_id,
+            description_cid,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
 pub struct TaskInfo {
     client: ClientAccountId,
@@ -137,8 +648,10 @@ pub struct TaskInfo {
 
 decl_storage! {
     trait Store for Module<T: Trait> as Abc {
+        // Maps keyed by user supplied data (cids, errand ids) must use `blake2_128_concat`,
+        // `twox_64_concat` is only safe for keys the caller can't choose freely.
         Errands get(fn errand):
-            map hasher(twox_64_concat) Cid => Option<Errand>;
+            map hasher(blake2_128_concat) Cid => Option<Errand>;
 
         Clients get(fn clients): map hasher(blake2_128_concat) T::AccountId => bool;
 
@@ -157,13 +670,220 @@ decl_storage! {
         ClientDelegator get(fn client_delegator):
             map hasher(blake2_128_concat) T::AccountId => T::AccountId;
 
+        // Tasks queued at the given height. Tasks of a block retracted by a reorg vanish
+        // with it and are not queued again, their senders have to submit them again. The
+        // offchain worker only acts on what the current state still holds.
         Tasks get(fn tasks):
-            map hasher(blake2_128_concat) T::BlockNumber => Vec<TaskInfo>;
+            map hasher(blake2_128_concat) T::BlockNumber => BoundedVec<TaskInfo, T::MaxTasksPerBlock>;
+
+        // Sum of the lengths of all `Tasks` entries, kept up to date on every change so it
+        // can be read without iterating them.
+        PendingTaskCount get(fn total_pending_tasks): u64;
+
+        // Description cids of errands by errand id.
+        ErrandCids get(fn errand_cid):
+            map hasher(blake2_128_concat) ErrandId => Option<Cid>;
+
+        // Last progress in percent the worker of an errand reported and the part of the
+        // task fee released to it so far.
+        ErrandProgress get(fn errand_progress):
+            map hasher(blake2_128_concat) ErrandId => (u8, u32);
+
+        // Task fee of an errand as queued, reserved from its client when the task is queued
+        // or its errand initialized depending on `ReserveMode`. Removed once the errand is
+        // done, failed or disputed and its fee released.
+        ErrandFees get(fn errand_fee):
+            map hasher(blake2_128_concat) ErrandId => u32;
+
+        // Kinds of queued tasks and their errands by errand id.
+        TaskKinds get(fn task_kind):
+            map hasher(blake2_128_concat) ErrandId => KindId;
+
+        // Height of `Tasks` the task of an errand id is queued at.
+        TaskLocation get(fn task_location):
+            map hasher(blake2_128_concat) ErrandId => Option<T::BlockNumber>;
+
+        // Errand ids of queued tasks by bid, highest fee first and in queueing order among
+        // equal fees. The offchain worker sends tasks in this order across all heights.
+        PendingBids get(fn pending_bids): Vec<(u32, ErrandId)>;
 
         ClientsApplys get(fn delegate_accounts):
             map hasher(blake2_128_concat) T::BlockNumber => Vec<(T::AccountId, T::AccountId)>;
 
         ProcessingErrands get(fn processing_errands): Vec<Cid>;
+
+        // Accounts whose local keys the offchain worker signs transactions with, other local
+        // keys never dispatch.
+        RegisteredWorkers get(fn registered_workers): Vec<T::AccountId>;
+
+        // Kinds governance halted, no new tasks of them are queued while errands of them
+        // already queued or processing still complete.
+        PausedKinds get(fn paused_kinds): Vec<KindId>;
+
+        KindRegistry get(fn kind_info): map hasher(twox_64_concat) KindId => Option<KindInfo>;
+
+        ErrandFailReasons get(fn errand_fail_reason):
+            map hasher(blake2_128_concat) Cid => Vec<u8>;
+
+        // Content types of the results of done errands, so clients know how to render them.
+        ErrandContentTypes get(fn errand_content_type):
+            map hasher(blake2_128_concat) Cid => Vec<u8>;
+
+        // Done errands whose `result` is the IPFS CID of the result instead of the result.
+        ResultIsCid get(fn result_is_cid):
+            map hasher(blake2_128_concat) Cid => bool;
+
+        // Worker and its signature over `completion_message` of done errands completed with
+        // a proof, so anyone can check the worker produced the result.
+        CompletionProofs get(fn completion_proof):
+            map hasher(blake2_128_concat) Cid => Option<(T::AccountId, Vec<u8>)>;
+
+        ErrandOutcomes get(fn errand_outcome):
+            map hasher(blake2_128_concat) Cid => Option<Outcome>;
+
+        ErrandResultHashes get(fn errand_result_hash):
+            map hasher(blake2_128_concat) Cid => Option<T::Hash>;
+
+        // Algorithm the result hash of a done errand was computed with, results completed
+        // before it was recorded were hashed with blake2_256.
+        ResultHashAlgos get(fn result_hash_algo):
+            map hasher(blake2_128_concat) Cid => HashAlgo;
+
+        // Number of registered workers that have to submit the same result before an errand
+        // is done, zero and one let the errand updater complete it alone.
+        RequiredConfirmations get(fn required_confirmations):
+            map hasher(blake2_128_concat) Cid => u8;
+
+        // Workers and result hashes submitted so far for a quorum errand.
+        ResultSubmissions get(fn result_submissions):
+            map hasher(blake2_128_concat) Cid => Vec<(T::AccountId, T::Hash)>;
+
+        // Cids of errands whose inline result is cleared at the given height.
+        ResultRetentionQueue get(fn result_retention_queue):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<Cid>;
+
+        // The worker which claimed a processing errand by initializing it.
+        ErrandWorker get(fn errand_worker):
+            map hasher(blake2_128_concat) Cid => Option<T::AccountId>;
+
+        // The only worker which can claim and complete an errand, chosen by its sender.
+        AssignedWorkers get(fn assigned_worker):
+            map hasher(blake2_128_concat) ErrandId => Option<T::AccountId>;
+
+        // Fees of queued tasks to reserve when their errands are initialized, only tasks
+        // queued in `ReserveMode::OnDispatch` have one.
+        UnreservedTaskFees get(fn unreserved_task_fee):
+            map hasher(blake2_128_concat) ErrandId => Option<u32>;
+
+        // Rolling average milliseconds of the task requests timed by workers and the number
+        // of samples it stands for, see `rolling_latency`.
+        ServiceLatency get(fn service_latency): (u32, u32);
+
+        // Seller and price of listed errands, whose claim is held by `listing_account`
+        // until they are bought.
+        ErrandListings get(fn errand_listing):
+            map hasher(blake2_128_concat) Cid => Option<(T::AccountId, BalanceOf<T>)>;
+
+        ActiveClaims get(fn active_claims):
+            map hasher(blake2_128_concat) T::AccountId => u32;
+
+        ClaimExpiry get(fn claim_expiry):
+            map hasher(blake2_128_concat) Cid => Option<T::BlockNumber>;
+
+        // Ids of the errands created at the given height, in creation order.
+        ErrandsCreatedAt get(fn errands_created_at):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<ErrandId>;
+
+        // Id of the errand queued by the extrinsic at the given height and index, the
+        // extrinsic hash isn't known to the runtime.
+        ErrandByExtrinsic get(fn errand_by_extrinsic):
+            double_map hasher(twox_64_concat) T::BlockNumber, hasher(twox_64_concat) u32
+            => Option<ErrandId>;
+
+        ErrandsByCid get(fn errands_by_cid):
+            map hasher(blake2_128_concat) Cid => BoundedVec<ErrandId, MaxErrandsPerCid>;
+
+        // Proof signed by the client (employer) authorizing a worker to send its tasks.
+        Delegations get(fn delegations):
+            double_map hasher(blake2_128_concat) T::AccountId, hasher(blake2_128_concat) T::AccountId
+            => Option<Vec<u8>>;
+
+        // Fees this pallet has reserved and not yet released, per client account.
+        ClientReserved get(fn client_reserved):
+            map hasher(blake2_128_concat) T::AccountId => BalanceOf<T>;
+
+        // Sum of all `ClientReserved` balances.
+        TotalReserved get(fn total_reserved): BalanceOf<T>;
+
+        AccountErrands get(fn account_errands):
+            map hasher(blake2_128_concat) T::AccountId => Vec<Cid>;
+
+        // Account allowed to claim the errands of an employer inactive for `RecoveryDelay`.
+        RecoveryAccounts get(fn recovery_account):
+            map hasher(blake2_128_concat) T::AccountId => Option<T::AccountId>;
+
+        // Last height an employer managed its errands at.
+        LastSeen get(fn last_seen):
+            map hasher(blake2_128_concat) T::AccountId => T::BlockNumber;
+
+        // Number of errands `init_errand` initialized in the current block, reported and
+        // reset when the block is finalized.
+        ErrandsInitedInBlock get(fn errands_inited_in_block): u32;
+
+        ErrandLabels get(fn errand_labels):
+            map hasher(blake2_128_concat) ErrandId => Vec<Label>;
+
+        ErrandsByLabel get(fn errands_by_label):
+            map hasher(blake2_128_concat) Label => Vec<ErrandId>;
+
+        // Errands whose result is left out of the public runtime api until the employer
+        // reveals it. The result itself remains in `Errands`.
+        PrivateErrands get(fn is_private_errand):
+            map hasher(blake2_128_concat) Cid => bool;
+
+        // Errands whose inline result the employer keeps past `RetentionBlocks`.
+        PinnedErrands get(fn is_pinned):
+            map hasher(blake2_128_concat) Cid => bool;
+
+        // Comment cids of the employer and the worker of an errand, in adding order.
+        ErrandComments get(fn errand_comments):
+            map hasher(blake2_128_concat) Cid => Vec<(T::AccountId, Cid, T::BlockNumber)>;
+
+        // Hashes of the webhook urls employers are notified at when their errands complete,
+        // the urls themselves are only kept in offchain local storage.
+        WebhookHashes get(fn webhook_hash):
+            map hasher(blake2_128_concat) T::AccountId => Option<T::Hash>;
+
+        // Employers and cids of the errands completed at the given height, for the offchain
+        // worker of that height to notify.
+        WebhookNotifications get(fn webhook_notifications):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<(T::AccountId, Cid)>;
+
+        // Status codes of an errand with the heights it entered them, in transition order.
+        StatusLog get(fn status_log):
+            map hasher(blake2_128_concat) ErrandId => Vec<(u8, T::BlockNumber)>;
+
+        // Results of the errands of a cid with their completion heights, oldest first, so
+        // results of resubmitted errands don't overwrite earlier ones. Kept when archived.
+        ResultVersions get(fn result_versions):
+            map hasher(blake2_128_concat) Cid => Vec<(T::BlockNumber, Vec<u8>)>;
+
+        // Ids of the errands queued by `begin_task_with_salt`.
+        SaltedErrandIds get(fn is_salted_errand_id):
+            map hasher(blake2_128_concat) ErrandId => bool;
+
+        // Errand ids of the tasks queued with an idempotency key, per sender and key.
+        IdempotencyKeys get(fn idempotency_key):
+            double_map hasher(blake2_128_concat) T::AccountId, hasher(blake2_128_concat) Vec<u8>
+            => Option<ErrandId>;
+
+        // Idempotency keys forgotten at the given height.
+        IdempotencyKeyExpiry get(fn idempotency_key_expiry):
+            map hasher(twox_64_concat) T::BlockNumber => Vec<(T::AccountId, Vec<u8>)>;
+
+        // New chains start at the latest release, their storage needs no migration. Bump it
+        // along with each new migration.
+        StorageVersion build(|_| Releases::V4_0_0): Releases;
     }
 }
 
@@ -171,12 +891,64 @@ decl_event!(
     pub enum Event<T>
     where
         AccountId = <T as frame_system::Trait>::AccountId,
+        Balance = BalanceOf<T>,
+        BlockNumber = <T as frame_system::Trait>::BlockNumber,
     {
         DelegateRequested(AccountId, AccountId),
         DelegateUpdated(AccountId),
         TaskBegan(AccountId, Vec<u8>, AccountId),
         ErrandInited(AccountId, Vec<u8>),
+        TaskDispatched(Vec<u8>),
         ErrandUpdated(Vec<u8>, Vec<u8>),
+        ErrandFailed(Vec<u8>, Vec<u8>),
+        ReservesReconciled(AccountId, Balance),
+        DelegationRegistered(AccountId, AccountId),
+        ErrandResultRevealed(AccountId, Vec<u8>),
+        CommentAdded(AccountId, Vec<u8>, Vec<u8>),
+        // Numbers of flushed and failed tasks.
+        TasksFlushed(u32, u32),
+        ClaimExpired(AccountId, Vec<u8>),
+        FeeReserved(AccountId, Balance),
+        FeeRefunded(AccountId, Balance),
+        // Client, receiver and amount of a fee paid from its reserve.
+        FeeSettled(AccountId, AccountId, Balance),
+        // Sender and errand id of a task already queued with the same idempotency key.
+        TaskDeduplicated(AccountId, Vec<u8>),
+        // Errand id and the base fee, priority surcharge and kind multiplier component of the
+        // fee reserved for its task, see `fee_breakdown`.
+        FeeBreakdown(Vec<u8>, u32, u32, u32),
+        WorkerRegistered(AccountId),
+        ErrandArchived(AccountId, Vec<u8>),
+        TaskCancelled(AccountId, Vec<u8>),
+        WebhookRegistered(AccountId),
+        // Employer, errand cid and the new deadline of its claim.
+        DeadlineExtended(AccountId, Vec<u8>, BlockNumber),
+        KindPaused(u32),
+        KindResumed(u32),
+        KindRegistered(u32),
+        // Worker and errand cid of a result submitted before its errand reached quorum.
+        ResultSubmitted(AccountId, Vec<u8>),
+        ErrandDisputed(Vec<u8>),
+        RecoveryAccountSet(AccountId, Option<AccountId>),
+        // Inactive employer, its recovery account and the number of errands moved.
+        ErrandsRecovered(AccountId, AccountId, u32),
+        // Height and number of errands the offchain worker initialized at it.
+        ErrandsBatchProcessed(BlockNumber, u32),
+        // Worker, errand id and the progress in percent it reported.
+        ProgressReported(AccountId, Vec<u8>, u8),
+        // Seller, errand id and price of a listed errand.
+        ErrandListed(AccountId, Vec<u8>, Balance),
+        // Buyer, errand id and price of a bought errand listing.
+        ErrandListingBought(AccountId, Vec<u8>, Balance),
+        ErrandPinned(AccountId, Vec<u8>),
+        ErrandUnpinned(AccountId, Vec<u8>),
+        // Employer and the fees refunded for its expired stuck errands.
+        StuckReservesReclaimed(AccountId, Balance),
+        // Worker, average milliseconds and number of the task requests it timed.
+        LatencyReported(AccountId, u32, u32),
+        // Client and errand id of a task dropped when its errand was initialized, as its fee
+        // couldn't be reserved.
+        TaskDroppedUnfunded(AccountId, Vec<u8>),
     }
 );
 
@@ -203,6 +975,58 @@ decl_error! {
         NoRightToUpdateErrand,
         AccountId32ConvertionError,
         LocalStorageError,
+        FailReasonTooLong,
+        TooManyErrandsForCid,
+        TooManyAccountsToReconcile,
+        MissingDelegation,
+        InvalidDelegationProof,
+        TooManyLabels,
+        LabelTooLong,
+        TooManyErrandsForLabel,
+        NotErrandOwner,
+        UnknownErrand,
+        EmployerMismatch,
+        CidTooLong,
+        ResultTooLong,
+        NoRightToComment,
+        TooManyComments,
+        NoTasksToFlush,
+        TooManyClaims,
+        ErrandNotClaimed,
+        ClaimNotExpired,
+        IdempotencyKeyTooLong,
+        InvalidOutcome,
+        ContentTypeTooLong,
+        DeadlinePassed,
+        WorkerAlreadyRegistered,
+        TooManyWorkers,
+        TooManyErrandsForAccount,
+        ErrandStillProcessing,
+        SaltTooLong,
+        DuplicateErrandId,
+        TaskNotQueued,
+        NoRightToCancelTask,
+        InvalidWebhookUrl,
+        ResultAboveInlineThreshold,
+        TooManyTasksInBlock,
+        ReserveCapExceeded,
+        InvalidCompletionProof,
+        KindPaused,
+        KindNotPaused,
+        UnknownKind,
+        InvalidKindInfo,
+        InvalidConfirmations,
+        NotRegisteredWorker,
+        ResultAlreadySubmitted,
+        NotRecoveryAccount,
+        NotErrandWorker,
+        InvalidProgress,
+        AccountStillActive,
+        ErrandNotListed,
+        ErrandNotPinned,
+        NotAssignedWorker,
+        NoStuckErrands,
+        NoLatencySamples,
     }
 }
 
@@ -210,8 +1034,64 @@ decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
         type Error = Error<T>;
 
+        const PollInterval: T::BlockNumber = T::PollInterval::get();
+
+        const RetentionBlocks: T::BlockNumber = T::RetentionBlocks::get();
+
+        const MaxClaimsPerWorker: u32 = T::MaxClaimsPerWorker::get();
+
+        const ClaimTimeout: T::BlockNumber = T::ClaimTimeout::get();
+
+        const MaxDeadline: T::BlockNumber = T::MaxDeadline::get();
+
+        const MaxErrandsPerAccount: u32 = T::MaxErrandsPerAccount::get();
+
+        const InlineResultThreshold: u32 = T::InlineResultThreshold::get();
+
+        const ConfirmationDepth: T::BlockNumber = T::ConfirmationDepth::get();
+
+        const AccountReserveCap: BalanceOf<T> = T::AccountReserveCap::get();
+
+        const RecoveryDelay: T::BlockNumber = T::RecoveryDelay::get();
+
+        const BatchInitEvents: bool = T::BatchInitEvents::get();
+
+        const UnsignedPriority: TransactionPriority = T::UnsignedPriority::get();
+
+        const ExpiryWarningBlocks: T::BlockNumber = T::ExpiryWarningBlocks::get();
+
+        const ModuleId: ModuleId = T::ModuleId::get();
+
+        const ResultHashAlgo: HashAlgo = T::ResultHashAlgo::get();
+
+        const MaxSendJitter: u64 = T::MaxSendJitter::get();
+
+        const MaxRetryJitter: u32 = T::MaxRetryJitter::get();
+
+        const ReserveMode: ReserveMode = T::ReserveMode::get();
+
         fn deposit_event() = default;
 
+        fn on_runtime_upgrade() -> Weight {
+            migration::migrate_errands_to_blake2::<T>()
+                .saturating_add(migration::count_pending_tasks::<T>())
+                .saturating_add(migration::record_errand_fees::<T>())
+        }
+
+        fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            Self::sweep_retained_results(block_number)
+                .saturating_add(Self::sweep_idempotency_keys(block_number))
+                .saturating_add(Self::clear_webhook_notifications(block_number))
+                .saturating_add(Self::prune_stale_bids(block_number))
+        }
+
+        fn on_finalize(block_number: T::BlockNumber) {
+            let count = ErrandsInitedInBlock::take();
+            if count > 0 {
+                Self::deposit_event(RawEvent::ErrandsBatchProcessed(block_number, count));
+            }
+        }
+
         #[weight = 10_000]
         pub fn request_delegate(origin,
             client: T::AccountId,
@@ -225,7 +1105,7 @@ decl_module! {
 
             // reserve fee for commit errand delegator
             ensure!(fee > 0, Error::<T>::InsufficientFee);
-            T::Currency::reserve(&client, fee.into())?;
+            Self::reserve_fee(&client, fee)?;
             ClientDelegateFee::<T>::insert(&client, fee);
 
             let block_number = frame_system::Module::<T>::block_number();
@@ -262,54 +1142,138 @@ decl_module! {
             Ok(())
         }
 
+        /// Queues task `description_cid` of `client`. A task queued again by the same sender
+        /// with the same `idempotency_key` within `IDEMPOTENCY_KEY_LIFETIME` blocks is not
+        /// queued twice, `TaskDeduplicated` reports the errand id of the first one instead.
+        /// Only `assigned_worker`, if given, can claim and complete the errand.
         #[weight = 10_000]
         pub fn begin_task(origin,
             client: T::AccountId,
             description_cid: Cid,
             fee: u32,
-            ) -> dispatch::DispatchResult {
+            labels: Vec<Label>,
+            private: bool,
+            idempotency_key: Option<Vec<u8>>,
+            kind: KindId,
+            assigned_worker: Option<T::AccountId>,
+            ) -> dispatch::DispatchResultWithPostInfo {
             let sender = ensure_signed(origin)?;
-
-            ensure!(Clients::<T>::contains_key(&client), Error::<T>::ClientNotExist);
-            ensure!(Clients::<T>::get(&client), Error::<T>::ClientNotReady);
-            ensure!(!Errands::contains_key(&description_cid), Error::<T>::ErrandAlreadyExecuted);
-            ensure!(ClientSender::<T>::contains_key(&client), Error::<T>::ClientSenderNotExist);
-            ensure!(sender == ClientSender::<T>::get(&client), Error::<T>::ClientSenderNotExist);
-            ensure!(ClientDelegator::<T>::contains_key(&client), Error::<T>::ClientDelegatorNotExist);
-
+            let kind_info = Self::registered_kind(kind)
+                .ok_or(Error::<T>::UnknownKind.with_weight(BEGIN_TASK_VALIDATION_WEIGHT))?;
+            ensure!(
+                !PausedKinds::get().contains(&kind),
+                Error::<T>::KindPaused.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+            );
+            let (base, surcharge, kind_component) = Self::fee_breakdown(fee, &kind_info);
+            let fee = base + surcharge + kind_component;
+
+            if let Some(key) = idempotency_key.as_ref() {
+                Self::ensure_bounded(key, MAX_IDEMPOTENCY_KEY_LENGTH as u32, Error::<T>::IdempotencyKeyTooLong)
+                    .map_err(|e| e.with_weight(BEGIN_TASK_VALIDATION_WEIGHT))?;
+                if let Some(errand_id) = IdempotencyKeys::<T>::get(&sender, key) {
+                    Self::deposit_event(RawEvent::TaskDeduplicated(sender, errand_id));
+                    return Ok(().into());
+                }
+            }
             let errand_id = Self::generate_errand_id(&sender);
-            let task_info = TaskInfo {
-                client: client.encode(),
-                sender: sender.encode(),
-                description_cid: description_cid.clone(),
-                errand_id,
+            Self::queue_new_task(
+                sender.clone(),
+                client,
+                description_cid,
                 fee,
-            };
-
-            let block_number = frame_system::Module::<T>::block_number();
-            if Tasks::<T>::contains_key(&block_number) {
-                let mut task_array = Tasks::<T>::take(&block_number);
-
-                for task in task_array.iter() {
-                    ensure!(!task.description_cid.eq(&description_cid), Error::<T>::ErrandAlreadyExecuted);
-                }
-                task_array.push(task_info);
-                // reserve fee for commit errand delegator
-                ensure!(fee > 0, Error::<T>::InsufficientFee);
-                T::Currency::reserve(&client, fee.into())?;
-                ClientTaskFee::<T>::insert(&client, fee);
+                labels,
+                private,
+                errand_id.clone(),
+            )?;
+            Self::deposit_event(RawEvent::FeeBreakdown(
+                errand_id.clone(),
+                base,
+                surcharge,
+                kind_component,
+            ));
+            TaskKinds::insert(&errand_id, kind);
+            if let Some(worker) = assigned_worker {
+                AssignedWorkers::<T>::insert(&errand_id, worker);
+            }
+            if let Some(key) = idempotency_key {
+                IdempotencyKeys::<T>::insert(&sender, &key, &errand_id);
+                IdempotencyKeyExpiry::<T>::append(
+                    &Self::future_block(IDEMPOTENCY_KEY_LIFETIME),
+                    (sender, key),
+                );
+            }
+            Ok(().into())
+        }
 
-                Tasks::<T>::insert(&block_number, task_array);
-            } else {
-                // reserve fee for commit errand delegator
-                ensure!(fee > 0, Error::<T>::InsufficientFee);
-                T::Currency::reserve(&client, fee.into())?;
-                ClientTaskFee::<T>::insert(&client, fee);
+        /// Same as `begin_task` with the errand id derived from the sender and `salt`, see
+        /// `derive_errand_id`. Each salt can only be used once per sender.
+        #[weight = 10_000]
+        pub fn begin_task_with_salt(origin,
+            client: T::AccountId,
+            description_cid: Cid,
+            fee: u32,
+            labels: Vec<Label>,
+            private: bool,
+            salt: Vec<u8>,
+            kind: KindId,
+            ) -> dispatch::DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            let kind_info = Self::registered_kind(kind)
+                .ok_or(Error::<T>::UnknownKind.with_weight(BEGIN_TASK_VALIDATION_WEIGHT))?;
+            ensure!(
+                !PausedKinds::get().contains(&kind),
+                Error::<T>::KindPaused.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+            );
+            let (base, surcharge, kind_component) = Self::fee_breakdown(fee, &kind_info);
+            let fee = base + surcharge + kind_component;
+            Self::ensure_bounded(&salt, MAX_SALT_LENGTH as u32, Error::<T>::SaltTooLong)
+                .map_err(|e| e.with_weight(BEGIN_TASK_VALIDATION_WEIGHT))?;
+            let errand_id = Self::derive_errand_id(&sender, &salt);
+            ensure!(
+                !SaltedErrandIds::contains_key(&errand_id),
+                Error::<T>::DuplicateErrandId.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+            );
+
+            Self::queue_new_task(sender, client, description_cid, fee, labels, private, errand_id.clone())?;
+            Self::deposit_event(RawEvent::FeeBreakdown(
+                errand_id.clone(),
+                base,
+                surcharge,
+                kind_component,
+            ));
+            TaskKinds::insert(&errand_id, kind);
+            SaltedErrandIds::insert(&errand_id, true);
+            Ok(().into())
+        }
 
-                Tasks::<T>::insert(&block_number, vec![task_info]);
+        /// Removes the queued task of `errand_id` before its errand is initialized and refunds
+        /// its fee. The sender and the client of the task can cancel it.
+        #[weight = 10_000]
+        pub fn cancel_task_by_id(origin,
+            errand_id: ErrandId,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let block_number = TaskLocation::<T>::get(&errand_id).ok_or(Error::<T>::TaskNotQueued)?;
+            let tasks = Tasks::<T>::get(&block_number);
+            let index = tasks
+                .iter()
+                .position(|task| task.errand_id.eq(&errand_id))
+                .ok_or(Error::<T>::TaskNotQueued)?;
+            let task = &tasks[index];
+            ensure!(
+                task.sender == sender.encode() || task.client == sender.encode(),
+                Error::<T>::NoRightToCancelTask
+            );
+            ensure!(!Errands::contains_key(&task.description_cid), Error::<T>::ErrandAlreadyExecuted);
+            let client = Self::bytes_to_account(&mut task.client.as_slice())?;
+
+            let task = Self::unqueue_task(&block_number, tasks, index);
+            // fees of tasks queued in `ReserveMode::OnDispatch` were never reserved
+            if UnreservedTaskFees::take(&errand_id).is_none() {
+                Self::unreserve_fee(&client, task.fee);
             }
 
-            Self::deposit_event(RawEvent::TaskBegan(client, description_cid, sender));
+            Self::deposit_event(RawEvent::TaskCancelled(sender, errand_id));
             Ok(())
         }
 
@@ -321,248 +1285,2113 @@ decl_module! {
             ) -> dispatch::DispatchResult {
 
             let sender = ensure_signed(origin)?;
-            ensure!(ClientSender::<T>::contains_key(&client), Error::<T>::ClientSenderNotExist);
-            ensure!(sender == ClientSender::<T>::get(&client), Error::<T>::ClientSenderNotExist);
-
-            let errand = Errand {
-                account_id: client.encode(),
-                errand_id: errand_id.clone(),
-                description_cid: description_cid.clone(),
-                status: ErrandStatus::Processing,
-                result: Vec::new(),
-            };
-            Errands::insert(description_cid.clone(), errand);
-            Self::add_processing(description_cid.clone());
-
-            Self::deposit_event(RawEvent::ErrandInited(client, description_cid));
-            Ok(())
+            Self::init_errand_of(sender, client, errand_id, description_cid)
         }
 
+        /// Same as `init_errand` without a transaction fee, the client sender signs `payload`
+        /// with its offchain key instead. Only one transaction per errand id is accepted by
+        /// the pool, see `validate_unsigned`.
         #[weight = 10_000]
-        pub fn update_errand(origin,
-            description_cid: Cid,
-            result: Vec<u8>,
+        pub fn init_errand_unsigned(origin,
+            payload: InitErrandPayload<T::Public, T::AccountId>,
+            _signature: T::Signature,
             ) -> dispatch::DispatchResult {
-            let sender = ensure_signed(origin)?;
-            ensure!(Errands::contains_key(&description_cid), Error::<T>::ErrandTaskNotExist);
-            let sender_account: AccountId32 = Self::account_to_bytes(&sender)?;
-            let accounts: Vec<AccountId32> = vec![sender_account];
-
-            #[cfg(feature = "std")]
-            ensure!(task::account_from_seed_in_accounts("Alice", accounts), Error::<T>::NoRightToUpdateErrand);
+            ensure_none(origin)?;
+            let sender = payload.public.clone().into_account();
+            Self::init_errand_of(
+                sender,
+                payload.client,
+                payload.errand_id,
+                payload.description_cid,
+            )
+        }
 
-            Errands::mutate(&description_cid, |val| {
-                if let Some(errand) = val {
-                    errand.status = ErrandStatus::Done;
-                    errand.result = result.clone();
-                }
+        /// Initializes the errands of the tasks queued at the current height right away,
+        /// for operators when the offchain worker can't send them. Tasks failing to
+        /// initialize stay queued.
+        #[weight = 10_000]
+        pub fn flush_tasks(origin) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+            let block_number = frame_system::Module::<T>::block_number();
+            let mut remaining = Tasks::<T>::get(&block_number);
+            ensure!(!remaining.is_empty(), Error::<T>::NoTasksToFlush);
+
+            let mut flushed = 0u32;
+            remaining.retain(|task| {
+                let result = Self::bytes_to_account(&mut task.client.as_slice())
+                    .map_err(|e| e.into())
+                    .and_then(|client| {
+                        Self::insert_errand(
+                            client,
+                            task.errand_id.clone(),
+                            task.description_cid.clone(),
+                            true,
+                        )
+                    });
+                match result {
+                    Ok(_) => {
+                        flushed += 1;
+                        TaskLocation::<T>::remove(&task.errand_id);
+                        false
+                    }
+                    Err(e) => {
+                        debug::error!("flush task {:?} error: {:?}", &task.errand_id, e);
+                        true
+                    }
+                }
             });
+
+            let failed = remaining.len() as u32;
+            if remaining.is_empty() {
+                Tasks::<T>::remove(&block_number);
+            } else {
+                Tasks::<T>::insert(&block_number, remaining);
+            }
+            PendingTaskCount::mutate(|count| *count = count.saturating_sub(flushed.into()));
+            Self::deposit_event(RawEvent::TasksFlushed(flushed, failed));
+            Ok(())
+        }
+
+        /// Clears the expired claim of errand `description_cid` and queues its task again so
+        /// another worker can claim it, `retry_jitter` blocks later.
+        #[weight = 10_000]
+        pub fn expire_claim(origin,
+            description_cid: Cid,
+            ) -> dispatch::DispatchResult {
+            ensure_signed(origin)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(errand.status == ErrandStatus::Processing, Error::<T>::ErrandAlreadyExecuted);
+            let expiry = ClaimExpiry::<T>::get(&description_cid).ok_or(Error::<T>::ErrandNotClaimed)?;
+            let block_number = frame_system::Module::<T>::block_number();
+            ensure!(expiry <= block_number, Error::<T>::ClaimNotExpired);
+            let worker = ErrandWorker::<T>::get(&description_cid).ok_or(Error::<T>::ErrandNotClaimed)?;
+            let client = Self::bytes_to_account(&mut errand.account_id.as_slice())?;
+            let fee = ErrandFees::get(&errand.errand_id);
+            let max_jitter = T::MaxRetryJitter::get();
+            let retry_at =
+                Self::future_block(Self::retry_jitter(&errand.errand_id, block_number, max_jitter));
+            let mut tasks = Tasks::<T>::get(&retry_at);
+            tasks
+                .try_push(TaskInfo {
+                    client: errand.account_id,
+                    sender: ClientSender::<T>::get(&client).encode(),
+                    description_cid: description_cid.clone(),
+                    errand_id: errand.errand_id.clone(),
+                    fee,
+                })
+                .map_err(|_| Error::<T>::TooManyTasksInBlock)?;
+
+            Self::release_claim(&description_cid);
             Self::remove_processing(&description_cid);
+            TaskLocation::<T>::insert(&errand.errand_id, retry_at);
+            Self::insert_bid(&errand.errand_id, fee);
+            Tasks::<T>::insert(&retry_at, tasks);
+            PendingTaskCount::mutate(|count| *count = count.saturating_add(1));
 
-            if let Some(errand) = Errands::get(&description_cid) {
-                let client = Self::bytes_to_account(&mut errand.account_id.as_slice())?;
-                let sender = ClientSender::<T>::get(&client);
-                let fee = ClientTaskFee::<T>::get(&client);
-                let balance = T::Currency::repatriate_reserved(&client, &sender, fee.into(), BalanceStatus::Free);
-                match balance {
-                    Ok(_b) => {debug::info!("repatriate reserved succeed");},
-                    Err(_e) => {debug::error!("failed to repatriate reserved wiith cid: {:?}", description_cid);},
+            Self::deposit_event(RawEvent::ClaimExpired(worker, description_cid));
+            Ok(())
+        }
+
+        /// Gives the worker of errand `description_cid` `additional_blocks` more to complete
+        /// it, the new deadline is clamped to `MaxDeadline` blocks from now. Only the employer
+        /// can extend the deadline of a claim that has not expired yet.
+        #[weight = 10_000]
+        pub fn extend_deadline(origin,
+            description_cid: Cid,
+            additional_blocks: T::BlockNumber,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(errand.account_id == sender.encode(), Error::<T>::NotErrandOwner);
+            ensure!(errand.status == ErrandStatus::Processing, Error::<T>::ErrandAlreadyExecuted);
+            let expiry = ClaimExpiry::<T>::get(&description_cid).ok_or(Error::<T>::ErrandNotClaimed)?;
+            ensure!(expiry > frame_system::Module::<T>::block_number(), Error::<T>::DeadlinePassed);
+
+            let deadline = expiry
+                .saturating_add(additional_blocks)
+                .min(Self::blocks_later(T::MaxDeadline::get()))
+                .max(expiry);
+            Self::mark_seen(&sender);
+            ClaimExpiry::<T>::insert(&description_cid, deadline);
+            Self::deposit_event(RawEvent::DeadlineExtended(sender, description_cid, deadline));
+            Ok(())
+        }
+
+        /// Requires `confirmations` registered workers to submit the same result before the
+        /// processing errand `description_cid` is done. Only its employer can require them.
+        #[weight = 10_000]
+        pub fn require_confirmations(origin,
+            description_cid: Cid,
+            confirmations: u8,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            ensure!(
+                confirmations > 0 && confirmations <= MAX_REQUIRED_CONFIRMATIONS,
+                Error::<T>::InvalidConfirmations
+            );
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(errand.account_id == sender.encode(), Error::<T>::NotErrandOwner);
+            ensure!(errand.status == ErrandStatus::Processing, Error::<T>::ErrandAlreadyExecuted);
+
+            Self::mark_seen(&sender);
+            RequiredConfirmations::insert(&description_cid, confirmations);
+            Ok(())
+        }
+
+        /// Releases `pct` percent of the task fee of errand `errand_id` to its worker, less
+        /// what earlier reports released. Progress only increases up to 100, the part of the
+        /// fee not released by then is paid on completion.
+        #[weight = 10_000]
+        pub fn report_progress(origin,
+            errand_id: ErrandId,
+            pct: u8,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let description_cid = Self::processing_errand_cid(&errand_id)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(
+                ErrandWorker::<T>::get(&description_cid).as_ref() == Some(&sender),
+                Error::<T>::NotErrandWorker
+            );
+            let (reported, released) = ErrandProgress::get(&errand_id);
+            ensure!(pct > reported && pct <= 100, Error::<T>::InvalidProgress);
+
+            let client = Self::bytes_to_account(&mut errand.account_id.as_slice())?;
+            let fee = ErrandFees::get(&errand_id);
+            let due = (fee as u64 * pct as u64 / 100) as u32;
+            let amount = due.saturating_sub(released);
+            Self::repatriate_fee(&client, &sender, amount)?;
+            ErrandProgress::insert(&errand_id, (pct, released.saturating_add(amount)));
+            Self::deposit_event(RawEvent::ProgressReported(sender, errand_id, pct));
+            Ok(())
+        }
+
+        /// Lists the claim of processing errand `errand_id` for sale at `price`. The claim is
+        /// held by `listing_account` until the listing is bought, completing the errand
+        /// meanwhile pays its fee to the seller.
+        #[weight = 10_000]
+        pub fn list_errand(origin,
+            errand_id: ErrandId,
+            price: BalanceOf<T>,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let description_cid = Self::processing_errand_cid(&errand_id)?;
+            ensure!(
+                ErrandWorker::<T>::get(&description_cid).as_ref() == Some(&sender),
+                Error::<T>::NotErrandWorker
+            );
+
+            Self::transfer_claim(&description_cid, &Self::listing_account(&errand_id));
+            ErrandListings::<T>::insert(&description_cid, (&sender, price));
+            Self::deposit_event(RawEvent::ErrandListed(sender, errand_id, price));
+            Ok(())
+        }
+
+        /// Buys the listing of errand `errand_id`, its price is transferred to the seller and
+        /// the buyer claims the errand.
+        #[weight = 10_000]
+        pub fn buy_errand_listing(origin,
+            errand_id: ErrandId,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let description_cid = Self::processing_errand_cid(&errand_id)?;
+            let (seller, price) = ErrandListings::<T>::get(&description_cid)
+                .ok_or(Error::<T>::ErrandNotListed)?;
+            ensure!(
+                ActiveClaims::<T>::get(&sender) < T::MaxClaimsPerWorker::get(),
+                Error::<T>::TooManyClaims
+            );
+            T::Currency::transfer(&sender, &seller, price, ExistenceRequirement::KeepAlive)?;
+
+            ErrandListings::<T>::remove(&description_cid);
+            Self::transfer_claim(&description_cid, &sender);
+            Self::deposit_event(RawEvent::ErrandListingBought(sender, errand_id, price));
+            Ok(())
+        }
+
+        /// Completes errand `description_cid` with `result` of `content_type`, an empty
+        /// content type is stored as `DEFAULT_CONTENT_TYPE`. Results longer than
+        /// `InlineResultThreshold` are given as their IPFS CID with `result_is_cid` set.
+        /// `proof` is the sr25519 or ed25519 signature of the claimant of the errand over
+        /// `completion_message`, completions without one can't be verified by third parties.
+        ///
+        /// Errands requiring confirmations take results of registered workers instead, each
+        /// proof signed by its submitter. The errand is done once enough workers submitted
+        /// the same result and disputed as soon as one submits a different one.
+        #[weight = 10_000]
+        pub fn update_errand(origin,
+            description_cid: Cid,
+            result: Vec<u8>,
+            content_type: Vec<u8>,
+            result_is_cid: bool,
+            proof: Option<Vec<u8>>,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            ensure!(Errands::contains_key(&description_cid), Error::<T>::ErrandTaskNotExist);
+            Self::ensure_bounded(&result, MAX_RESULT_LENGTH as u32, Error::<T>::ResultTooLong)?;
+            ensure!(
+                result_is_cid || result.len() <= T::InlineResultThreshold::get() as usize,
+                Error::<T>::ResultAboveInlineThreshold
+            );
+            Self::ensure_bounded(&content_type, MAX_CONTENT_TYPE_LENGTH as u32, Error::<T>::ContentTypeTooLong)?;
+            let required = RequiredConfirmations::get(&description_cid);
+            if required > 1 {
+                ensure!(RegisteredWorkers::<T>::get().contains(&sender), Error::<T>::NotRegisteredWorker);
+            } else {
+                Self::ensure_errand_updater(&sender)?;
+            }
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(errand.status == ErrandStatus::Processing, Error::<T>::ErrandAlreadyExecuted);
+            Self::ensure_assigned_worker(&errand.errand_id, &sender)?;
+            // settle first, a failed settlement leaves the fee reserved and the errand
+            // processing as if the update never happened
+            let client = Self::bytes_to_account(&mut errand.account_id.as_slice())?;
+            let claimant = Self::completion_signer(&description_cid, &client);
+            let signer = if required > 1 { sender.clone() } else { claimant.clone() };
+            let result_hash = Self::result_hash(&result);
+            if let Some(proof) = &proof {
+                ensure!(
+                    Self::is_signed_by(&signer, &Self::completion_message(&errand.errand_id, &result_hash), proof),
+                    Error::<T>::InvalidCompletionProof
+                );
+            }
+            if required > 1 {
+                let mut submissions = ResultSubmissions::<T>::get(&description_cid);
+                ensure!(
+                    !submissions.iter().any(|(submitter, _)| submitter == &sender),
+                    Error::<T>::ResultAlreadySubmitted
+                );
+                if submissions.iter().any(|(_, hash)| hash != &result_hash) {
+                    Self::dispute_errand(&client, &description_cid);
+                    return Ok(());
+                }
+                submissions.push((sender.clone(), result_hash));
+                if submissions.len() < required as usize {
+                    ResultSubmissions::<T>::insert(&description_cid, submissions);
+                    Self::deposit_event(RawEvent::ResultSubmitted(sender, description_cid));
+                    return Ok(());
+                }
+            }
+            let fee = Self::unreleased_fee(&description_cid);
+            Self::repatriate_fee(&client, &claimant, fee)?;
+            ErrandFees::remove(&errand.errand_id);
+            ResultSubmissions::<T>::remove(&description_cid);
+
+            Errands::mutate(&description_cid, |val| {
+                if let Some(errand) = val {
+                    errand.status = ErrandStatus::Done;
+                    errand.result = result.clone();
+                    Self::log_status(&errand.errand_id, &errand.status);
                 }
+            });
+            ErrandResultHashes::<T>::insert(&description_cid, result_hash);
+            ResultHashAlgos::insert(&description_cid, T::ResultHashAlgo::get());
+            Self::add_result_version(&description_cid, &result);
+            match proof {
+                Some(proof) => CompletionProofs::<T>::insert(&description_cid, (signer, proof)),
+                None => CompletionProofs::<T>::remove(&description_cid),
+            }
+            ErrandOutcomes::insert(&description_cid, Outcome::Success);
+            if result_is_cid {
+                ResultIsCid::insert(&description_cid, true);
+            }
+            if content_type.is_empty() {
+                ErrandContentTypes::insert(&description_cid, DEFAULT_CONTENT_TYPE.to_vec());
             } else {
-                debug::error!("found empty errand with cid: {:?}", description_cid);
+                ErrandContentTypes::insert(&description_cid, content_type);
+            }
+            Self::schedule_result_retention(&description_cid);
+            Self::remove_processing(&description_cid);
+            Self::release_claim(&description_cid);
+            if WebhookHashes::<T>::contains_key(&client) {
+                let block_number = frame_system::Module::<T>::block_number();
+                WebhookNotifications::<T>::append(&block_number, (client, description_cid.clone()));
             }
+            T::OnErrandCompleted::on_completed(&errand.errand_id, &result);
 
             Self::deposit_event(RawEvent::ErrandUpdated(description_cid, result));
             Ok(())
         }
 
+        /// Fails processing errand `description_cid` with `reason`, `outcome` tells why it
+        /// failed and can't be `Outcome::Success`. The worker claiming the errand can fail it,
+        /// anybody else has to be a registered worker.
+        #[weight = 10_000]
+        pub fn fail_errand(origin,
+            description_cid: Cid,
+            reason: Vec<u8>,
+            outcome: Outcome,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            Self::ensure_bounded(&reason, MAX_FAIL_REASON_LENGTH as u32, Error::<T>::FailReasonTooLong)?;
+            ensure!(outcome != Outcome::Success, Error::<T>::InvalidOutcome);
+            // a finished errand has nothing reserved left to refund
+            ensure!(errand.status == ErrandStatus::Processing, Error::<T>::ErrandAlreadyExecuted);
+            if ErrandWorker::<T>::get(&description_cid).as_ref() != Some(&sender) {
+                // the updater check only runs natively, the registry check runs in wasm too
+                ensure!(
+                    RegisteredWorkers::<T>::get().contains(&sender),
+                    Error::<T>::NoRightToUpdateErrand
+                );
+                Self::ensure_errand_updater(&sender)?;
+            }
+
+            Self::mark_failed(description_cid, reason, outcome)?;
+            Ok(())
+        }
+
+        /// Makes the result of the sender's private errand `description_cid` available
+        /// through the public runtime api.
+        #[weight = 10_000]
+        pub fn reveal_errand_result(origin,
+            description_cid: Cid,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(errand.account_id == sender.encode(), Error::<T>::NotErrandOwner);
+
+            Self::mark_seen(&sender);
+            PrivateErrands::remove(&description_cid);
+            Self::deposit_event(RawEvent::ErrandResultRevealed(sender, description_cid));
+            Ok(())
+        }
+
+        /// Adds `comment_cid` to the thread of errand `description_cid`, only its employer
+        /// and the worker sending its tasks can comment.
+        #[weight = 10_000]
+        pub fn add_comment(origin,
+            description_cid: Cid,
+            comment_cid: Cid,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_bounded(&comment_cid, MAX_CID_LENGTH as u32, Error::<T>::CidTooLong)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            let client = Self::bytes_to_account(&mut errand.account_id.as_slice())?;
+            ensure!(
+                sender == client || sender == ClientSender::<T>::get(&client),
+                Error::<T>::NoRightToComment
+            );
+            ensure!(
+                ErrandComments::<T>::decode_len(&description_cid).unwrap_or(0) < MAX_COMMENTS_PER_ERRAND,
+                Error::<T>::TooManyComments
+            );
+
+            let block_number = frame_system::Module::<T>::block_number();
+            ErrandComments::<T>::append(&description_cid, (sender.clone(), comment_cid.clone(), block_number));
+            Self::deposit_event(RawEvent::CommentAdded(sender, description_cid, comment_cid));
+            Ok(())
+        }
+
+        /// Removes the sender's finished errand `description_cid` from storage to make room
+        /// for new ones, only the hash of its result is kept.
+        #[weight = 10_000]
+        pub fn archive_errand(origin,
+            description_cid: Cid,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(errand.account_id == sender.encode(), Error::<T>::NotErrandOwner);
+            ensure!(errand.status != ErrandStatus::Processing, Error::<T>::ErrandStillProcessing);
+
+            Self::mark_seen(&sender);
+            Errands::remove(&description_cid);
+            TaskKinds::remove(&errand.errand_id);
+            AssignedWorkers::<T>::remove(&errand.errand_id);
+            ErrandCids::remove(&errand.errand_id);
+            ErrandProgress::remove(&errand.errand_id);
+            ErrandFees::remove(&errand.errand_id);
+            ErrandsByCid::remove(&description_cid);
+            ErrandFailReasons::remove(&description_cid);
+            ErrandOutcomes::remove(&description_cid);
+            ErrandContentTypes::remove(&description_cid);
+            ResultIsCid::remove(&description_cid);
+            PrivateErrands::remove(&description_cid);
+            PinnedErrands::remove(&description_cid);
+            RequiredConfirmations::remove(&description_cid);
+            ResultSubmissions::<T>::remove(&description_cid);
+            ErrandComments::<T>::remove(&description_cid);
+            AccountErrands::<T>::mutate(&sender, |errands| {
+                errands.retain(|cid| !cid.eq(&description_cid))
+            });
+            Self::deposit_event(RawEvent::ErrandArchived(sender, description_cid));
+            Ok(())
+        }
+
+        /// Keeps the inline result of the sender's errand `description_cid` past
+        /// `RetentionBlocks` until it is unpinned.
+        #[weight = 10_000]
+        pub fn pin_errand(origin,
+            description_cid: Cid,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(errand.account_id == sender.encode(), Error::<T>::NotErrandOwner);
+
+            Self::mark_seen(&sender);
+            PinnedErrands::insert(&description_cid, true);
+            Self::deposit_event(RawEvent::ErrandPinned(sender, description_cid));
+            Ok(())
+        }
+
+        /// Unpins the sender's errand `description_cid`, a done errand keeps its inline
+        /// result for another `RetentionBlocks`.
+        #[weight = 10_000]
+        pub fn unpin_errand(origin,
+            description_cid: Cid,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+            ensure!(errand.account_id == sender.encode(), Error::<T>::NotErrandOwner);
+            ensure!(PinnedErrands::get(&description_cid), Error::<T>::ErrandNotPinned);
+
+            Self::mark_seen(&sender);
+            PinnedErrands::remove(&description_cid);
+            if errand.status == ErrandStatus::Done && !errand.result.is_empty() {
+                Self::schedule_result_retention(&description_cid);
+            }
+            Self::deposit_event(RawEvent::ErrandUnpinned(sender, description_cid));
+            Ok(())
+        }
+
         #[weight = 10_000]
         fn unreserve(origin,
             client: T::AccountId,
             fee: u32,
             ) {
-            T::Currency::unreserve(&client, fee.into());
+            Self::unreserve_fee(&client, fee);
             Clients::<T>::remove(&client);
             ClientSender::<T>::remove(&client);
             ClientNetAddress::<T>::remove(&client);
         }
 
-        fn offchain_worker(block_number: T::BlockNumber) {
-            debug::info!("Entering off-chain workers");
+        /// Allows the offchain worker to sign transactions with the local keys of `worker`.
+        #[weight = 10_000]
+        pub fn register_worker(origin,
+            worker: T::AccountId,
+            ) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+            let mut workers = RegisteredWorkers::<T>::get();
+            ensure!(!workers.contains(&worker), Error::<T>::WorkerAlreadyRegistered);
+            ensure!(workers.len() < MAX_REGISTERED_WORKERS, Error::<T>::TooManyWorkers);
+
+            workers.push(worker.clone());
+            RegisteredWorkers::<T>::put(workers);
+            Self::deposit_event(RawEvent::WorkerRegistered(worker));
+            Ok(())
+        }
+
+        /// Stops queueing new tasks of `kind`, errands of it already queued or processing
+        /// still complete.
+        #[weight = 10_000]
+        pub fn pause_kind(origin, kind: KindId) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+            let mut paused = PausedKinds::get();
+            ensure!(!paused.contains(&kind), Error::<T>::KindPaused);
+
+            paused.push(kind);
+            PausedKinds::put(paused);
+            Self::deposit_event(RawEvent::KindPaused(kind));
+            Ok(())
+        }
+
+        /// Lets tasks of the paused `kind` be queued again.
+        #[weight = 10_000]
+        pub fn resume_kind(origin, kind: KindId) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+            let mut paused = PausedKinds::get();
+            ensure!(paused.contains(&kind), Error::<T>::KindNotPaused);
+
+            paused.retain(|paused_kind| *paused_kind != kind);
+            PausedKinds::put(paused);
+            Self::deposit_event(RawEvent::KindResumed(kind));
+            Ok(())
+        }
+
+        /// Registers `kind` so tasks of it can be queued, registering it again replaces its
+        /// metadata. `DEFAULT_KIND` can be registered too to change how its tasks are sent.
+        #[weight = 10_000]
+        pub fn register_kind(origin,
+            kind: KindId,
+            info: KindInfo,
+        ) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+            Self::ensure_bounded(
+                &info.action_path,
+                MAX_ACTION_PATH_LENGTH as u32,
+                Error::<T>::InvalidKindInfo,
+            )?;
+            ensure!(
+                info.action_path.first() == Some(&b'/')
+                    && sp_std::str::from_utf8(&info.action_path).is_ok(),
+                Error::<T>::InvalidKindInfo
+            );
+            Self::ensure_bounded(
+                &info.accept,
+                MAX_CONTENT_TYPE_LENGTH as u32,
+                Error::<T>::InvalidKindInfo,
+            )?;
+            ensure!(sp_std::str::from_utf8(&info.accept).is_ok(), Error::<T>::InvalidKindInfo);
+            ensure!(
+                info.fee_multiplier_percent <= MAX_FEE_MULTIPLIER_PERCENT,
+                Error::<T>::InvalidKindInfo
+            );
+
+            KindRegistry::insert(kind, info);
+            Self::deposit_event(RawEvent::KindRegistered(kind));
+            Ok(())
+        }
+
+        /// Lets `recovery` claim the finished errands of the sender once it has not managed
+        /// them for `RecoveryDelay` blocks, `None` removes the recovery account.
+        #[weight = 10_000]
+        pub fn set_recovery_account(origin,
+            recovery: Option<T::AccountId>,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::mark_seen(&sender);
+            match &recovery {
+                Some(account) => RecoveryAccounts::<T>::insert(&sender, account),
+                None => RecoveryAccounts::<T>::remove(&sender),
+            }
+            Self::deposit_event(RawEvent::RecoveryAccountSet(sender, recovery));
+            Ok(())
+        }
+
+        /// Moves the finished errands of the inactive employer `of` to the sender, its
+        /// recovery account. Processing errands stay with `of` since their fees are reserved
+        /// from it, they can be recovered once finished.
+        #[weight = 10_000]
+        pub fn recover_errands(origin,
+            of: T::AccountId,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            ensure!(
+                RecoveryAccounts::<T>::get(&of).as_ref() == Some(&sender),
+                Error::<T>::NotRecoveryAccount
+            );
+            let inactive_from = LastSeen::<T>::get(&of).saturating_add(T::RecoveryDelay::get());
+            ensure!(
+                frame_system::Module::<T>::block_number() >= inactive_from,
+                Error::<T>::AccountStillActive
+            );
+            let (finished, processing): (Vec<Cid>, Vec<Cid>) = AccountErrands::<T>::get(&of)
+                .into_iter()
+                .partition(|cid| {
+                    Errands::get(cid).map_or(true, |errand| errand.status != ErrandStatus::Processing)
+                });
+            let mut recovered = AccountErrands::<T>::get(&sender);
+            let recovered_count = recovered.len().saturating_add(finished.len());
+            ensure!(
+                recovered_count <= T::MaxErrandsPerAccount::get() as usize,
+                Error::<T>::TooManyErrandsForAccount
+            );
+
+            for cid in finished.iter() {
+                Errands::mutate(cid, |val| {
+                    if let Some(errand) = val {
+                        errand.account_id = sender.encode();
+                    }
+                });
+                if !recovered.contains(cid) {
+                    recovered.push(cid.clone());
+                }
+            }
+            AccountErrands::<T>::insert(&sender, recovered);
+            AccountErrands::<T>::insert(&of, processing);
+            Self::deposit_event(RawEvent::ErrandsRecovered(of, sender, finished.len() as u32));
+            Ok(())
+        }
+
+        /// Registers the http(s) `url` the offchain worker posts to when errands of the sender
+        /// complete. Only its hash is stored on chain, the url goes to offchain local storage
+        /// of nodes running with offchain indexing.
+        #[weight = 10_000]
+        pub fn register_webhook(origin,
+            url: Vec<u8>,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_bounded(&url, MAX_WEBHOOK_URL_LENGTH as u32, Error::<T>::InvalidWebhookUrl)?;
+            ensure!(
+                url.starts_with(b"http://") || url.starts_with(b"https://"),
+                Error::<T>::InvalidWebhookUrl
+            );
+
+            WebhookHashes::<T>::insert(&sender, T::Hashing::hash(&url));
+            sp_io::offchain_index::set(&Self::webhook_key(&sender), &url.encode());
+            Self::deposit_event(RawEvent::WebhookRegistered(sender));
+            Ok(())
+        }
+
+        /// Registers `proof`, the sender's sr25519 signature over `delegation_message`,
+        /// authorizing `worker` to send tasks on behalf of the sender.
+        #[weight = 10_000]
+        pub fn register_delegation(origin,
+            worker: T::AccountId,
+            proof: Vec<u8>,
+            ) -> dispatch::DispatchResult {
+            let employer = ensure_signed(origin)?;
+            Self::verify_delegation_proof(&employer, &worker, &proof)?;
+
+            Delegations::<T>::insert(&employer, &worker, proof);
+            Self::deposit_event(RawEvent::DelegationRegistered(employer, worker));
+            Ok(())
+        }
+
+        /// Releases the fees tracked in `ClientReserved` for `accounts` beyond their fees still
+        /// due, see `expected_reserve`. Reserves of other pallets are left alone. Reads every
+        /// queued task once, its weight grows with `PendingTaskCount`.
+        #[weight = T::DbWeight::get().reads_writes(
+            (accounts.len() as Weight)
+                .saturating_mul(3 * T::MaxErrandsPerAccount::get() as Weight + 6)
+                .saturating_add(PendingTaskCount::get().saturating_mul(3)),
+            (accounts.len() as Weight).saturating_mul(4),
+        )]
+        pub fn reconcile_reserves(origin,
+            accounts: Vec<T::AccountId>,
+            ) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+            ensure!(accounts.len() <= MAX_RECONCILE_ACCOUNTS, Error::<T>::TooManyAccountsToReconcile);
+
+            let clients: Vec<Vec<u8>> = accounts.iter().map(|account| account.encode()).collect();
+            let mut queued = Self::queued_task_fees(&clients);
+            for (account, client) in accounts.iter().zip(clients.iter()) {
+                let queued_fees = queued.remove(client).unwrap_or(0);
+                let expected = Self::expected_reserve(account, queued_fees);
+                let tracked = ClientReserved::<T>::get(account);
+                if tracked > expected {
+                    let orphaned = tracked - expected;
+                    let remaining = T::Currency::unreserve(account, orphaned);
+                    Self::track_release(account, orphaned);
+                    let released = orphaned.saturating_sub(remaining);
+                    Self::deposit_event(RawEvent::ReservesReconciled(account.clone(), released));
+                }
+            }
+            Ok(())
+        }
+
+        /// Fails the sender's processing errands whose claim expired without being cleared
+        /// and refunds their unreleased fees. They fail with `Outcome::Timeout`, so they count
+        /// as expired. Only the first `MAX_RECLAIM_SCAN` errands of the sender are looked at.
+        #[weight = 10_000]
+        pub fn reclaim_stuck_reserves(origin) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let block_number = frame_system::Module::<T>::block_number();
+            let stuck: Vec<Cid> = AccountErrands::<T>::get(&sender)
+                .into_iter()
+                .take(MAX_RECLAIM_SCAN)
+                .filter(|cid| Self::is_stuck(cid, block_number))
+                .collect();
+            ensure!(!stuck.is_empty(), Error::<T>::NoStuckErrands);
+
+            Self::mark_seen(&sender);
+            let mut reclaimed = 0u32;
+            for cid in stuck {
+                let reason = STUCK_ERRAND_FAIL_REASON.to_vec();
+                let refunded = Self::mark_failed(cid, reason, Outcome::Timeout)?;
+                reclaimed = reclaimed.saturating_add(refunded);
+            }
+            Self::deposit_event(RawEvent::StuckReservesReclaimed(sender, reclaimed.into()));
+            Ok(())
+        }
+
+        /// Adds `sample_count` task requests taking `avg_ms` milliseconds on average to
+        /// `ServiceLatency`. Only registered workers can report.
+        #[weight = 10_000]
+        pub fn report_latency(origin,
+            avg_ms: u32,
+            sample_count: u32,
+            ) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            ensure!(
+                RegisteredWorkers::<T>::get().contains(&sender),
+                Error::<T>::NotRegisteredWorker
+            );
+            ensure!(sample_count > 0, Error::<T>::NoLatencySamples);
+
+            ServiceLatency::mutate(|latency| {
+                *latency = Self::rolling_latency(*latency, avg_ms, sample_count)
+            });
+            Self::deposit_event(RawEvent::LatencyReported(sender, avg_ms, sample_count));
+            Ok(())
+        }
+
+        fn offchain_worker(block_number: T::BlockNumber) {
+            debug::info!("Entering off-chain workers");
+
+            let draining = Self::is_draining();
+            if !draining {
+                Self::apply_delegates(block_number);
+            }
+            Self::send_errand_tasks(block_number);
+            if !draining {
+                Self::reap_expired_claims(block_number);
+            }
+            Self::warn_expiring_errands(block_number);
+            Self::notify_webhooks(block_number);
+            if Self::should_poll(block_number) {
+                if draining {
+                    debug::info!("draining, skip polling errand results");
+                } else {
+                    Self::query_errand_task_results(block_number);
+                }
+                Self::update_errand_task_results(block_number);
+            }
+
+            #[cfg(feature = "std")]
+            if (block_number % METRICS_LOG_INTERVAL.into()).is_zero() {
+                metrics::log_metrics_summary();
+            }
+            #[cfg(feature = "std")]
+            if (block_number % LATENCY_REPORT_INTERVAL.into()).is_zero() {
+                Self::report_service_latency();
+            }
+        }
+    }
+}
+
+impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
+    type Call = Call<T>;
+
+    /// Unsigned inits provide their errand id as tag, so of two inits of the same errand
+    /// only one can be in the pool or land in a block.
+    fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+        match call {
+            Call::init_errand_unsigned(payload, signature) => {
+                if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+                    return InvalidTransaction::BadProof.into();
+                }
+                let initialized = Errands::get(&payload.description_cid)
+                    .map_or(false, |errand| errand.errand_id == payload.errand_id);
+                if initialized {
+                    return InvalidTransaction::Stale.into();
+                }
+                ValidTransaction::with_tag_prefix("AbcInitErrand")
+                    .priority(T::UnsignedPriority::get())
+                    .and_provides(&payload.errand_id)
+                    .longevity(UNSIGNED_INIT_LONGEVITY)
+                    .propagate(true)
+                    .build()
+            }
+            _ => InvalidTransaction::Call.into(),
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    fn init_errand_of(
+        sender: T::AccountId,
+        client: T::AccountId,
+        errand_id: ErrandId,
+        description_cid: Cid,
+    ) -> dispatch::DispatchResult {
+        Self::validate_cid(&description_cid)?;
+        ensure!(ClientSender::<T>::contains_key(&client), Error::<T>::ClientSenderNotExist);
+        ensure!(sender == ClientSender::<T>::get(&client), Error::<T>::ClientSenderNotExist);
+        let task = Self::find_recent_task(&errand_id, &description_cid)
+            .ok_or(Error::<T>::UnknownErrand)?;
+        ensure!(task.client == client.encode(), Error::<T>::EmployerMismatch);
+        Self::ensure_assigned_worker(&errand_id, &sender)?;
+        if let Some(fee) = UnreservedTaskFees::get(&errand_id) {
+            if !Self::can_reserve_fee(&client, fee) {
+                Self::drop_unfunded_task(client, errand_id);
+                return Ok(());
+            }
+        }
+
+        Self::insert_errand(
+            client,
+            errand_id.clone(),
+            description_cid,
+            !T::BatchInitEvents::get(),
+        )?;
+        Self::dequeue_initialized_task(&errand_id);
+        ErrandsInitedInBlock::mutate(|count| *count = count.saturating_add(1));
+        Ok(())
+    }
+
+    /// Numbers of processing, done, failed and expired errands of `account`. Errands failed
+    /// with `Outcome::Timeout` count as expired, disputed ones as failed.
+    pub fn account_status_summary(account: T::AccountId) -> (u32, u32, u32, u32) {
+        let mut summary = (0u32, 0u32, 0u32, 0u32);
+        for cid in AccountErrands::<T>::get(&account).iter() {
+            let errand = match Errands::get(cid) {
+                Some(errand) => errand,
+                None => continue,
+            };
+            let count = match errand.status {
+                ErrandStatus::Processing => &mut summary.0,
+                ErrandStatus::Done => &mut summary.1,
+                ErrandStatus::Failed if ErrandOutcomes::get(cid) == Some(Outcome::Timeout) => {
+                    &mut summary.3
+                }
+                ErrandStatus::Failed | ErrandStatus::Disputed => &mut summary.2,
+            };
+            *count = count.saturating_add(1);
+        }
+        summary
+    }
+
+    /// Ids and results of at most `limit` done errands of `account`. Results of private
+    /// errands are left empty.
+    pub fn completed_errands_of(account: T::AccountId, limit: u32) -> Vec<(ErrandId, Cid)> {
+        let limit = limit.min(MAX_PAGE_SIZE) as usize;
+        AccountErrands::<T>::get(&account)
+            .iter()
+            .filter_map(|cid| Errands::get(cid))
+            .filter(|errand| errand.status == ErrandStatus::Done)
+            .take(limit)
+            .map(|errand| {
+                if PrivateErrands::get(&errand.description_cid) {
+                    (errand.errand_id, Vec::new())
+                } else {
+                    (errand.errand_id, errand.result)
+                }
+            })
+            .collect()
+    }
+
+    /// Offchain worker configuration derived from on chain values.
+    pub fn offchain_config() -> OffchainConfig<T::BlockNumber> {
+        OffchainConfig {
+            send_task_timeout: TEA_SEND_TASK_TIMEOUT_PERIOD,
+            service_url_prefix: SERVICE_BASE_URL_PREFIX.as_bytes().to_vec(),
+            poll_interval: T::PollInterval::get(),
+            max_init_errand_delay: MAX_INIT_ERRAND_DELAY,
+            polling_enabled: !T::PollInterval::get().is_zero(),
+        }
+    }
+
+    /// Status codes of the errands of the given description cids, `0` for the unknown ones.
+    /// Only the first `MAX_STATUS_BATCH` cids are queried.
+    pub fn errand_statuses(cids: Vec<Cid>) -> Vec<u8> {
+        cids.iter()
+            .take(MAX_STATUS_BATCH)
+            .map(|cid| Errands::get(cid).map_or(0, |errand| errand.status.code()))
+            .collect()
+    }
+
+    /// Ids and creation heights of at most `limit` errands created from height `from` to
+    /// `to` inclusive. The range is clamped to `MAX_INDEX_BLOCK_RANGE` blocks from `from`.
+    pub fn errands_created_between(
+        from: T::BlockNumber,
+        to: T::BlockNumber,
+        limit: u32,
+    ) -> Vec<(ErrandId, T::BlockNumber)> {
+        let limit = limit.min(MAX_PAGE_SIZE) as usize;
+        let last = to.min(from.saturating_add((MAX_INDEX_BLOCK_RANGE - 1).into()));
+        let mut errands = Vec::new();
+        let mut height = from;
+        while height <= last && errands.len() < limit {
+            errands.extend(
+                ErrandsCreatedAt::<T>::get(&height)
+                    .into_iter()
+                    .map(|errand_id| (errand_id, height))
+                    .take(limit - errands.len()),
+            );
+            if height == last {
+                break;
+            }
+            height = height.saturating_add(One::one());
+        }
+        errands
+    }
+
+    /// Errand id `begin_task_with_salt` queues the task of `sender` with, so clients can
+    /// know it in advance.
+    pub fn derive_errand_id(sender: &T::AccountId, salt: &[u8]) -> ErrandId {
+        let payload = (ERRAND_ID_SALT_PREFIX, sender, salt);
+        Self::errand_id_from_seed(&payload.encode(), ERRAND_ID_MODE)
+    }
+
+    fn generate_errand_id(sender: &T::AccountId) -> Vec<u8> {
+        let payload = (
+            T::Randomness::random_seed(),
+            &sender,
+            <frame_system::Module<T>>::extrinsic_index(),
+        );
+        Self::errand_id_from_seed(&payload.encode(), ERRAND_ID_MODE)
+    }
+
+    fn errand_id_from_seed(seed: &[u8], mode: ErrandIdMode) -> ErrandId {
+        match mode {
+            ErrandIdMode::Uuid => Self::errand_id_from_bytes(blake2_128(seed)),
+            ErrandIdMode::Wide => bytes_to_hex_ascii(&blake2_256(seed)),
+        }
+    }
+
+    fn errand_id_from_bytes(bytes: [u8; 16]) -> ErrandId {
+        let uuid = Builder::from_bytes(bytes)
+            .set_variant(Variant::RFC4122)
+            .set_version(Version::Random)
+            .build();
+        let mut buf = Uuid::encode_buffer();
+        let uuid = uuid.to_hyphenated().encode_lower(&mut buf);
+        uuid.as_bytes().to_vec()
+    }
+
+    fn apply_delegates(block_number: T::BlockNumber) {
+        if !ClientsApplys::<T>::contains_key(&block_number) {
+            debug::info!("height {:?} has no delegates, just return", &block_number);
+            return;
+        }
+
+        let signer = Self::worker_signer();
+        if !signer.can_sign() {
+            debug::info!("No local account available when apply delegate");
+            return;
+        }
+        let account_ids: Vec<(T::AccountId, T::Public)> = Self::get_accounts();
+        let accounts = ClientsApplys::<T>::get(&block_number);
+        for acc in accounts.iter() {
+            let mut signer_filter: Vec<T::Public> = Vec::new();
+            for (aid, pk) in account_ids.iter() {
+                if aid == &acc.1 {
+                    signer_filter.push(pk.clone());
+                }
+            }
+            if signer_filter.len() == 0 {
+                continue;
+            }
+            let sender = ClientSender::<T>::get(&acc.0);
+            let fee = ClientDelegateFee::<T>::get(&acc.0);
+            if let Err(e) = Self::apply_single_delegate(&acc.0) {
+                debug::error!("apply_single_delegate error: {:?}", e);
+                // revert changes
+                Signer::<T, T::AuthorityId>::all_accounts()
+                    .with_filter(signer_filter)
+                    .send_signed_transaction(|_acct| Call::unreserve(acc.0.clone(), fee.into()));
+                continue;
+            } else {
+                let balance = T::Currency::repatriate_reserved(
+                    &acc.0,
+                    &sender,
+                    fee.into(),
+                    BalanceStatus::Free,
+                );
+                match balance {
+                    Ok(_b) => {
+                        debug::info!("repatriate reserved finished");
+                    }
+                    Err(e) => {
+                        debug::error!("repatriate reserved: {:?}", e);
+                    }
+                }
+            }
+            let result = Signer::<T, T::AuthorityId>::all_accounts()
+                .with_filter(signer_filter)
+                .send_signed_transaction(|_acct| {
+                    Call::update_delegate_status(acc.0.clone(), acc.1.clone())
+                });
+
+            for (_acc, err) in &result {
+                if err.is_err() {
+                    debug::error!("apply delegate {:?} error: {:?}", &acc.0, err);
+                }
+            }
+        }
+    }
+
+    fn send_errand_tasks(block_number: T::BlockNumber) {
+        let task_array = Self::tick_tasks(block_number);
+        if task_array.is_empty() {
+            debug::info!("no pending bids, just return");
+            return;
+        }
+
+        let signer = Self::worker_signer();
+        if !signer.can_sign() {
+            debug::info!("No local account available when send errand tasks");
+            return;
+        }
+        let account_ids: Vec<(T::AccountId, T::Public)> = Self::get_accounts();
+        if let Some((_, public)) = account_ids.first() {
+            let jitter = Self::send_jitter(&public.encode(), block_number, T::MaxSendJitter::get());
+            if jitter > 0 {
+                debug::info!("delay sending tasks by {} ms", jitter);
+                let deadline = sp_io::offchain::timestamp()
+                    .add(sp_runtime::offchain::Duration::from_millis(jitter));
+                sp_io::offchain::sleep_until(deadline);
+            }
+        }
+        let started_at = sp_io::offchain::timestamp();
+        for item in task_array.iter() {
+            // a failed send earlier in this tick backs off the rest of the kind
+            let kind = TaskKinds::get(&item.errand_id);
+            if !Self::is_kind_healthy(kind, block_number) {
+                continue;
+            }
+            let kind_info = match Self::registered_kind(kind) {
+                Some(info) => info,
+                None => continue,
+            };
+            match T::AccountId::decode(&mut item.client.as_slice()) {
+                Ok(client) => {
+                    let sender = ClientSender::<T>::get(&client);
+                    let fee = ClientDelegateFee::<T>::get(&client);
+
+                    let mut signer_filter: Vec<T::Public> = Vec::new();
+                    for (aid, pk) in account_ids.iter() {
+                        if aid == &sender {
+                            signer_filter.push(pk.clone());
+                        }
+                    }
+                    if signer_filter.len() == 0 {
+                        continue;
+                    }
+                    // assigned tasks are only sent by the node holding the worker key
+                    if let Some(assigned) = AssignedWorkers::<T>::get(&item.errand_id) {
+                        if !account_ids.iter().any(|(aid, _)| aid == &assigned) {
+                            continue;
+                        }
+                    }
+
+                    let proof = match Self::delegation_proof(&client, &sender) {
+                        Ok(proof) => proof,
+                        Err(e) => {
+                            debug::error!("skip task {:?}: {:?}", &item.errand_id, e);
+                            continue;
+                        }
+                    };
+
+                    let cached = Self::cached_result(&item.description_cid, block_number);
+                    let in_flight = Self::is_in_flight(&item.errand_id);
+                    if in_flight {
+                        debug::info!("task {:?} was sent before, skip sending", &item.errand_id);
+                    }
+                    let delegator = ClientDelegator::<T>::get(&client);
+                    match Self::account_to_bytes(&delegator) {
+                        Ok(account) => {
+                            let net_address = ClientNetAddress::<T>::get(&client);
+                            #[cfg(feature = "std")]
+                            if cached.is_none() && !in_flight {
+                                Self::set_in_flight(&item.errand_id, true);
+                                if !task::send_task_to_tea_network(
+                                    &account,
+                                    &item.description_cid,
+                                    &item.errand_id,
+                                    &net_address,
+                                    &proof,
+                                    &kind_info,
+                                ) {
+                                    Self::set_in_flight(&item.errand_id, false);
+                                    Self::back_off_kind(kind, block_number);
+                                    Self::unreserve_fee(&client, fee);
+                                    continue;
+                                }
+                            }
+                        }
+                        Err(e) => debug::error!("decode client error: {:?}", e),
+                    }
+
+                    match T::AccountId::decode(&mut item.client.as_slice()) {
+                        Ok(client) => {
+                            let selected_signer = Signer::<T, T::AuthorityId>::all_accounts()
+                                .with_filter(signer_filter);
+                            if !Self::init_single_errand_task(
+                                &selected_signer,
+                                &client,
+                                &item.description_cid,
+                                &item.errand_id,
+                            ) {
+                                Self::unreserve_fee(&client, fee);
+                            } else {
+                                Self::set_in_flight(&item.errand_id, false);
+                                #[cfg(feature = "std")]
+                                task::clear_send_attempts(&item.errand_id);
+                                if let Some((result, content_type)) = &cached {
+                                    debug::info!(
+                                        "complete errand {:?} from the result cache",
+                                        &item.errand_id
+                                    );
+                                    if let Err(e) = Self::update_single_errand(
+                                        &selected_signer,
+                                        &sender,
+                                        &item.errand_id,
+                                        result,
+                                        content_type,
+                                        &item.description_cid,
+                                    ) {
+                                        debug::error!("complete cached errand error: {:?}", e);
+                                    }
+                                }
+                            }
+                        }
+                        Err(e) => debug::error!("decode account id error: {:?}", e),
+                    }
+                }
+                Err(e) => debug::error!("convert client to str error: {:?}", e),
+            }
+        }
+        debug::info!(
+            "sending {} tasks at height {:?} took {} ms",
+            task_array.len(),
+            block_number,
+            sp_io::offchain::timestamp().diff(&started_at).millis()
+        );
+    }
+
+    /// Milliseconds below `max` the node with key `public` delays sending tasks at
+    /// `block_number` by. Nodes get different delays, each node a different one per block.
+    pub fn send_jitter(public: &[u8], block_number: T::BlockNumber, max: u64) -> u64 {
+        if max == 0 {
+            return 0;
+        }
+        let seed = blake2_128(&(public, block_number).encode());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&seed[..8]);
+        u64::from_le_bytes(bytes) % max
+    }
+
+    /// Blocks below `max` the task of `errand_id` retried at `block_number` is queued again
+    /// after. Errands get different delays, each errand a different one per retry height.
+    pub fn retry_jitter(errand_id: &ErrandId, block_number: T::BlockNumber, max: u32) -> u32 {
+        if max == 0 {
+            return 0;
+        }
+        let seed = blake2_128(&(errand_id, block_number).encode());
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&seed[..4]);
+        u32::from_le_bytes(bytes) % max
+    }
+
+    /// Tasks to send at `block_number` in bid order, at most `KIND_TICK_BUDGET` of each kind
+    /// and `MAX_BIDS_PER_TICK` in total, so a slow service of one kind can't hold up the
+    /// others. Kinds backing off after a failed send are skipped.
+    fn tick_tasks(block_number: T::BlockNumber) -> Vec<TaskInfo> {
+        let mut budgets: BTreeMap<KindId, usize> = BTreeMap::new();
+        let shards = Self::assigned_shards();
+        Self::bid_ordered_tasks(block_number)
+            .into_iter()
+            .filter(|task| {
+                if let Some((count, assigned)) = &shards {
+                    if !assigned.contains(&Self::shard_of(&task.errand_id, *count)) {
+                        return false;
+                    }
+                }
+                let kind = TaskKinds::get(&task.errand_id);
+                if !Self::is_kind_healthy(kind, block_number) {
+                    return false;
+                }
+                let taken = budgets.entry(kind).or_insert(0);
+                *taken += 1;
+                *taken <= KIND_TICK_BUDGET
+            })
+            .take(MAX_BIDS_PER_TICK)
+            .collect()
+    }
+
+    /// Shard of errand `errand_id` among `count` shards.
+    pub fn shard_of(errand_id: &ErrandId, count: u32) -> u32 {
+        let hash = blake2_128(errand_id);
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&hash[..4]);
+        u32::from_le_bytes(bytes) % count.max(1)
+    }
+
+    /// The configured `LOCAL_STORAGE_TASK_SHARDS_KEY`, `None` if tasks of all shards are
+    /// sent.
+    fn assigned_shards() -> Option<(u32, Vec<u32>)> {
+        let shards = StorageValueRef::persistent(LOCAL_STORAGE_TASK_SHARDS_KEY.as_bytes());
+        match shards.get::<(u32, Vec<u32>)>() {
+            Some(Some((count, assigned))) if count > 0 => Some((count, assigned)),
+            _ => None,
+        }
+    }
+
+    /// Metadata of `kind`, `DEFAULT_KIND` is known even if it was never registered.
+    pub fn registered_kind(kind: KindId) -> Option<KindInfo> {
+        KindRegistry::get(kind).or_else(|| {
+            if kind == DEFAULT_KIND {
+                Some(KindInfo {
+                    action_path: DEFAULT_KIND_ACTION_PATH.as_bytes().to_vec(),
+                    default_fee: 0,
+                    fee_multiplier_percent: 0,
+                    http_method: HttpMethod::Post,
+                    accept: Vec::new(),
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Base fee, priority surcharge and kind multiplier component of a task of `kind_info`
+    /// queued with `fee`, summing up to the fee reserved for it. The base is the default fee
+    /// of the kind, anything bid above it is a surcharge for being sent earlier. The kind
+    /// multiplier component is `fee_multiplier_percent` of both.
+    pub fn fee_breakdown(fee: u32, kind_info: &KindInfo) -> (u32, u32, u32) {
+        let fee = if fee == 0 { kind_info.default_fee } else { fee };
+        let base = fee.min(kind_info.default_fee);
+        let kind_component = fee as u64 * kind_info.fee_multiplier_percent as u64 / 100;
+        let kind_component = kind_component.min((u32::max_value() - fee) as u64) as u32;
+        (base, fee - base, kind_component)
+    }
+
+    fn kind_backoff_key(kind: KindId) -> Vec<u8> {
+        [LOCAL_STORAGE_KIND_BACKOFF_PREFIX.as_bytes(), &kind.encode()].concat()
+    }
+
+    fn is_kind_healthy(kind: KindId, block_number: T::BlockNumber) -> bool {
+        let backoff = StorageValueRef::persistent(&Self::kind_backoff_key(kind));
+        match backoff.get::<T::BlockNumber>() {
+            Some(Some(until)) => until <= block_number,
+            _ => true,
+        }
+    }
+
+    /// Skips the tasks of `kind` for `KIND_BACKOFF_BLOCKS` after a failed send.
+    fn back_off_kind(kind: KindId, block_number: T::BlockNumber) {
+        debug::info!("service of kind {} failed, backing off", kind);
+        StorageValueRef::persistent(&Self::kind_backoff_key(kind))
+            .set(&block_number.saturating_add(KIND_BACKOFF_BLOCKS.into()));
+    }
+
+    fn result_cache_key(description_cid: &Cid) -> Vec<u8> {
+        [LOCAL_STORAGE_RESULT_CACHE_PREFIX.as_bytes(), description_cid].concat()
+    }
+
+    /// Result and content type cached for `description_cid`, `None` if there is none or it
+    /// expired by `block_number`. Private errands never use the cache.
+    fn cached_result(
+        description_cid: &Cid,
+        block_number: T::BlockNumber,
+    ) -> Option<(Vec<u8>, Vec<u8>)> {
+        if PrivateErrands::get(description_cid) {
+            return None;
+        }
+        let cache = StorageValueRef::persistent(&Self::result_cache_key(description_cid));
+        match cache.get::<(T::BlockNumber, Vec<u8>, Vec<u8>)>() {
+            Some(Some((expires_at, result, content_type))) if block_number < expires_at => {
+                Some((result, content_type))
+            }
+            _ => None,
+        }
+    }
+
+    /// Caches the result of `description_cid` for `RESULT_CACHE_TTL_BLOCKS`, results of
+    /// private errands are not cached.
+    fn cache_result(
+        description_cid: &Cid,
+        result: &Vec<u8>,
+        content_type: &Vec<u8>,
+        block_number: T::BlockNumber,
+    ) {
+        if PrivateErrands::get(description_cid) {
+            return;
+        }
+        let expires_at = block_number.saturating_add(RESULT_CACHE_TTL_BLOCKS.into());
+        StorageValueRef::persistent(&Self::result_cache_key(description_cid))
+            .set(&(expires_at, result, content_type));
+    }
+
+    fn in_flight_key(errand_id: &ErrandId) -> Vec<u8> {
+        [LOCAL_STORAGE_IN_FLIGHT_PREFIX.as_bytes(), errand_id].concat()
+    }
+
+    /// Whether the task of `errand_id` was sent and its errand is not initialized yet.
+    fn is_in_flight(errand_id: &ErrandId) -> bool {
+        let in_flight = StorageValueRef::persistent(&Self::in_flight_key(errand_id));
+        in_flight.get::<bool>() == Some(Some(true))
+    }
+
+    fn set_in_flight(errand_id: &ErrandId, in_flight: bool) {
+        StorageValueRef::persistent(&Self::in_flight_key(errand_id)).set(&in_flight);
+    }
+
+    /// Tasks of the pending bids confirmed at `block_number`, highest bid first. Tasks are
+    /// confirmed `ConfirmationDepth` blocks after being queued.
+    fn bid_ordered_tasks(block_number: T::BlockNumber) -> Vec<TaskInfo> {
+        let confirmed = block_number.saturating_sub(T::ConfirmationDepth::get());
+        PendingBids::get()
+            .iter()
+            .filter_map(|(_fee, errand_id)| {
+                let queued_at = TaskLocation::<T>::get(errand_id)?;
+                if queued_at > confirmed {
+                    return None;
+                }
+                Self::load_tasks(&queued_at)?
+                    .into_iter()
+                    .find(|task| task.errand_id.eq(errand_id))
+            })
+            .collect()
+    }
+
+    /// Inserts the bid of `errand_id` after all pending bids of at least `fee`.
+    fn insert_bid(errand_id: &ErrandId, fee: u32) {
+        PendingBids::mutate(|bids| {
+            let position = bids
+                .iter()
+                .position(|(bid, _)| *bid < fee)
+                .unwrap_or(bids.len());
+            bids.insert(position, (fee, errand_id.clone()));
+        });
+    }
+
+    fn remove_bid(errand_id: &ErrandId) {
+        PendingBids::mutate(|bids| bids.retain(|(_, id)| !id.eq(errand_id)));
+    }
+
+    /// Drops the bids of tasks queued too long ago to be initialized.
+    fn prune_stale_bids(block_number: T::BlockNumber) -> Weight {
+        let oldest = block_number.saturating_sub(MAX_INIT_ERRAND_DELAY.into());
+        let mut bids = PendingBids::get();
+        let count = bids.len();
+        bids.retain(|(_, errand_id)| match TaskLocation::<T>::get(errand_id) {
+            Some(queued_at) => queued_at >= oldest,
+            None => false,
+        });
+        if bids.len() == count {
+            return T::DbWeight::get().reads(count as Weight + 1);
+        }
+        PendingBids::put(bids);
+        T::DbWeight::get().reads_writes(count as Weight + 1, 1)
+    }
+
+    /// Validates and queues task `description_cid` with `errand_id`, reserving its fee in
+    /// `ReserveMode::OnSubmit`.
+    /// Validation failures only charge `BEGIN_TASK_VALIDATION_WEIGHT`.
+    fn queue_new_task(
+        sender: T::AccountId,
+        client: T::AccountId,
+        description_cid: Cid,
+        fee: u32,
+        labels: Vec<Label>,
+        private: bool,
+        errand_id: ErrandId,
+    ) -> dispatch::DispatchResultWithPostInfo {
+        Self::ensure_task_queueable(&sender, &client, &description_cid, fee, &labels)
+            .map_err(|e| e.with_weight(BEGIN_TASK_VALIDATION_WEIGHT))?;
+
+        let task_info = TaskInfo {
+            client: client.encode(),
+            sender: sender.encode(),
+            description_cid: description_cid.clone(),
+            errand_id: errand_id.clone(),
+            fee,
+        };
+
+        let block_number = frame_system::Module::<T>::block_number();
+        let reserve_on_submit = T::ReserveMode::get() == ReserveMode::OnSubmit;
+        // the tasks of the block are only written back if the closure succeeds, a failed
+        // check leaves them untouched
+        Tasks::<T>::try_mutate(&block_number, |task_array| -> dispatch::DispatchResult {
+            ensure!(
+                !task_array
+                    .iter()
+                    .any(|task| task.description_cid.eq(&description_cid)),
+                Error::<T>::ErrandAlreadyExecuted
+            );
+            task_array
+                .try_push(task_info)
+                .map_err(|_| Error::<T>::TooManyTasksInBlock)?;
+            // reserve fee for commit errand delegator
+            if reserve_on_submit {
+                Self::reserve_fee(&client, fee)?;
+            }
+            ClientTaskFee::<T>::insert(&client, fee);
+            Ok(())
+        })?;
+        PendingTaskCount::mutate(|count| *count = count.saturating_add(1));
+        TaskLocation::<T>::insert(&errand_id, block_number);
+        ErrandFees::insert(&errand_id, fee);
+        if !reserve_on_submit {
+            UnreservedTaskFees::insert(&errand_id, fee);
+        }
+        if let Some(index) = frame_system::Module::<T>::extrinsic_index() {
+            ErrandByExtrinsic::<T>::insert(&block_number, index, &errand_id);
+        }
+        Self::insert_bid(&errand_id, fee);
+
+        if !labels.is_empty() {
+            for label in labels.iter() {
+                ErrandsByLabel::append(label, &errand_id);
+            }
+            ErrandLabels::insert(&errand_id, labels);
+        }
+        if private {
+            PrivateErrands::insert(&description_cid, true);
+        }
+
+        Self::deposit_event(RawEvent::TaskBegan(client, description_cid, sender));
+        Ok(().into())
+    }
+
+    /// Checks `begin_task` runs on description cids.
+    fn validate_cid(cid: &[u8]) -> dispatch::DispatchResult {
+        Self::ensure_bounded(cid, MAX_CID_LENGTH as u32, Error::<T>::CidTooLong)
+    }
+
+    /// Whether `cid` passes `validate_cid`.
+    pub fn is_valid_cid(cid: &[u8]) -> bool {
+        Self::validate_cid(cid).is_ok()
+    }
+
+    /// Checks of `queue_new_task` done before any storage write.
+    fn ensure_task_queueable(
+        sender: &T::AccountId,
+        client: &T::AccountId,
+        description_cid: &Cid,
+        fee: u32,
+        labels: &[Label],
+    ) -> dispatch::DispatchResult {
+        Self::validate_cid(description_cid)?;
+        ensure!(labels.len() <= MAX_LABELS, Error::<T>::TooManyLabels);
+        for label in labels.iter() {
+            Self::ensure_bounded(label, MAX_LABEL_LENGTH as u32, Error::<T>::LabelTooLong)?;
+            ensure!(
+                ErrandsByLabel::decode_len(label).unwrap_or(0) < MAX_ERRANDS_PER_LABEL,
+                Error::<T>::TooManyErrandsForLabel
+            );
+        }
+        ensure!(Clients::<T>::contains_key(client), Error::<T>::ClientNotExist);
+        ensure!(Clients::<T>::get(client), Error::<T>::ClientNotReady);
+        ensure!(!Errands::contains_key(description_cid), Error::<T>::ErrandAlreadyExecuted);
+        ensure!(ClientSender::<T>::contains_key(client), Error::<T>::ClientSenderNotExist);
+        ensure!(*sender == ClientSender::<T>::get(client), Error::<T>::ClientSenderNotExist);
+        ensure!(ClientDelegator::<T>::contains_key(client), Error::<T>::ClientDelegatorNotExist);
+        ensure!(
+            AccountErrands::<T>::decode_len(client).unwrap_or(0) < T::MaxErrandsPerAccount::get() as usize,
+            Error::<T>::TooManyErrandsForAccount
+        );
+        ensure!(fee > 0, Error::<T>::InsufficientFee);
+        let block_number = frame_system::Module::<T>::block_number();
+        ensure!(
+            Tasks::<T>::get(&block_number).len() < T::MaxTasksPerBlock::get() as usize,
+            Error::<T>::TooManyTasksInBlock
+        );
+        Ok(())
+    }
+
+    /// Stores the errand of a queued task, `per_errand_events` selects whether it emits
+    /// `TaskDispatched` and `ErrandInited`.
+    fn insert_errand(
+        client: T::AccountId,
+        errand_id: ErrandId,
+        description_cid: Cid,
+        per_errand_events: bool,
+    ) -> dispatch::DispatchResult {
+        let mut cid_errands = ErrandsByCid::get(&description_cid);
+        // errands queued again after their claim expired are indexed already
+        let requeued = cid_errands.contains(&errand_id);
+        if !requeued {
+            cid_errands
+                .try_push(errand_id.clone())
+                .map_err(|_| Error::<T>::TooManyErrandsForCid)?;
+        }
+        let account_errands = AccountErrands::<T>::get(&client);
+        ensure!(
+            account_errands.contains(&description_cid)
+                || account_errands.len() < T::MaxErrandsPerAccount::get() as usize,
+            Error::<T>::TooManyErrandsForAccount
+        );
+        let worker = ClientSender::<T>::get(&client);
+        // the new errand replaces the one previously stored for the cid, with its claim
+        let replaced = ErrandWorker::<T>::get(&description_cid).as_ref() == Some(&worker);
+        let claims = ActiveClaims::<T>::get(&worker).saturating_sub(replaced as u32);
+        ensure!(claims < T::MaxClaimsPerWorker::get(), Error::<T>::TooManyClaims);
+        if let Some(fee) = UnreservedTaskFees::get(&errand_id) {
+            Self::reserve_fee(&client, fee)?;
+            UnreservedTaskFees::remove(&errand_id);
+        }
+        Self::release_claim(&description_cid);
+        Self::remove_bid(&errand_id);
+
+        ErrandWorker::<T>::insert(&description_cid, &worker);
+        ClaimExpiry::<T>::insert(&description_cid, Self::blocks_later(T::ClaimTimeout::get()));
+        ActiveClaims::<T>::mutate(&worker, |claims| *claims = claims.saturating_add(1));
+        let errand = Errand::new(client.encode(), errand_id.clone(), description_cid.clone());
+        Self::log_status(&errand_id, &errand.status);
+        Errands::insert(description_cid.clone(), errand);
+        ErrandCids::insert(&errand_id, &description_cid);
+        Self::add_processing(description_cid.clone());
+        ErrandsByCid::insert(&description_cid, cid_errands);
+        if !requeued {
+            ErrandsCreatedAt::<T>::append(frame_system::Module::<T>::block_number(), &errand_id);
+        }
+        AccountErrands::<T>::mutate(&client, |errands| {
+            if !errands.contains(&description_cid) {
+                errands.push(description_cid.clone());
+            }
+        });
+
+        if per_errand_events {
+            Self::deposit_event(RawEvent::TaskDispatched(errand_id));
+            Self::deposit_event(RawEvent::ErrandInited(client, description_cid));
+        }
+        Ok(())
+    }
+
+    /// Records the current height as the last activity of `employer`.
+    fn mark_seen(employer: &T::AccountId) {
+        LastSeen::<T>::insert(employer, frame_system::Module::<T>::block_number());
+    }
+
+    /// Marks the quorum errand `description_cid` disputed, its fee goes back to `client`.
+    fn dispute_errand(client: &T::AccountId, description_cid: &Cid) {
+        Errands::mutate(description_cid, |val| {
+            if let Some(errand) = val {
+                errand.status = ErrandStatus::Disputed;
+                Self::log_status(&errand.errand_id, &errand.status);
+            }
+        });
+        ResultSubmissions::<T>::remove(description_cid);
+        Self::remove_processing(description_cid);
+        Self::release_claim(description_cid);
+        Self::unreserve_fee(client, Self::unreleased_fee(description_cid));
+        if let Some(errand) = Errands::get(description_cid) {
+            ErrandFees::remove(&errand.errand_id);
+        }
+        Self::deposit_event(RawEvent::ErrandDisputed(description_cid.clone()));
+    }
+
+    /// Task fee of errand `description_cid` not released yet by its progress reports.
+    fn unreleased_fee(description_cid: &Cid) -> u32 {
+        Errands::get(description_cid).map_or(0, |errand| {
+            let released = ErrandProgress::get(&errand.errand_id).1;
+            ErrandFees::get(&errand.errand_id).saturating_sub(released)
+        })
+    }
+
+    /// Appends `status` at the current height to the status log of `errand_id`, dropping
+    /// the oldest entry of a full log.
+    fn log_status(errand_id: &ErrandId, status: &ErrandStatus) {
+        let block_number = frame_system::Module::<T>::block_number();
+        StatusLog::<T>::mutate(errand_id, |log| {
+            if log.len() >= MAX_STATUS_LOG_LENGTH {
+                log.remove(0);
+            }
+            log.push((status.code(), block_number));
+        });
+    }
+
+    /// Appends `result` at the current height to the versions of `description_cid`,
+    /// dropping the oldest version if there are `MAX_RESULT_VERSIONS` already.
+    fn add_result_version(description_cid: &Cid, result: &Vec<u8>) {
+        let block_number = frame_system::Module::<T>::block_number();
+        ResultVersions::<T>::mutate(description_cid, |versions| {
+            if versions.len() >= MAX_RESULT_VERSIONS {
+                versions.remove(0);
+            }
+            versions.push((block_number, result.clone()));
+        });
+    }
+
+    /// Latest result version of `description_cid` with its completion height, `None` while
+    /// its errand is private.
+    pub fn latest_result(description_cid: &Cid) -> Option<(T::BlockNumber, Vec<u8>)> {
+        Self::public_result_versions(description_cid).pop()
+    }
+
+    /// `ResultVersions` of `description_cid`, empty while its errand is private.
+    pub fn public_result_versions(description_cid: &Cid) -> Vec<(T::BlockNumber, Vec<u8>)> {
+        if PrivateErrands::get(description_cid) {
+            return Vec::new();
+        }
+        ResultVersions::<T>::get(description_cid)
+    }
+
+    fn release_claim(description_cid: &Cid) {
+        ClaimExpiry::<T>::remove(description_cid);
+        ErrandListings::<T>::remove(description_cid);
+        if let Some(worker) = ErrandWorker::<T>::take(description_cid) {
+            ActiveClaims::<T>::mutate(&worker, |claims| *claims = claims.saturating_sub(1));
+        }
+    }
+
+    /// Fails errand `description_cid` with `reason` and `outcome`, refunding the fee not
+    /// released yet to its employer. Returns the refunded fee.
+    fn mark_failed(
+        description_cid: Cid,
+        reason: Vec<u8>,
+        outcome: Outcome,
+    ) -> Result<u32, dispatch::DispatchError> {
+        Errands::mutate(&description_cid, |val| {
+            if let Some(errand) = val {
+                errand.status = ErrandStatus::Failed;
+                Self::log_status(&errand.errand_id, &errand.status);
+            }
+        });
+        Self::remove_processing(&description_cid);
+        Self::release_claim(&description_cid);
+        ErrandFailReasons::insert(&description_cid, &reason);
+        ErrandOutcomes::insert(&description_cid, outcome);
+
+        let mut fee = 0;
+        if let Some(errand) = Errands::get(&description_cid) {
+            // refund the task fee reserved in `begin_task` back to the client
+            let client = Self::bytes_to_account(&mut errand.account_id.as_slice())?;
+            fee = Self::unreleased_fee(&description_cid);
+            Self::unreserve_fee(&client, fee);
+            ErrandFees::remove(&errand.errand_id);
+        } else {
+            debug::error!("found empty errand with cid: {:?}", description_cid);
+        }
+
+        Self::deposit_event(RawEvent::ErrandFailed(description_cid, reason));
+        Ok(fee)
+    }
+
+    /// Whether errand `description_cid` is still processing past the deadline of its claim
+    /// with part of its fee reserved.
+    fn is_stuck(description_cid: &Cid, block_number: T::BlockNumber) -> bool {
+        let processing = match Errands::get(description_cid) {
+            Some(errand) => errand.status == ErrandStatus::Processing,
+            None => false,
+        };
+        let expired = match ClaimExpiry::<T>::get(description_cid) {
+            Some(expiry) => expiry <= block_number,
+            None => false,
+        };
+        processing && expired && Self::unreleased_fee(description_cid) > 0
+    }
+
+    /// Fails with `NotAssignedWorker` if errand `errand_id` is assigned to another worker than
+    /// `worker`.
+    fn ensure_assigned_worker(
+        errand_id: &ErrandId,
+        worker: &T::AccountId,
+    ) -> dispatch::DispatchResult {
+        if let Some(assigned) = AssignedWorkers::<T>::get(errand_id) {
+            ensure!(&assigned == worker, Error::<T>::NotAssignedWorker);
+        }
+        Ok(())
+    }
+
+    /// Moves the claim of errand `description_cid` to `worker`, its expiry doesn't change.
+    fn transfer_claim(description_cid: &Cid, worker: &T::AccountId) {
+        if let Some(previous) = ErrandWorker::<T>::take(description_cid) {
+            ActiveClaims::<T>::mutate(&previous, |claims| *claims = claims.saturating_sub(1));
+        }
+        ErrandWorker::<T>::insert(description_cid, worker);
+        ActiveClaims::<T>::mutate(worker, |claims| *claims = claims.saturating_add(1));
+    }
+
+    /// Account holding the claim of errand `errand_id` while it is listed.
+    pub fn listing_account(errand_id: &ErrandId) -> T::AccountId {
+        T::ModuleId::get().into_sub_account(errand_id)
+    }
+
+    /// Account paid for completing errand `description_cid`, the seller while it is listed.
+    fn claim_holder(description_cid: &Cid) -> Option<T::AccountId> {
+        match ErrandListings::<T>::get(description_cid) {
+            Some((seller, _)) => Some(seller),
+            None => ErrandWorker::<T>::get(description_cid),
+        }
+    }
+
+    /// Cid of the processing errand `errand_id`.
+    fn processing_errand_cid(errand_id: &ErrandId) -> Result<Cid, dispatch::DispatchError> {
+        let description_cid = ErrandCids::get(errand_id).ok_or(Error::<T>::UnknownErrand)?;
+        let errand = Errands::get(&description_cid).ok_or(Error::<T>::ErrandTaskNotExist)?;
+        ensure!(&errand.errand_id == errand_id, Error::<T>::UnknownErrand);
+        ensure!(errand.status == ErrandStatus::Processing, Error::<T>::ErrandAlreadyExecuted);
+        Ok(description_cid)
+    }
+
+    /// Fails with `err` if user supplied `data` is longer than `max` bytes.
+    fn ensure_bounded(data: &[u8], max: u32, err: Error<T>) -> dispatch::DispatchResult {
+        ensure!(data.len() <= max as usize, err);
+        Ok(())
+    }
+
+    /// Removes the task at `index` of the `tasks` queued at `block_number` from the queue,
+    /// what was recorded for it is kept.
+    fn dequeue_task(
+        block_number: &T::BlockNumber,
+        mut tasks: BoundedVec<TaskInfo, T::MaxTasksPerBlock>,
+        index: usize,
+    ) -> TaskInfo {
+        let task = tasks.remove(index);
+        if tasks.is_empty() {
+            Tasks::<T>::remove(block_number);
+        } else {
+            Tasks::<T>::insert(block_number, tasks);
+        }
+        PendingTaskCount::mutate(|count| *count = count.saturating_sub(1));
+        TaskLocation::<T>::remove(&task.errand_id);
+        task
+    }
+
+    /// Takes the task of the errand `errand_id` just initialized off the queue.
+    fn dequeue_initialized_task(errand_id: &ErrandId) {
+        if let Some(block_number) = TaskLocation::<T>::get(errand_id) {
+            let tasks = Tasks::<T>::get(&block_number);
+            if let Some(index) = tasks.iter().position(|task| task.errand_id.eq(errand_id)) {
+                Self::dequeue_task(&block_number, tasks, index);
+            }
+        }
+    }
+
+    /// Removes the task at `index` of the `tasks` queued at `block_number` with what was
+    /// recorded for it, its fee stays reserved.
+    fn unqueue_task(
+        block_number: &T::BlockNumber,
+        tasks: BoundedVec<TaskInfo, T::MaxTasksPerBlock>,
+        index: usize,
+    ) -> TaskInfo {
+        let task = Self::dequeue_task(block_number, tasks, index);
+        let errand_id = &task.errand_id;
+        TaskKinds::remove(errand_id);
+        AssignedWorkers::<T>::remove(errand_id);
+        ErrandFees::remove(errand_id);
+        Self::remove_bid(errand_id);
+        PrivateErrands::remove(&task.description_cid);
+        for label in ErrandLabels::take(errand_id).iter() {
+            ErrandsByLabel::mutate(label, |errands| errands.retain(|id| !id.eq(errand_id)));
+        }
+        task
+    }
+
+    /// Drops the queued task of `errand_id` whose fee `client` can't pay on dispatch.
+    fn drop_unfunded_task(client: T::AccountId, errand_id: ErrandId) {
+        UnreservedTaskFees::remove(&errand_id);
+        if let Some(block_number) = TaskLocation::<T>::get(&errand_id) {
+            let tasks = Tasks::<T>::get(&block_number);
+            if let Some(index) = tasks.iter().position(|task| task.errand_id.eq(&errand_id)) {
+                Self::unqueue_task(&block_number, tasks, index);
+            }
+        }
+        Self::deposit_event(RawEvent::TaskDroppedUnfunded(client, errand_id));
+    }
+
+    /// Finds the task of `errand_id` queued by `begin_task` within the last
+    /// `MAX_INIT_ERRAND_DELAY` blocks.
+    fn find_recent_task(errand_id: &ErrandId, description_cid: &Cid) -> Option<TaskInfo> {
+        let current = frame_system::Module::<T>::block_number();
+        let oldest = current.saturating_sub(MAX_INIT_ERRAND_DELAY.into());
+        let mut height = current;
+        loop {
+            let found = Tasks::<T>::get(&height).into_iter().find(|task| {
+                task.errand_id.eq(errand_id) && task.description_cid.eq(description_cid)
+            });
+            if found.is_some() || height <= oldest {
+                return found;
+            }
+            height = height.saturating_sub(One::one());
+        }
+    }
+
+    /// `ServiceLatency` after `latency` is joined by `count` samples of `avg_ms` on average,
+    /// counting at most `LATENCY_WINDOW_SAMPLES` samples.
+    pub fn rolling_latency(latency: (u32, u32), avg_ms: u32, count: u32) -> (u32, u32) {
+        let (current_ms, current_count) = latency;
+        let total = current_count as u64 + count as u64;
+        let sum = current_ms as u64 * current_count as u64 + avg_ms as u64 * count as u64;
+        ((sum / total) as u32, total.min(LATENCY_WINDOW_SAMPLES as u64) as u32)
+    }
+
+    /// Reports the task requests this node timed since the last report.
+    #[cfg(feature = "std")]
+    fn report_service_latency() {
+        let signer = Self::worker_signer();
+        if !signer.can_sign() {
+            debug::info!("No local account available when report service latency");
+            return;
+        }
+        let (avg_ms, count) = match metrics::take_latency_samples() {
+            Some(samples) => samples,
+            None => return,
+        };
+        let result = signer.send_signed_transaction(|_acct| Call::report_latency(avg_ms, count));
+        for (_acc, err) in &result {
+            if err.is_err() {
+                debug::error!("report service latency error: {:?}", err);
+            }
+        }
+    }
+
+    fn reap_expired_claims(block_number: T::BlockNumber) {
+        let expired: Vec<Cid> = ProcessingErrands::get()
+            .into_iter()
+            .filter(|cid| match ClaimExpiry::<T>::get(cid) {
+                Some(expiry) => expiry <= block_number,
+                None => false,
+            })
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+
+        let signer = Self::worker_signer();
+        if !signer.can_sign() {
+            debug::info!("No local account available when reap expired claims");
+            return;
+        }
+        for cid in expired {
+            let result = signer.send_signed_transaction(|_acct| Call::expire_claim(cid.clone()));
+            for (_acc, err) in &result {
+                if err.is_err() {
+                    debug::error!("expire claim of errand {:?} error: {:?}", &cid, err);
+                }
+            }
+        }
+    }
+
+    /// Reads the tasks queued at `block_number`, logging instead of panicking if the stored
+    /// value can't be decoded so the offchain worker keeps running. An empty array, as
+    /// cancellations used to leave behind, is skipped like a missing one.
+    fn load_tasks(block_number: &T::BlockNumber) -> Option<Vec<TaskInfo>> {
+        let key = Tasks::<T>::hashed_key_for(block_number);
+        let raw = frame_support::storage::unhashed::get_raw(&key)?;
+        match BoundedVec::<TaskInfo, T::MaxTasksPerBlock>::decode(&mut &raw[..]) {
+            Ok(tasks) if tasks.is_empty() => None,
+            Ok(tasks) => Some(tasks.into_inner()),
+            Err(e) => {
+                debug::error!("decode tasks at height {:?} error: {:?}", block_number, e);
+                None
+            }
+        }
+    }
+
+    fn delegation_proof(
+        client: &T::AccountId,
+        worker: &T::AccountId,
+    ) -> Result<Vec<u8>, Error<T>> {
+        Delegations::<T>::get(client, worker).ok_or(Error::<T>::MissingDelegation)
+    }
+
+    /// The canonical message an employer signs to delegate `worker`.
+    pub fn delegation_message(employer: &T::AccountId, worker: &T::AccountId) -> Vec<u8> {
+        (DELEGATION_MESSAGE_PREFIX, employer, worker).encode()
+    }
+
+    fn verify_delegation_proof(
+        employer: &T::AccountId,
+        worker: &T::AccountId,
+        proof: &Vec<u8>,
+    ) -> Result<(), Error<T>> {
+        ensure!(
+            Self::is_signed_by(employer, &Self::delegation_message(employer, worker), proof),
+            Error::<T>::InvalidDelegationProof
+        );
+        Ok(())
+    }
+
+    /// Hash of `result` with `ResultHashAlgo`, committed to by completions.
+    pub fn result_hash(result: &[u8]) -> T::Hash {
+        let hash = T::ResultHashAlgo::get().hash(result);
+        T::Hash::decode(&mut &hash[..]).unwrap_or_default()
+    }
+
+    /// Message the worker of errand `errand_id` signs to prove it produced the result
+    /// hashing to `result_hash`.
+    pub fn completion_message(errand_id: &ErrandId, result_hash: &T::Hash) -> Vec<u8> {
+        (COMPLETION_MESSAGE_PREFIX, errand_id, result_hash).encode()
+    }
+
+    /// Whether `proof` is the signature of `signer` over `message`. Accounts don't tell their
+    /// key type, so like `account_from_seed_in_accounts` both sr25519 and ed25519 are tried.
+    fn is_signed_by(signer: &T::AccountId, message: &[u8], proof: &[u8]) -> bool {
+        if proof.len() != 64 {
+            return false;
+        }
+        let signer_bytes: [u8; 32] = match Self::account_to_bytes(signer) {
+            Ok(account) => account.into(),
+            Err(_) => return false,
+        };
+        let mut signature = [0u8; 64];
+        signature.copy_from_slice(proof);
+        sp_io::crypto::sr25519_verify(
+            &sp_core::sr25519::Signature::from_raw(signature),
+            message,
+            &sp_core::sr25519::Public::from_raw(signer_bytes),
+        ) || sp_io::crypto::ed25519_verify(
+            &sp_core::ed25519::Signature::from_raw(signature),
+            message,
+            &sp_core::ed25519::Public::from_raw(signer_bytes),
+        )
+    }
+
+    /// Account signing the completion of errand `description_cid` of `client` and paid for
+    /// it: the holder of its claim, or the sender of `client` if it has none.
+    fn completion_signer(description_cid: &Cid, client: &T::AccountId) -> T::AccountId {
+        Self::claim_holder(description_cid).unwrap_or_else(|| ClientSender::<T>::get(client))
+    }
+
+    /// Completion signer and errand id of the errand stored for `description_cid`.
+    fn errand_signer(description_cid: &Cid) -> Option<(T::AccountId, ErrandId)> {
+        let errand = Errands::get(description_cid)?;
+        let client = T::AccountId::decode(&mut errand.account_id.as_slice()).ok()?;
+        Some((Self::completion_signer(description_cid, &client), errand.errand_id))
+    }
 
-            Self::apply_delegates(block_number);
-            Self::send_errand_tasks(block_number);
-            Self::query_errand_task_results(block_number);
-            Self::update_errand_task_results(block_number);
+    /// Signs the completion of errand `errand_id` with `result` by `worker` with its sr25519
+    /// or ed25519 key, `None` if the worker key is not in the local keystore.
+    fn sign_completion(
+        worker: &T::AccountId,
+        errand_id: &ErrandId,
+        result: &[u8],
+    ) -> Option<Vec<u8>> {
+        let worker_bytes: [u8; 32] = Self::account_to_bytes(worker).ok()?.into();
+        let message = Self::completion_message(errand_id, &Self::result_hash(result));
+        let sr25519 = sp_core::sr25519::Public::from_raw(worker_bytes);
+        if let Some(signature) = sp_io::crypto::sr25519_sign(KEY_TYPE, &sr25519, &message) {
+            return Some(signature.0.to_vec());
         }
+        let ed25519 = sp_core::ed25519::Public::from_raw(worker_bytes);
+        let signature = sp_io::crypto::ed25519_sign(KEY_TYPE, &ed25519, &message)?;
+        Some(signature.0.to_vec())
     }
-}
 
-impl<T: Trait> Module<T> {
-    fn generate_errand_id(sender: &T::AccountId) -> Vec<u8> {
-        let payload = (
-            <pallet_randomness_collective_flip::Module<T> as Randomness<T::Hash>>::random_seed(),
-            &sender,
-            <frame_system::Module<T>>::extrinsic_index(),
-        );
-        let uuid = Builder::from_bytes(payload.using_encoded(blake2_128))
-            .set_variant(Variant::RFC4122)
-            .set_version(Version::Random)
-            .build();
-        let mut buf = Uuid::encode_buffer();
-        let uuid = uuid.to_hyphenated().encode_lower(&mut buf);
-        uuid.as_bytes().to_vec()
+    fn apply_single_delegate(client: &T::AccountId) -> Result<(), Error<T>> {
+        let net_address = ClientNetAddress::<T>::get(&client);
+        let delegator = ClientDelegator::<T>::get(&client);
+        let account: AccountId32 = Self::account_to_bytes(&delegator)?;
+        #[cfg(feature = "std")]
+        if !delegate::request_single_delegate(account, &net_address) {
+            return Err(Error::<T>::ApplyDelegateError);
+        }
+
+        Ok(())
     }
 
-    fn apply_delegates(block_number: T::BlockNumber) {
-        if !ClientsApplys::<T>::contains_key(&block_number) {
-            debug::info!("height {:?} has no delegates, just return", &block_number);
+    fn schedule_result_retention(description_cid: &Cid) {
+        let retention = T::RetentionBlocks::get();
+        if retention.is_zero() {
             return;
         }
+        ResultRetentionQueue::<T>::append(&Self::blocks_later(retention), description_cid);
+    }
 
-        let signer = Signer::<T, T::AuthorityId>::all_accounts();
-        if !signer.can_sign() {
-            debug::info!("No local account available when apply delegate");
-            return;
+    /// The height `delay` blocks after the current one, saturating at the maximum block number.
+    pub fn future_block(delay: u32) -> T::BlockNumber {
+        Self::blocks_later(delay.into())
+    }
+
+    fn blocks_later(delay: T::BlockNumber) -> T::BlockNumber {
+        frame_system::Module::<T>::block_number().saturating_add(delay)
+    }
+
+    /// Clears the inline results whose retention ends at `block_number`, only their hashes
+    /// in `ErrandResultHashes` are kept. Pinned errands keep their results. Cids beyond
+    /// `MAX_RETENTION_SWEEP_PER_BLOCK` are postponed to the next block.
+    fn sweep_retained_results(block_number: T::BlockNumber) -> Weight {
+        let mut cids = ResultRetentionQueue::<T>::take(&block_number);
+        if cids.is_empty() {
+            return T::DbWeight::get().reads(1);
         }
-        let account_ids: Vec<(T::AccountId, T::Public)> = Self::get_accounts();
-        let accounts = ClientsApplys::<T>::get(&block_number);
-        for acc in accounts.iter() {
-            let mut signer_filter: Vec<T::Public> = Vec::new();
-            for (aid, pk) in account_ids.iter() {
-                if aid == &acc.1 {
-                    signer_filter.push(pk.clone());
-                }
-            }
-            if signer_filter.len() == 0 {
+        if cids.len() > MAX_RETENTION_SWEEP_PER_BLOCK {
+            let postponed = cids.split_off(MAX_RETENTION_SWEEP_PER_BLOCK);
+            ResultRetentionQueue::<T>::mutate(block_number.saturating_add(One::one()), |queue| {
+                queue.extend(postponed)
+            });
+        }
+
+        for cid in cids.iter() {
+            if PinnedErrands::get(cid) {
                 continue;
             }
-            let sender = ClientSender::<T>::get(&acc.0);
-            let fee = ClientDelegateFee::<T>::get(&acc.0);
-            if let Err(e) = Self::apply_single_delegate(&acc.0) {
-                debug::error!("apply_single_delegate error: {:?}", e);
-                // revert changes
-                Signer::<T, T::AuthorityId>::all_accounts()
-                    .with_filter(signer_filter)
-                    .send_signed_transaction(|_acct| Call::unreserve(acc.0.clone(), fee.into()));
-                continue;
-            } else {
-                let balance = T::Currency::repatriate_reserved(
-                    &acc.0,
-                    &sender,
-                    fee.into(),
-                    BalanceStatus::Free,
-                );
-                match balance {
-                    Ok(_b) => {
-                        debug::info!("repatriate reserved finished");
-                    }
-                    Err(e) => {
-                        debug::error!("repatriate reserved: {:?}", e);
-                    }
+            Errands::mutate(cid, |val| {
+                if let Some(errand) = val {
+                    errand.result = Vec::new();
                 }
-            }
-            let result = Signer::<T, T::AuthorityId>::all_accounts()
-                .with_filter(signer_filter)
-                .send_signed_transaction(|_acct| {
-                    Call::update_delegate_status(acc.0.clone(), acc.1.clone())
-                });
+            });
+            ResultIsCid::remove(cid);
+        }
+        let count = cids.len() as Weight;
+        T::DbWeight::get().reads_writes(count + 2, 2 * count + 2)
+    }
 
-            for (_acc, err) in &result {
-                if err.is_err() {
-                    debug::error!("apply delegate {:?} error: {:?}", &acc.0, err);
+    /// Forgets the idempotency keys recorded `IDEMPOTENCY_KEY_LIFETIME` blocks ago.
+    fn sweep_idempotency_keys(block_number: T::BlockNumber) -> Weight {
+        let keys = IdempotencyKeyExpiry::<T>::take(&block_number);
+        for (sender, key) in keys.iter() {
+            IdempotencyKeys::<T>::remove(sender, key);
+        }
+        let count = keys.len() as Weight;
+        T::DbWeight::get().reads_writes(1, count + 1)
+    }
+
+    /// Offchain local storage key of the webhook url of `employer`.
+    pub fn webhook_key(employer: &T::AccountId) -> Vec<u8> {
+        [LOCAL_STORAGE_WEBHOOK_PREFIX.as_bytes(), &employer.encode()].concat()
+    }
+
+    /// Posts the errands completed at `block_number` to the webhooks of their employers.
+    /// Local urls not matching the registered hash are skipped.
+    fn notify_webhooks(block_number: T::BlockNumber) {
+        for (employer, description_cid) in WebhookNotifications::<T>::get(&block_number) {
+            let url = match Self::webhook_url(&employer) {
+                Some(url) => url,
+                None => {
+                    debug::info!("no webhook url of {:?} in local storage", &employer);
+                    continue;
                 }
-            }
+            };
+            #[cfg(feature = "std")]
+            webhook::notify_completion(&url, &description_cid);
         }
     }
 
-    fn send_errand_tasks(block_number: T::BlockNumber) {
-        if !Tasks::<T>::contains_key(&block_number) {
-            debug::info!("height {:?} has no tasks, just return", &block_number);
-            return;
+    // the local webhook url of `employer`, if it matches the registered hash
+    fn webhook_url(employer: &T::AccountId) -> Option<Vec<u8>> {
+        let url = StorageValueRef::persistent(&Self::webhook_key(employer)).get::<Vec<u8>>();
+        let registered = WebhookHashes::<T>::get(employer);
+        match url {
+            Some(Some(url)) if Some(T::Hashing::hash(&url)) == registered => Some(url),
+            _ => None,
         }
+    }
 
-        let signer = Signer::<T, T::AuthorityId>::all_accounts();
-        if !signer.can_sign() {
-            debug::info!("No local account available when send errand tasks");
+    /// Warns the employers of processing errands whose claim expires within
+    /// `ExpiryWarningBlocks`, through their webhook if they registered one. Each claim expiry
+    /// is warned about once.
+    fn warn_expiring_errands(block_number: T::BlockNumber) {
+        let lead_time = T::ExpiryWarningBlocks::get();
+        if lead_time.is_zero() {
             return;
         }
-        let account_ids: Vec<(T::AccountId, T::Public)> = Self::get_accounts();
-        let task_array = Tasks::<T>::get(&block_number);
-        for item in task_array.iter() {
-            match T::AccountId::decode(&mut item.client.as_slice()) {
-                Ok(client) => {
-                    let sender = ClientSender::<T>::get(&client);
-                    let fee = ClientDelegateFee::<T>::get(&client);
-
-                    let mut signer_filter: Vec<T::Public> = Vec::new();
-                    for (aid, pk) in account_ids.iter() {
-                        if aid == &sender {
-                            signer_filter.push(pk.clone());
-                        }
-                    }
-                    if signer_filter.len() == 0 {
-                        continue;
-                    }
-
-                    let delegator = ClientDelegator::<T>::get(&client);
-                    match Self::account_to_bytes(&delegator) {
-                        Ok(account) => {
-                            let net_address = ClientNetAddress::<T>::get(&client);
-                            #[cfg(feature = "std")]
-                            if !task::send_task_to_tea_network(
-                                &account,
-                                &item.description_cid,
-                                &item.errand_id,
-                                &net_address,
-                            ) {
-                                T::Currency::unreserve(&client, fee.into());
-                                continue;
-                            }
-                        }
-                        Err(e) => debug::error!("decode client error: {:?}", e),
-                    }
-
-                    match T::AccountId::decode(&mut item.client.as_slice()) {
-                        Ok(client) => {
-                            let selected_signer = Signer::<T, T::AuthorityId>::all_accounts()
-                                .with_filter(signer_filter);
-                            if !Self::init_single_errand_task(
-                                &selected_signer,
-                                &client,
-                                &item.description_cid,
-                                &item.errand_id,
-                            ) {
-                                T::Currency::unreserve(&client, fee.into());
-                            }
-                        }
-                        Err(e) => debug::error!("decode account id error: {:?}", e),
-                    }
+        for cid in ProcessingErrands::get() {
+            let expiry = match ClaimExpiry::<T>::get(&cid) {
+                Some(expiry)
+                    if expiry > block_number && expiry <= block_number.saturating_add(lead_time) =>
+                {
+                    expiry
                 }
-                Err(e) => debug::error!("convert client to str error: {:?}", e),
+                _ => continue,
+            };
+            let warned = StorageValueRef::persistent(
+                &[LOCAL_STORAGE_EXPIRY_WARNED_PREFIX.as_bytes(), &cid].concat(),
+            );
+            if warned.get::<T::BlockNumber>() == Some(Some(expiry)) {
+                continue;
+            }
+            warned.set(&expiry);
+
+            debug::warn!("claim of errand {:?} expires at {:?}", &cid, expiry);
+            let employer = Errands::get(&cid)
+                .and_then(|errand| Self::bytes_to_account(&errand.account_id).ok());
+            if let Some(url) = employer.and_then(|employer| Self::webhook_url(&employer)) {
+                let expires_at: u64 = expiry.unique_saturated_into();
+                #[cfg(feature = "std")]
+                webhook::notify_expiry(&url, &cid, expires_at);
             }
         }
     }
 
-    fn apply_single_delegate(client: &T::AccountId) -> Result<(), Error<T>> {
-        let net_address = ClientNetAddress::<T>::get(&client);
-        let delegator = ClientDelegator::<T>::get(&client);
-        let account: AccountId32 = Self::account_to_bytes(&delegator)?;
-        #[cfg(feature = "std")]
-        if !delegate::request_single_delegate(account, &net_address) {
-            return Err(Error::<T>::ApplyDelegateError);
+    fn clear_webhook_notifications(block_number: T::BlockNumber) -> Weight {
+        // the offchain worker of the previous block has seen them
+        WebhookNotifications::<T>::remove(block_number.saturating_sub(One::one()));
+        T::DbWeight::get().writes(1)
+    }
+
+    fn is_draining() -> bool {
+        let flag = StorageValueRef::persistent(LOCAL_STORAGE_DRAINING_KEY.as_bytes());
+        match flag.get::<bool>() {
+            Some(Some(draining)) => draining,
+            _ => false,
         }
+    }
 
-        Ok(())
+    fn should_poll(block_number: T::BlockNumber) -> bool {
+        let interval = T::PollInterval::get();
+        !interval.is_zero() && (block_number % interval).is_zero()
     }
 
     fn query_errand_task_results(block_number: T::BlockNumber) {
-        // query every 10 block (about 1 minute)
-        let height: u64 = block_number.try_into().ok().unwrap() as u64;
-        if height % 10 != 0 {
-            return;
-        }
-        let signer = Signer::<T, T::AuthorityId>::all_accounts();
+        let signer = Self::worker_signer();
         if !signer.can_sign() {
             debug::info!("No local account available when query errand task results");
             return;
@@ -576,20 +3405,24 @@ impl<T: Trait> Module<T> {
 
         let processing_errands: Vec<Cid> = ProcessingErrands::get();
         debug::info!(
-            "processing errand counts is {} at height {}",
+            "processing errand counts is {} at height {:?}",
             processing_errands.len(),
-            height
+            block_number
         );
         for item in processing_errands {
             if let Some(errand) = Errands::get(&item) {
                 match T::AccountId::decode(&mut errand.account_id.as_slice()) {
                     Ok(client) => {
                         let net_address = ClientNetAddress::<T>::get(&client);
+                        let accept = Self::registered_kind(TaskKinds::get(&errand.errand_id))
+                            .map(|kind| kind.accept)
+                            .unwrap_or_default();
                         #[cfg(feature = "std")]
                         task::fetch_single_task_result(
                             &errand.errand_id,
                             &errand.description_cid,
                             &net_address,
+                            &accept,
                         );
                     }
                     Err(e) => debug::error!("decode account id error: {:?}", e),
@@ -600,8 +3433,8 @@ impl<T: Trait> Module<T> {
         }
     }
 
-    fn update_errand_task_results(_block_number: T::BlockNumber) {
-        let signer = Signer::<T, T::AuthorityId>::all_accounts();
+    fn update_errand_task_results(block_number: T::BlockNumber) {
+        let signer = Self::worker_signer();
         if !signer.can_sign() {
             debug::info!("No local account available when update errand task results");
             return;
@@ -614,12 +3447,16 @@ impl<T: Trait> Module<T> {
             return;
         }
 
-        if let Err(e) = Self::load_tasks_results_info(&signer) {
+        Self::retry_buffered_completions(&signer);
+        if let Err(e) = Self::load_tasks_results_info(&signer, block_number) {
             debug::error!("load_tasks_results_info error: {:?}", e);
         }
     }
 
-    fn load_tasks_results_info(signer: &Signer<T, T::AuthorityId, ForAll>) -> Result<(), Error<T>> {
+    fn load_tasks_results_info(
+        signer: &Signer<T, T::AuthorityId, ForAll>,
+        block_number: T::BlockNumber,
+    ) -> Result<(), Error<T>> {
         let key = LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes().to_vec();
         let lock_key = LOCAL_STORAGE_TASKS_RESULTS_LOCK.as_bytes().to_vec();
 
@@ -642,8 +3479,49 @@ impl<T: Trait> Module<T> {
             Ok(true) => {
                 match value_ref.get::<Vec<(Cid, ErrandResultInfo)>>() {
                     Some(Some(results)) => {
+                        // results were fetched in an earlier tick, their errands may have
+                        // been finished or reorged out since
+                        let processing: Vec<Cid> = ProcessingErrands::get();
                         for item in results.iter() {
-                            Self::update_single_errand(signer, &item.1.result_cid, &item.0)?;
+                            if !processing.contains(&item.0) {
+                                debug::info!("skip result of errand {:?} no longer processing", &item.0);
+                                continue;
+                            }
+                            match item.1.status.errand_status() {
+                                Some(ErrandStatus::Done)
+                                    if !T::ResultAcceptance::result_acceptable(
+                                        &item.1.result_cid,
+                                    ) =>
+                                {
+                                    debug::info!("reject result of errand {:?}", &item.0)
+                                }
+                                Some(ErrandStatus::Done) => {
+                                    Self::cache_result(
+                                        &item.0,
+                                        &item.1.result_cid,
+                                        &item.1.content_type,
+                                        block_number,
+                                    );
+                                    match Self::errand_signer(&item.0) {
+                                        Some((worker, errand_id)) => Self::update_single_errand(
+                                            signer,
+                                            &worker,
+                                            &errand_id,
+                                            &item.1.result_cid,
+                                            &item.1.content_type,
+                                            &item.0,
+                                        )?,
+                                        None => debug::error!(
+                                            "found empty errand with cid: {:?}",
+                                            &item.0
+                                        ),
+                                    }
+                                }
+                                Some(ErrandStatus::Failed) => {
+                                    Self::fail_single_errand(signer, &item.1.reason, &item.0)?
+                                }
+                                _ => debug::info!("skip non-final result of errand {:?}", &item.0),
+                            }
                         }
 
                         let empty_array: Vec<(Cid, ErrandResultInfo)> = vec![];
@@ -661,17 +3539,139 @@ impl<T: Trait> Module<T> {
 
     fn update_single_errand(
         signer: &Signer<T, T::AuthorityId, ForAll>,
+        worker: &T::AccountId,
+        errand_id: &ErrandId,
         result_cid: &Cid,
+        content_type: &Vec<u8>,
         description_cid: &Cid,
     ) -> Result<(), Error<T>> {
+        let (result_cid, result_is_cid) =
+            if result_cid.len() > T::InlineResultThreshold::get() as usize {
+                match Self::upload_result(result_cid) {
+                    Some(cid) => (cid, true),
+                    // the errand stays processing, its result is queried again next poll
+                    None => {
+                        debug::error!("upload result of errand {:?} failed", description_cid);
+                        return Ok(());
+                    }
+                }
+            } else {
+                (result_cid.clone(), false)
+            };
+        let completion = PendingCompletion {
+            description_cid: description_cid.clone(),
+            proof: Self::sign_completion(worker, errand_id, &result_cid),
+            result: result_cid,
+            content_type: content_type.clone(),
+            result_is_cid,
+        };
+        if !Self::submit_completion(signer, &completion) {
+            Self::buffer_completion(completion);
+        }
+        Ok(())
+    }
+
+    // whether the completion was handed to the transaction pool by all signers
+    fn submit_completion(
+        signer: &Signer<T, T::AuthorityId, ForAll>,
+        completion: &PendingCompletion,
+    ) -> bool {
         let result = signer.send_signed_transaction(|_acct| {
-            Call::update_errand(description_cid.clone(), result_cid.clone())
+            Call::update_errand(
+                completion.description_cid.clone(),
+                completion.result.clone(),
+                completion.content_type.clone(),
+                completion.result_is_cid,
+                completion.proof.clone(),
+            )
         });
 
         for (_acc, err) in &result {
             if err.is_err() {
                 debug::error!(
                     "try update single errand {:?} error: {:?}",
+                    &completion.description_cid,
+                    err
+                );
+            }
+        }
+        !result.is_empty() && result.iter().all(|(_acc, res)| res.is_ok())
+    }
+
+    /// Keeps `completion` to submit it again on the next ticks, replacing an older one of
+    /// the same errand.
+    fn buffer_completion(completion: PendingCompletion) {
+        let description_cid = completion.description_cid.clone();
+        let buffer = StorageValueRef::persistent(LOCAL_STORAGE_PENDING_COMPLETIONS_KEY.as_bytes());
+        let res = buffer.mutate(|pending: Option<Option<Vec<PendingCompletion>>>| {
+            let mut pending = pending.flatten().unwrap_or_default();
+            pending.retain(|p| p.description_cid != completion.description_cid);
+            if pending.len() >= MAX_PENDING_COMPLETIONS {
+                return Err(());
+            }
+            pending.push(completion);
+            Ok(pending)
+        });
+        match res {
+            Ok(Ok(_)) => debug::info!("buffered completion of errand {:?}", &description_cid),
+            _ => debug::error!("buffer completion of errand {:?} failed", &description_cid),
+        }
+    }
+
+    /// Submits the buffered completions again, those failing again stay buffered.
+    fn retry_buffered_completions(signer: &Signer<T, T::AuthorityId, ForAll>) {
+        let buffer = StorageValueRef::persistent(LOCAL_STORAGE_PENDING_COMPLETIONS_KEY.as_bytes());
+        let mut pending: Vec<PendingCompletion> = Vec::new();
+        let res = buffer.mutate(|buffered: Option<Option<Vec<PendingCompletion>>>| {
+            pending = buffered.flatten().unwrap_or_default();
+            Ok::<_, ()>(Vec::<PendingCompletion>::new())
+        });
+        match res {
+            Ok(Ok(_)) => {}
+            // another tick took the buffer concurrently
+            _ => return,
+        }
+
+        let processing: Vec<Cid> = ProcessingErrands::get();
+        for completion in pending {
+            if !processing.contains(&completion.description_cid) {
+                debug::info!(
+                    "drop buffered completion of errand {:?} no longer processing",
+                    &completion.description_cid
+                );
+                continue;
+            }
+            if !Self::submit_completion(signer, &completion) {
+                Self::buffer_completion(completion);
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn upload_result(result: &Vec<u8>) -> Option<Cid> {
+        ipfs::upload(result)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn upload_result(_result: &Vec<u8>) -> Option<Cid> {
+        None
+    }
+
+    fn fail_single_errand(
+        signer: &Signer<T, T::AuthorityId, ForAll>,
+        reason: &Vec<u8>,
+        description_cid: &Cid,
+    ) -> Result<(), Error<T>> {
+        let mut reason = reason.clone();
+        reason.truncate(MAX_FAIL_REASON_LENGTH);
+        let result = signer.send_signed_transaction(|_acct| {
+            Call::fail_errand(description_cid.clone(), reason.clone(), Outcome::Error)
+        });
+
+        for (_acc, err) in &result {
+            if err.is_err() {
+                debug::error!(
+                    "try fail single errand {:?} error: {:?}",
                     description_cid,
                     err
                 );
@@ -711,6 +3711,110 @@ impl<T: Trait> Module<T> {
         ProcessingErrands::put(errands);
     }
 
+    /// Fees `account` still has reserved with this pallet: the unreleased fees of its
+    /// processing errands, `queued_fees` of its queued tasks and its delegate fee while it is
+    /// a client.
+    fn expected_reserve(account: &T::AccountId, queued_fees: u32) -> BalanceOf<T> {
+        let mut expected = queued_fees;
+        for cid in AccountErrands::<T>::get(account).iter() {
+            if let Some(errand) = Errands::get(cid) {
+                if errand.status == ErrandStatus::Processing {
+                    expected = expected.saturating_add(Self::unreleased_fee(cid));
+                }
+            }
+        }
+        if Clients::<T>::contains_key(account) {
+            expected = expected.saturating_add(ClientDelegateFee::<T>::get(account));
+        }
+        expected.into()
+    }
+
+    /// Fees reserved on submit for the queued tasks of each of the encoded `clients`, in one
+    /// pass over `Tasks`.
+    fn queued_task_fees(clients: &[Vec<u8>]) -> BTreeMap<Vec<u8>, u32> {
+        let mut fees = BTreeMap::new();
+        for (_block_number, tasks) in Tasks::<T>::iter() {
+            for task in tasks.iter().filter(|task| clients.contains(&task.client)) {
+                // tasks queued again for processing errands are counted with their errands
+                let reserved = !ErrandCids::contains_key(&task.errand_id)
+                    && !UnreservedTaskFees::contains_key(&task.errand_id);
+                if reserved {
+                    let fee = fees.entry(task.client.clone()).or_insert(0u32);
+                    *fee = fee.saturating_add(task.fee);
+                }
+            }
+        }
+        fees
+    }
+
+    /// Whether `reserve_fee` of `fee` would succeed.
+    fn can_reserve_fee(client: &T::AccountId, fee: u32) -> bool {
+        let reserved = ClientReserved::<T>::get(client).saturating_add(fee.into());
+        reserved <= T::AccountReserveCap::get() && T::Currency::can_reserve(client, fee.into())
+    }
+
+    fn reserve_fee(client: &T::AccountId, fee: u32) -> dispatch::DispatchResult {
+        let reserved = ClientReserved::<T>::get(client).saturating_add(fee.into());
+        ensure!(
+            reserved <= T::AccountReserveCap::get(),
+            Error::<T>::ReserveCapExceeded
+        );
+        T::Currency::reserve(client, fee.into())?;
+        Self::track_reserve(client, fee.into());
+        Self::deposit_event(RawEvent::FeeReserved(client.clone(), fee.into()));
+        debug_assert!(
+            TotalReserved::<T>::get() <= T::Currency::total_issuance(),
+            "reserved fees exceed the total issuance"
+        );
+        Ok(())
+    }
+
+    fn unreserve_fee(client: &T::AccountId, fee: u32) {
+        let remaining = T::Currency::unreserve(client, fee.into());
+        let released = BalanceOf::<T>::from(fee).saturating_sub(remaining);
+        Self::track_release(client, released);
+        Self::deposit_event(RawEvent::FeeRefunded(client.clone(), released));
+    }
+
+    /// Moves `fee` from the reserve of `client` to the free balance of `dest` in one step,
+    /// on error nothing is moved.
+    fn repatriate_fee(
+        client: &T::AccountId,
+        dest: &T::AccountId,
+        fee: u32,
+    ) -> Result<BalanceOf<T>, dispatch::DispatchError> {
+        let remaining =
+            T::Currency::repatriate_reserved(client, dest, fee.into(), BalanceStatus::Free)?;
+        let moved = BalanceOf::<T>::from(fee).saturating_sub(remaining);
+        Self::track_release(client, moved);
+        Self::deposit_event(RawEvent::FeeSettled(client.clone(), dest.clone(), moved));
+        Ok(remaining)
+    }
+
+    fn track_reserve(client: &T::AccountId, amount: BalanceOf<T>) {
+        ClientReserved::<T>::mutate(client, |reserved| *reserved = reserved.saturating_add(amount));
+        TotalReserved::<T>::mutate(|total| *total = total.saturating_add(amount));
+    }
+
+    fn track_release(client: &T::AccountId, amount: BalanceOf<T>) {
+        // never release more from the total than was tracked for the client
+        let released = ClientReserved::<T>::get(client).min(amount);
+        ClientReserved::<T>::mutate(client, |reserved| *reserved = reserved.saturating_sub(released));
+        TotalReserved::<T>::mutate(|total| *total = total.saturating_sub(released));
+    }
+
+    fn ensure_errand_updater(sender: &T::AccountId) -> Result<(), Error<T>> {
+        let sender_account: AccountId32 = Self::account_to_bytes(sender)?;
+        let accounts: Vec<AccountId32> = vec![sender_account];
+
+        #[cfg(feature = "std")]
+        ensure!(
+            task::account_from_seed_in_accounts("Alice", accounts),
+            Error::<T>::NoRightToUpdateErrand
+        );
+        Ok(())
+    }
+
     fn account_to_bytes(account: &T::AccountId) -> Result<AccountId32, Error<T>> {
         let account_vec = account.encode();
         if account_vec.len() != 32 {
@@ -730,22 +3834,49 @@ impl<T: Trait> Module<T> {
         }
     }
 
+    /// Signer of the local keys of registered workers.
+    fn worker_signer() -> Signer<T, T::AuthorityId, ForAll> {
+        let keys = Self::get_accounts().into_iter().map(|(_, public)| public).collect();
+        Signer::<T, T::AuthorityId>::all_accounts().with_filter(keys)
+    }
+
+    /// Local accounts of registered workers with their public keys.
+    /// Only the pinned one if `LOCAL_STORAGE_SIGNER_PUBKEY_KEY` matches one of them.
     fn get_accounts() -> Vec<(T::AccountId, T::Public)> {
+        let workers = RegisteredWorkers::<T>::get();
+        let pinned = Self::pinned_signer_key();
         let mut account_ids: Vec<(T::AccountId, T::Public)> = Vec::new();
         for (_pos, key) in
             <T::AuthorityId as AppCrypto<T::Public, T::Signature>>::RuntimeAppPublic::all()
                 .into_iter()
                 .enumerate()
         {
+            let raw_key = key.to_raw_vec();
             let generic_public =
                 <T::AuthorityId as AppCrypto<T::Public, T::Signature>>::GenericPublic::from(key);
             let public: T::Public = generic_public.into();
             let account_id: T::AccountId = public.clone().into_account();
-            account_ids.push((account_id, public.clone()));
+            if workers.contains(&account_id) {
+                if pinned.as_ref() == Some(&raw_key) {
+                    return vec![(account_id, public)];
+                }
+                account_ids.push((account_id, public.clone()));
+            }
+        }
+        if pinned.is_some() {
+            debug::warn!("pinned signer key is no registered worker key, sign with all keys");
         }
         return account_ids;
     }
 
+    fn pinned_signer_key() -> Option<Vec<u8>> {
+        let key = StorageValueRef::persistent(LOCAL_STORAGE_SIGNER_PUBKEY_KEY.as_bytes());
+        match key.get::<Vec<u8>>() {
+            Some(Some(key)) if !key.is_empty() => Some(key),
+            _ => None,
+        }
+    }
+
     fn get_account_ids() -> Vec<AccountId32> {
         let mut accounts: Vec<AccountId32> = Vec::new();
         for (_pos, key) in
@@ -0,0 +1,90 @@
+use codec::{Decode, Encode};
+use frame_support::debug;
+use sp_runtime::offchain::storage::StorageValueRef;
+
+const LOCAL_STORAGE_METRICS_KEY: &'static str = "local-storage::offchain-metrics";
+const LOCAL_STORAGE_LATENCY_KEY: &'static str = "local-storage::offchain-latency";
+
+/// Counters of the offchain worker activity on this node, they are not part of the
+/// consensus state.
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq, Debug)]
+pub struct OffchainMetrics {
+    pub sent_ok: u64,
+    pub sent_err: u64,
+    pub polled_ok: u64,
+}
+
+pub fn load_metrics() -> OffchainMetrics {
+    let value_ref = StorageValueRef::persistent(LOCAL_STORAGE_METRICS_KEY.as_bytes());
+    match value_ref.get::<OffchainMetrics>() {
+        Some(Some(metrics)) => metrics,
+        _ => Default::default(),
+    }
+}
+
+pub fn record_send(succeed: bool) {
+    update_metrics(|metrics| {
+        if succeed {
+            metrics.sent_ok = metrics.sent_ok.saturating_add(1);
+        } else {
+            metrics.sent_err = metrics.sent_err.saturating_add(1);
+        }
+    });
+}
+
+pub fn record_poll() {
+    update_metrics(|metrics| metrics.polled_ok = metrics.polled_ok.saturating_add(1));
+}
+
+/// Adds a task request taking `millis` milliseconds to the samples of the next latency
+/// report.
+pub fn record_latency(millis: u64) {
+    let value_ref = StorageValueRef::persistent(LOCAL_STORAGE_LATENCY_KEY.as_bytes());
+    let res = value_ref.mutate(|s: Option<Option<(u64, u32)>>| {
+        let (total, count) = s.and_then(|m| m).unwrap_or_default();
+        Ok::<(u64, u32), ()>((total.saturating_add(millis), count.saturating_add(1)))
+    });
+    if let Err(_) = res {
+        debug::error!("record latency failed");
+    }
+}
+
+/// Average milliseconds and number of the task requests recorded since the last call,
+/// `None` if none were.
+pub fn take_latency_samples() -> Option<(u32, u32)> {
+    let value_ref = StorageValueRef::persistent(LOCAL_STORAGE_LATENCY_KEY.as_bytes());
+    let (total, count) = match value_ref.get::<(u64, u32)>() {
+        Some(Some(samples)) => samples,
+        _ => return None,
+    };
+    value_ref.set(&(0u64, 0u32));
+    if count == 0 {
+        return None;
+    }
+    Some(((total / count as u64).min(u32::max_value() as u64) as u32, count))
+}
+
+pub fn log_metrics_summary() {
+    let metrics = load_metrics();
+    debug::info!(
+        "offchain metrics: sent_ok {}, sent_err {}, polled_ok {}",
+        metrics.sent_ok,
+        metrics.sent_err,
+        metrics.polled_ok
+    );
+}
+
+fn update_metrics<F>(f: F)
+where
+    F: FnOnce(&mut OffchainMetrics),
+{
+    let value_ref = StorageValueRef::persistent(LOCAL_STORAGE_METRICS_KEY.as_bytes());
+    let res = value_ref.mutate(|s: Option<Option<OffchainMetrics>>| {
+        let mut metrics = s.and_then(|m| m).unwrap_or_default();
+        f(&mut metrics);
+        Ok::<OffchainMetrics, ()>(metrics)
+    });
+    if let Err(_) = res {
+        debug::error!("update offchain metrics failed");
+    }
+}
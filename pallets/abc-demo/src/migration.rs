@@ -0,0 +1,95 @@
+use crate::{
+    Cid, ClientTaskFee, Errand, ErrandFees, ErrandStatus, Errands, PendingTaskCount, Releases,
+    StorageVersion, Tasks, Trait,
+};
+use codec::Decode;
+use frame_support::{
+    debug, storage::migration::StorageIterator, traits::Get, weights::Weight,
+    IterableStorageMap, StorageMap, StorageValue,
+};
+use sp_std::prelude::*;
+
+/// Re-keys `Errands` from the `twox_64_concat` hasher to `blake2_128_concat`.
+///
+/// Errand keys are user supplied cids, so the non-cryptographic twox hasher allowed callers
+/// to craft colliding storage keys.
+pub fn migrate_errands_to_blake2<T: Trait>() -> Weight {
+    if StorageVersion::get() != Releases::V1_0_0 {
+        return T::DbWeight::get().reads(1);
+    }
+
+    let errands: Vec<(Vec<u8>, Errand)> =
+        StorageIterator::<Errand>::new(b"Abc", b"Errands").drain().collect();
+    let count = errands.len() as Weight;
+    for (key, errand) in errands {
+        // a `twox_64_concat` key suffix is the 8 bytes hash followed by the encoded cid
+        if key.len() < 8 {
+            debug::error!("skip errand with malformed storage key: {:?}", key);
+            continue;
+        }
+        match Cid::decode(&mut &key[8..]) {
+            Ok(cid) => Errands::insert(cid, errand),
+            Err(e) => debug::error!("decode errand key {:?} error: {:?}", key, e),
+        }
+    }
+    StorageVersion::put(Releases::V2_0_0);
+
+    debug::info!("migrated {} errands to blake2_128_concat keys", count);
+    T::DbWeight::get().reads_writes(count + 1, count * 2 + 1)
+}
+
+/// Initializes `PendingTaskCount` from the tasks queued before it was introduced.
+pub fn count_pending_tasks<T: Trait>() -> Weight {
+    if StorageVersion::get() != Releases::V2_0_0 {
+        return T::DbWeight::get().reads(1);
+    }
+
+    let mut entries: Weight = 0;
+    let mut count: u64 = 0;
+    for (_block_number, tasks) in Tasks::<T>::iter() {
+        entries += 1;
+        count += tasks.len() as u64;
+    }
+    PendingTaskCount::put(count);
+    StorageVersion::put(Releases::V3_0_0);
+
+    debug::info!("counted {} pending tasks", count);
+    T::DbWeight::get().reads_writes(entries + 1, 2)
+}
+
+/// Records the fees of the tasks queued and the errands processing before `ErrandFees`
+/// existed. Processing errands get the last task fee of their client, the only fee kept for
+/// them so far.
+pub fn record_errand_fees<T: Trait>() -> Weight {
+    if StorageVersion::get() != Releases::V3_0_0 {
+        return T::DbWeight::get().reads(1);
+    }
+
+    let mut reads: Weight = 0;
+    let mut count: Weight = 0;
+    for (_block_number, tasks) in Tasks::<T>::iter() {
+        reads += 1;
+        for task in tasks.iter() {
+            ErrandFees::insert(&task.errand_id, task.fee);
+            count += 1;
+        }
+    }
+    for (_cid, errand) in Errands::iter() {
+        reads += 1;
+        if errand.status != ErrandStatus::Processing {
+            continue;
+        }
+        match T::AccountId::decode(&mut errand.account_id.as_slice()) {
+            Ok(client) => {
+                ErrandFees::insert(&errand.errand_id, ClientTaskFee::<T>::get(&client));
+                reads += 1;
+                count += 1;
+            }
+            Err(e) => debug::error!("decode client of errand {:?} error: {:?}", errand, e),
+        }
+    }
+    StorageVersion::put(Releases::V4_0_0);
+
+    debug::info!("recorded the fees of {} errands", count);
+    T::DbWeight::get().reads_writes(reads + 1, count + 1)
+}
@@ -1,20 +1,51 @@
 use super::*;
 use crate::*;
-use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+use frame_support::{
+    assert_ok,
+    dispatch::DispatchResultWithPostInfo,
+    impl_outer_event, impl_outer_origin, parameter_types,
+    traits::{OffchainWorker, Randomness, UnfilteredDispatchable},
+    weights::Weight,
+};
 use frame_system as system;
 use pallet_balances;
+use parking_lot::RwLock;
+use std::cell::RefCell;
 use sp_core::crypto::Ss58Codec;
-use sp_core::H256;
+use sp_core::offchain::{
+    testing::{self, OffchainState, PoolState},
+    OffchainExt, TransactionPoolExt,
+};
+use sp_core::testing::KeyStore;
+use sp_core::traits::KeystoreExt;
+use codec::Decode;
+use sp_core::{Pair, H256};
 use sp_runtime::{
     testing::{Header, TestXt},
     traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentifyAccount, IdentityLookup, Verify},
-    MultiSignature, Perbill,
+    ModuleId, MultiSignature, Perbill,
 };
+use std::sync::Arc;
+
+pub const ACCOUNT1: &str = "5G97JLuuT1opraWvfS6Smt4jaAZuyDquP9GjamKVcPC366qU";
+pub const ACCOUNT2: &str = "5EPhGXBymJfrcvT2apo6E9tAm2xpNf8Y77zLxd4zjCBdVJeS";
 
 impl_outer_origin! {
     pub enum Origin for Test {}
 }
 
+mod abc {
+    pub use crate::Event;
+}
+
+impl_outer_event! {
+    pub enum TestEvent for Test {
+        system<T>,
+        pallet_balances<T>,
+        abc<T>,
+    }
+}
+
 // Configure a mock runtime to test the pallet.
 
 #[derive(Clone, Eq, PartialEq)]
@@ -38,7 +69,7 @@ impl frame_system::Trait for Test {
     type AccountId = AccountId;
     type Lookup = IdentityLookup<Self::AccountId>;
     type Header = Header;
-    type Event = ();
+    type Event = TestEvent;
     type BlockHashCount = BlockHashCount;
     type MaximumBlockWeight = MaximumBlockWeight;
     type DbWeight = ();
@@ -58,24 +89,176 @@ impl frame_system::Trait for Test {
 impl pallet_balances::Trait for Test {
     type Balance = u64;
     type DustRemoval = ();
-    type Event = ();
+    type Event = TestEvent;
     type ExistentialDeposit = ExistentialDeposit;
     type AccountStore = System;
     type WeightInfo = ();
     type MaxLocks = ();
 }
 
+parameter_types! {
+    pub const PollInterval: u64 = 10;
+    pub const RetentionBlocks: u64 = 5;
+    pub const MaxClaimsPerWorker: u32 = 3;
+    pub const ClaimTimeout: u64 = 20;
+    pub const MaxDeadline: u64 = 50;
+    pub const MaxErrandsPerAccount: u32 = 8;
+    pub const InlineResultThreshold: u32 = 16;
+    pub const ConfirmationDepth: u64 = 2;
+    pub const MaxTasksPerBlock: u32 = 16;
+    pub const AccountReserveCap: u64 = 500;
+    pub const RecoveryDelay: u64 = 30;
+    pub const ExpiryWarningBlocks: u64 = 5;
+    pub const AbcModuleId: ModuleId = ModuleId(*b"abc/demo");
+    pub const ResultHashAlgo: HashAlgo = HashAlgo::Blake2_256;
+    // sleeping is not supported by the offchain test externalities
+    pub const MaxSendJitter: u64 = 0;
+}
+
+impl pallet_randomness_collective_flip::Trait for Test {}
+
+thread_local! {
+    static RANDOM_SEED: RefCell<Option<H256>> = RefCell::new(None);
+}
+
+// collective flip randomness unless a fixed seed was set with `set_random_seed`
+pub struct TestRandomness;
+
+impl Randomness<H256> for TestRandomness {
+    fn random(subject: &[u8]) -> H256 {
+        match RANDOM_SEED.with(|seed| *seed.borrow()) {
+            Some(seed) => seed,
+            None => pallet_randomness_collective_flip::Module::<Test>::random(subject),
+        }
+    }
+
+    fn random_seed() -> H256 {
+        match RANDOM_SEED.with(|seed| *seed.borrow()) {
+            Some(seed) => seed,
+            None => pallet_randomness_collective_flip::Module::<Test>::random_seed(),
+        }
+    }
+}
+
+pub fn set_random_seed(seed: H256) {
+    RANDOM_SEED.with(|value| *value.borrow_mut() = Some(seed));
+}
+
 impl Trait for Test {
     type Currency = pallet_balances::Module<Test>;
-    type Event = ();
+    type Event = TestEvent;
     type AuthorityId = crypto::AuthId;
     type Call = Call<Test>;
+    type PollInterval = PollInterval;
+    type Randomness = TestRandomness;
+    type RetentionBlocks = RetentionBlocks;
+    type MaxClaimsPerWorker = MaxClaimsPerWorker;
+    type ClaimTimeout = ClaimTimeout;
+    type MaxDeadline = MaxDeadline;
+    type MaxErrandsPerAccount = MaxErrandsPerAccount;
+    type InlineResultThreshold = InlineResultThreshold;
+    type ConfirmationDepth = ConfirmationDepth;
+    type OnErrandCompleted = RecordCompletions;
+    type MaxTasksPerBlock = MaxTasksPerBlock;
+    type AccountReserveCap = AccountReserveCap;
+    type RecoveryDelay = RecoveryDelay;
+    type ResultAcceptance = RejectEmptyResults;
+    type BatchInitEvents = BatchInitEvents;
+    type UnsignedPriority = UnsignedPriority;
+    type ExpiryWarningBlocks = ExpiryWarningBlocks;
+    type ModuleId = AbcModuleId;
+    type ResultHashAlgo = ResultHashAlgo;
+    type MaxSendJitter = MaxSendJitter;
+    type MaxRetryJitter = MaxRetryJitter;
+    type ReserveMode = TestReserveMode;
+}
+
+thread_local! {
+    static COMPLETIONS: RefCell<Vec<(Vec<u8>, Vec<u8>)>> = RefCell::new(Vec::new());
+}
+
+// records the errand ids and results it is called with, see `completions`
+pub struct RecordCompletions;
+
+impl OnErrandCompleted for RecordCompletions {
+    fn on_completed(errand_id: &[u8], result: &[u8]) {
+        COMPLETIONS.with(|completions| {
+            completions
+                .borrow_mut()
+                .push((errand_id.to_vec(), result.to_vec()))
+        });
+    }
+}
+
+pub fn completions() -> Vec<(Vec<u8>, Vec<u8>)> {
+    COMPLETIONS.with(|completions| completions.borrow().clone())
+}
+
+thread_local! {
+    static BATCH_INIT_EVENTS: RefCell<bool> = RefCell::new(false);
+}
+
+// per errand init events are emitted unless a test turns them off
+pub struct BatchInitEvents;
+
+impl frame_support::traits::Get<bool> for BatchInitEvents {
+    fn get() -> bool {
+        BATCH_INIT_EVENTS.with(|batch| *batch.borrow())
+    }
+}
+
+pub fn set_batch_init_events(batch: bool) {
+    BATCH_INIT_EVENTS.with(|value| *value.borrow_mut() = batch);
+}
+
+thread_local! {
+    static MAX_RETRY_JITTER: RefCell<u32> = RefCell::new(0);
+}
+
+// expired claims are queued again right away unless a test sets a jitter
+pub struct MaxRetryJitter;
+
+impl frame_support::traits::Get<u32> for MaxRetryJitter {
+    fn get() -> u32 {
+        MAX_RETRY_JITTER.with(|jitter| *jitter.borrow())
+    }
+}
+
+pub fn set_max_retry_jitter(max: u32) {
+    MAX_RETRY_JITTER.with(|value| *value.borrow_mut() = max);
+}
+
+thread_local! {
+    static RESERVE_MODE: RefCell<ReserveMode> = RefCell::new(ReserveMode::OnSubmit);
+}
+
+// fees are reserved on submit unless a test reserves them on dispatch
+pub struct TestReserveMode;
+
+impl frame_support::traits::Get<ReserveMode> for TestReserveMode {
+    fn get() -> ReserveMode {
+        RESERVE_MODE.with(|mode| *mode.borrow())
+    }
+}
+
+pub fn set_reserve_mode(mode: ReserveMode) {
+    RESERVE_MODE.with(|value| *value.borrow_mut() = mode);
+}
+
+pub struct RejectEmptyResults;
+
+impl ResultAcceptance for RejectEmptyResults {
+    fn result_acceptable(result: &[u8]) -> bool {
+        !result.is_empty()
+    }
 }
 
 pub type System = system::Module<Test>;
+pub type Balances = pallet_balances::Module<Test>;
 pub type TemplateModule = Module<Test>;
 
-type Extrinsic = TestXt<Call<Test>, ()>;
+// the signer is kept as extra data so pool transactions can be dispatched as signed calls
+type Extrinsic = TestXt<Call<Test>, AccountId>;
 type Signature = MultiSignature;
 type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
 
@@ -99,13 +282,25 @@ where
     fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
         call: Call<Test>,
         _public: <Signature as Verify>::Signer,
-        _account: AccountId,
+        account: AccountId,
         nonce: u64,
     ) -> Option<(Call<Test>, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
-        Some((call, (nonce, ())))
+        if POOL_UNAVAILABLE.with(|unavailable| *unavailable.borrow()) {
+            return None;
+        }
+        Some((call, (nonce, account)))
     }
 }
 
+thread_local! {
+    static POOL_UNAVAILABLE: RefCell<bool> = RefCell::new(false);
+}
+
+// makes signed transactions fail to be created, as if the chain was unreachable
+pub fn set_pool_unavailable(unavailable: bool) {
+    POOL_UNAVAILABLE.with(|value| *value.borrow_mut() = unavailable);
+}
+
 parameter_types! {
     pub const GracePeriod: u64 = 5;
     pub const UnsignedInterval: u64 = 128;
@@ -120,20 +315,221 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
 
     let _ = pallet_balances::GenesisConfig::<Test> {
         balances: vec![
-            (
-                AccountId32::from_string("5G97JLuuT1opraWvfS6Smt4jaAZuyDquP9GjamKVcPC366qU")
-                    .unwrap(),
-                1000,
-            ),
-            (
-                AccountId32::from_string("5EPhGXBymJfrcvT2apo6E9tAm2xpNf8Y77zLxd4zjCBdVJeS")
-                    .unwrap(),
-                2000,
-            ),
+            (AccountId32::from_string(ACCOUNT1).unwrap(), 1000),
+            (AccountId32::from_string(ACCOUNT2).unwrap(), 2000),
         ],
     }
     .assimilate_storage(&mut t)
     .unwrap();
+    crate::GenesisConfig::default()
+        .assimilate_storage::<Test>(&mut t)
+        .unwrap();
 
     t.into()
 }
+
+pub struct OffchainTestExt {
+    pub ext: sp_io::TestExternalities,
+    pub offchain_state: Arc<RwLock<OffchainState>>,
+    pub pool_state: Arc<RwLock<PoolState>>,
+}
+
+// test externalities with offchain, transaction pool and keystore extensions, the keystore
+// holds the sr25519 keys of `seeds`
+pub fn new_offchain_test_ext(seeds: &[&str]) -> OffchainTestExt {
+    let (offchain, offchain_state) = testing::TestOffchainExt::new();
+    let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+    let keystore = KeyStore::new();
+    for seed in seeds {
+        keystore
+            .write()
+            .sr25519_generate_new(crate::KEY_TYPE, Some(seed))
+            .unwrap();
+    }
+
+    let mut ext = new_test_ext();
+    ext.register_extension(OffchainExt::new(offchain));
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.register_extension(KeystoreExt(keystore));
+    OffchainTestExt {
+        ext,
+        offchain_state,
+        pool_state,
+    }
+}
+
+pub fn alice() -> AccountId32 {
+    let public: [u8; 32] = sp_core::sr25519::Pair::from_string("//Alice", None)
+        .unwrap()
+        .public()
+        .into();
+    AccountId32::from(public)
+}
+
+// allows the offchain worker to sign with the local keys of `worker`
+pub fn register_worker(worker: &AccountId) {
+    assert_ok!(TemplateModule::register_worker(Origin::root(), worker.clone()));
+}
+
+// registers ACCOUNT2 as a ready client of ACCOUNT1
+pub fn register_client(fee: u32) {
+    let sender = Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap());
+    let sender_account = AccountId32::from_string(ACCOUNT1).unwrap();
+    let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+    assert_ok!(TemplateModule::request_delegate(
+        sender.clone(),
+        client_account.clone(),
+        sender_account.clone(),
+        vec![0u8],
+        fee
+    ));
+    assert_ok!(TemplateModule::update_delegate_status(
+        sender,
+        client_account,
+        sender_account
+    ));
+}
+
+// queues task `cid` of the registered client and returns the generated errand id
+pub fn queue_task(cid: &Vec<u8>, fee: u32, private: bool) -> Vec<u8> {
+    queue_task_of(
+        &AccountId32::from_string(ACCOUNT1).unwrap(),
+        &AccountId32::from_string(ACCOUNT2).unwrap(),
+        cid,
+        fee,
+        private,
+    )
+}
+
+pub fn queue_task_of(
+    sender: &AccountId,
+    client: &AccountId,
+    cid: &Vec<u8>,
+    fee: u32,
+    private: bool,
+) -> Vec<u8> {
+    queue_task_of_kind(sender, client, cid, fee, private, DEFAULT_KIND)
+}
+
+// registers `kind` with the action and method of `DEFAULT_KIND`
+pub fn register_kind(kind: u32, default_fee: u32) {
+    assert_ok!(TemplateModule::register_kind(
+        Origin::root(),
+        kind,
+        KindInfo {
+            action_path: DEFAULT_KIND_ACTION_PATH.as_bytes().to_vec(),
+            default_fee,
+            fee_multiplier_percent: 0,
+            http_method: HttpMethod::Post,
+            accept: Vec::new(),
+        }
+    ));
+}
+
+pub fn queue_task_of_kind(
+    sender: &AccountId,
+    client: &AccountId,
+    cid: &Vec<u8>,
+    fee: u32,
+    private: bool,
+    kind: u32,
+) -> Vec<u8> {
+    if TemplateModule::registered_kind(kind).is_none() {
+        register_kind(kind, 0);
+    }
+    assert_ok!(TemplateModule::begin_task(
+        Origin::signed(sender.clone()),
+        client.clone(),
+        cid.clone(),
+        fee,
+        Vec::new(),
+        private,
+        None,
+        kind,
+        None
+    ));
+    Tasks::<Test>::get(System::block_number())
+        .into_iter()
+        .find(|task| task.description_cid.eq(cid))
+        .unwrap()
+        .errand_id
+}
+
+pub fn init_queued_errand(cid: &Vec<u8>, errand_id: &Vec<u8>) {
+    assert_ok!(TemplateModule::init_errand(
+        Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+        AccountId32::from_string(ACCOUNT2).unwrap(),
+        errand_id.clone(),
+        cid.clone()
+    ));
+}
+
+// registers ACCOUNT2 as a ready client of ACCOUNT1 and brings errand `cid` into processing
+pub fn init_processing_errand(cid: &Vec<u8>, fee: u32) -> Vec<u8> {
+    init_errand_with_privacy(cid, fee, false)
+}
+
+pub fn init_errand_with_privacy(cid: &Vec<u8>, fee: u32, private: bool) -> Vec<u8> {
+    register_client(fee);
+    let errand_id = queue_task(cid, fee, private);
+    init_queued_errand(cid, &errand_id);
+    errand_id
+}
+
+// completes errand `cid` with `result` the way the result updater does
+pub fn complete(cid: &Vec<u8>, result: &[u8]) {
+    assert_ok!(TemplateModule::update_errand(
+        Origin::signed(alice()),
+        cid.clone(),
+        result.to_vec(),
+        Vec::new(),
+        false,
+        None
+    ));
+}
+
+// errand ids of tasks queued by the same sender differ only by their extrinsic index
+pub fn set_extrinsic_index(index: u32) {
+    frame_support::storage::unhashed::put(
+        sp_core::storage::well_known_keys::EXTRINSIC_INDEX,
+        &index,
+    );
+}
+
+// runs the offchain worker at `block` as the node would after importing it
+pub fn run_offchain_at(block: u64) {
+    System::set_block_number(block);
+    TemplateModule::offchain_worker(block);
+}
+
+// dispatches and removes the transactions submitted to the pool, signed ones with the
+// signer origin and unsigned ones with the none origin
+pub fn apply_pool_transactions(pool_state: &RwLock<PoolState>) -> Vec<DispatchResultWithPostInfo> {
+    let transactions = std::mem::take(&mut pool_state.write().transactions);
+    transactions
+        .iter()
+        .map(|raw| {
+            let xt = Extrinsic::decode(&mut &raw[..]).unwrap();
+            let origin = match xt.signature {
+                Some((_nonce, account)) => Origin::signed(account),
+                None => Origin::none(),
+            };
+            xt.call.dispatch_bypass_filter(origin)
+        })
+        .collect()
+}
+
+// expects a task service post request to `uri` answered with `data`
+pub fn expect_post(offchain_state: &RwLock<OffchainState>, uri: String, data: &str) {
+    offchain_state
+        .write()
+        .expect_request(testing::PendingRequest {
+            method: "POST".into(),
+            uri,
+            body: b"post body".to_vec(),
+            headers: vec![("User-Agent".into(), "tearust".into())],
+            response: Some(format!(r#"{{"data":"{}"}}"#, data).into_bytes()),
+            sent: true,
+            ..Default::default()
+        });
+}
@@ -0,0 +1,48 @@
+use crate::{Errand, ErrandStatus};
+use alt_serde::Serialize;
+
+/// Errand as served over RPC. Unlike the storage struct its field names are fixed whatever
+/// the struct fields are called, byte vectors are hex encoded and the fields are always
+/// serialized in this order, so clients can rely on the exact JSON.
+#[serde(crate = "alt_serde")]
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct ErrandJson {
+    #[serde(rename = "errand_id")]
+    pub errand_id: String,
+    #[serde(rename = "description_cid")]
+    pub description_cid: String,
+    /// SCALE encoded account of the employer.
+    #[serde(rename = "account_id")]
+    pub account_id: String,
+    /// `processing`, `done`, `failed` or `disputed`.
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "result")]
+    pub result: String,
+}
+
+impl From<&Errand> for ErrandJson {
+    fn from(errand: &Errand) -> Self {
+        ErrandJson {
+            errand_id: hex::encode(&errand.errand_id),
+            description_cid: hex::encode(&errand.description_cid),
+            account_id: hex::encode(&errand.account_id),
+            status: status_name(&errand.status).into(),
+            result: hex::encode(&errand.result),
+        }
+    }
+}
+
+/// JSON of `errand` as served over RPC.
+pub fn errand_json(errand: &Errand) -> String {
+    serde_json::to_string(&ErrandJson::from(errand)).expect("errand json only has strings")
+}
+
+fn status_name(status: &ErrandStatus) -> &'static str {
+    match status {
+        ErrandStatus::Processing => "processing",
+        ErrandStatus::Done => "done",
+        ErrandStatus::Failed => "failed",
+        ErrandStatus::Disputed => "disputed",
+    }
+}
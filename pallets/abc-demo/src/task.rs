@@ -1,25 +1,61 @@
-use crate::delegate::{get_url, load_delegate_info, DelegateInfo};
+use crate::delegate::{action_path, get_url, load_delegate_info, DelegateInfo};
 use crate::error::AbcError;
-use crate::http::http_post;
+use crate::http::{
+    http_post_for_response, http_request_with_method, HttpResponse, DEFAULT_POST_BODY,
+};
+use crate::metrics::{record_latency, record_poll, record_send};
 use crate::storage::operate_local_storage;
 use crate::{
-    Cid, ErrandId, ErrandResultInfo, NetAddress, LOCAL_STORAGE_TASKS_RESULTS_KEY,
-    LOCAL_STORAGE_TASKS_RESULTS_LOCK,
+    Cid, ErrandId, ErrandResultInfo, KindInfo, NetAddress, DEFAULT_CONTENT_TYPE,
+    LOCAL_STORAGE_TASKS_RESULTS_KEY, LOCAL_STORAGE_TASKS_RESULTS_LOCK, MAX_CONTENT_TYPE_LENGTH,
+    MAX_RESULT_LENGTH,
 };
 use frame_support::debug;
 use sp_core::crypto::AccountId32;
 use sp_core::Pair;
+use sp_runtime::offchain::storage::StorageValueRef;
+
+const QUERY_ERRAND_RESULT_ACTION: &'static str = "/query_errand_execution_result_by_uuid";
+
+/// Results arrive escaped inside the JSON of a query response, so responses may be several
+/// times longer than the longest result they carry.
+pub const MAX_RESULT_RESPONSE_LENGTH: usize = 8 * MAX_RESULT_LENGTH;
+
+/// Task request urls longer than this move their content into the post body.
+pub const MAX_URL_LENGTH: usize = 2048;
+
+/// Content type of post bodies carrying the content of a task request.
+pub const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
 
-const QUERY_ERRAND_RESULT_ACTION: &'static str = "/api/query_errand_execution_result_by_uuid";
-const SEND_ERRAND_TASK_ACTION: &'static str = "/api/service";
+/// When set to `true` tasks requests are only logged instead of being sent.
+pub const LOCAL_STORAGE_DRY_RUN_KEY: &'static str = "abc-demo::dry-run";
+/// API key of the TEA service, sent as a bearer token with task requests. It is node local
+/// and must never be stored on chain.
+pub const LOCAL_STORAGE_SERVICE_AUTH_TOKEN_KEY: &'static str = "abc-demo::service-auth-token";
+/// When set to `true` task requests carry `Connection: keep-alive`. Offchain http requests
+/// are scoped to a single request, so this is only a hint letting the service and proxies
+/// keep the connection open for the next tasks of a tick. Whether the node reuses it is up
+/// to its http client.
+pub const LOCAL_STORAGE_KEEP_ALIVE_KEY: &'static str = "abc-demo::keep-alive";
+/// When set to `true` task requests carry an `Idempotency-Key` header of the errand id and
+/// the number of the send attempt, see `idempotency_key`.
+pub const LOCAL_STORAGE_IDEMPOTENCY_KEY_KEY: &'static str = "abc-demo::idempotency-key";
+/// Prefix of the local storage keys counting the sends of a task, followed by its errand id.
+pub const LOCAL_STORAGE_SEND_ATTEMPTS_PREFIX: &'static str = "abc-demo::send-attempts-";
 
+/// Polls the result of errand `errand_id`, whose task asked for results in the `accept`
+/// format.
 pub fn fetch_single_task_result(
     errand_id: &ErrandId,
     description_cid: &Cid,
     net_address: &NetAddress,
+    accept: &[u8],
 ) -> bool {
-    match fetch_errand_result_info(errand_id, description_cid, net_address) {
-        Ok(result) => result,
+    match fetch_errand_result_info(errand_id, description_cid, net_address, accept) {
+        Ok(result) => {
+            record_poll();
+            result
+        }
         Err(e) => {
             debug::error!("query_result_from_http error: {}", e);
             false
@@ -31,15 +67,26 @@ fn fetch_errand_result_info(
     errand_id: &ErrandId,
     description_cid: &Cid,
     net_address: &NetAddress,
+    accept: &[u8],
 ) -> anyhow::Result<bool> {
-    let resp_bytes = http_query_task_result(errand_id, &net_address)?;
-    let resp_str = String::from_utf8(resp_bytes)?;
-    let result_info: ErrandResultInfo = serde_json::from_str::<ErrandResultInfo>(&resp_str)
+    let resp = http_query_task_result(errand_id, &net_address)?;
+    let resp_str = String::from_utf8(resp.data)?;
+    let mut result_info: ErrandResultInfo = serde_json::from_str::<ErrandResultInfo>(&resp_str)
         .map_err(|e| AbcError::Common(format!("{}", e)))?;
+    result_info.content_type = result_content_type(resp.content_type, accept);
     if result_info.completed != true {
         debug::info!("errand is not completed");
         return Ok(true);
     }
+    if result_info.status.errand_status().is_none() {
+        // partial results are not final, keep the errand processing and poll again later
+        debug::info!(
+            "errand {} got partial result {}",
+            hex::encode(errand_id),
+            hex::encode(&result_info.result_cid)
+        );
+        return Ok(true);
+    }
 
     let key = LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes().to_vec();
     let lock_key = LOCAL_STORAGE_TASKS_RESULTS_LOCK.as_bytes().to_vec();
@@ -59,18 +106,36 @@ fn fetch_errand_result_info(
     Ok(true)
 }
 
+/// Content type of a result, the `accept` format the task asked for if the service sent
+/// none or one longer than `MAX_CONTENT_TYPE_LENGTH`, `DEFAULT_CONTENT_TYPE` if it asked for
+/// none either.
+fn result_content_type(header: Option<String>, accept: &[u8]) -> Vec<u8> {
+    match header {
+        Some(value) if !value.is_empty() && value.len() <= MAX_CONTENT_TYPE_LENGTH => {
+            value.into_bytes()
+        }
+        _ if !accept.is_empty() => accept.to_vec(),
+        _ => DEFAULT_CONTENT_TYPE.to_vec(),
+    }
+}
+
 fn http_query_task_result(
     errand_id: &ErrandId,
     net_address: &NetAddress,
-) -> anyhow::Result<Vec<u8>> {
+) -> anyhow::Result<HttpResponse> {
     let service_url = get_url(net_address)?;
     let request_url = format!(
         "{}{}/{}",
         service_url,
-        QUERY_ERRAND_RESULT_ACTION,
-        String::from_utf8(errand_id.to_vec())?,
+        action_path(QUERY_ERRAND_RESULT_ACTION),
+        hex::encode(errand_id),
     );
-    http_post(&request_url)
+    http_post_for_response(
+        &request_url,
+        &[],
+        DEFAULT_POST_BODY,
+        MAX_RESULT_RESPONSE_LENGTH,
+    )
 }
 
 pub fn send_task_to_tea_network(
@@ -78,12 +143,27 @@ pub fn send_task_to_tea_network(
     description_cid: &Cid,
     errand_id: &ErrandId,
     net_address: &NetAddress,
+    delegation_proof: &Vec<u8>,
+    kind: &KindInfo,
 ) -> bool {
     let client = format!("{}", account);
-    match send_task_internal(&client, description_cid, errand_id, net_address) {
-        Ok(_) => true,
+    let started_at = sp_io::offchain::timestamp();
+    match send_task_internal(
+        &client,
+        description_cid,
+        errand_id,
+        net_address,
+        delegation_proof,
+        kind,
+    ) {
+        Ok(_) => {
+            record_send(true);
+            record_latency(sp_io::offchain::timestamp().diff(&started_at).millis());
+            true
+        }
         Err(e) => {
             debug::error!("send_task_to_tea_network got error: {}", e);
+            record_send(false);
             false
         }
     }
@@ -94,22 +174,55 @@ fn send_task_internal(
     description_cid: &Cid,
     errand_id: &ErrandId,
     net_address: &NetAddress,
+    delegation_proof: &Vec<u8>,
+    kind: &KindInfo,
 ) -> anyhow::Result<()> {
     let info: DelegateInfo = load_delegate_info(client)?;
-    let cid = String::from_utf8(description_cid.to_vec())?;
+    let cid = hex::encode(description_cid);
 
-    let service_url = get_url(net_address)?;
-    let request_url = format!(
-        "{}{}/{}/{}/{}?content={}",
-        service_url,
-        SEND_ERRAND_TASK_ACTION,
+    let request = task_request(
         client,
-        String::from_utf8(errand_id.to_vec())?,
-        &hex::encode(info.sig),
-        &cid,
-    );
-    println!("{}", request_url);
-    let res = http_post(&request_url)?;
+        &info,
+        description_cid,
+        errand_id,
+        net_address,
+        delegation_proof,
+        core::str::from_utf8(&kind.action_path)?,
+    )?;
+    if is_dry_run() {
+        debug::info!("dry-run mode, skip sending task request: {}", request.url);
+        return Ok(());
+    }
+
+    debug::info!("sending task request: {}", request.url);
+    let authorization = service_auth_token().map(|token| format!("Bearer {}", token));
+    let mut headers: Vec<(&str, &str)> = Vec::new();
+    if let Some(value) = &authorization {
+        headers.push(("Authorization", value.as_str()));
+    }
+    if is_keep_alive() {
+        headers.push(("Connection", "keep-alive"));
+    }
+    let idempotency_key = if sends_idempotency_key() {
+        Some(idempotency_key(errand_id, next_send_attempt(errand_id)))
+    } else {
+        None
+    };
+    if let Some(value) = &idempotency_key {
+        headers.push(("Idempotency-Key", value.as_str()));
+    }
+    if !kind.accept.is_empty() {
+        headers.push(("Accept", core::str::from_utf8(&kind.accept)?));
+    }
+    if request.body.is_some() {
+        headers.push(("Content-Type", FORM_CONTENT_TYPE));
+    }
+    let res = http_request_with_method(
+        kind.http_method,
+        &request.url,
+        &headers,
+        request.body.as_ref().map(|body| body.as_slice()),
+    )?;
 
     debug::info!(
         "client {} send task (cid {}) go response: {}",
@@ -120,18 +233,163 @@ fn send_task_internal(
     Ok(())
 }
 
+pub struct TaskRequest {
+    pub url: String,
+    /// Post body carrying the content, `None` if the content is in the url.
+    pub body: Option<Vec<u8>>,
+}
+
+/// Builds a task request to the service `action`, the content moves from the url query into
+/// the post body if the url would be longer than `MAX_URL_LENGTH`.
+pub fn task_request(
+    client: &str,
+    info: &DelegateInfo,
+    description_cid: &Cid,
+    errand_id: &ErrandId,
+    net_address: &NetAddress,
+    delegation_proof: &Vec<u8>,
+    action: &str,
+) -> anyhow::Result<TaskRequest> {
+    let url = task_request_url(
+        client,
+        info,
+        description_cid,
+        errand_id,
+        net_address,
+        delegation_proof,
+        action,
+    )?;
+    if url.len() <= MAX_URL_LENGTH {
+        return Ok(TaskRequest { url, body: None });
+    }
+
+    let url = format!(
+        "{}?proof={}",
+        task_service_url(client, info, errand_id, net_address, action)?,
+        &hex::encode(delegation_proof),
+    );
+    if url.len() > MAX_URL_LENGTH {
+        return Err(anyhow::anyhow!(
+            "task request url is longer than {} bytes",
+            MAX_URL_LENGTH
+        ));
+    }
+    let body = format!("content={}", hex::encode(description_cid));
+    Ok(TaskRequest {
+        url,
+        body: Some(body.into_bytes()),
+    })
+}
+
+/// Builds the url of a task request. Raw bytes (ids, cids and signatures) are hex encoded
+/// so any byte sequence produces a valid url.
+pub fn task_request_url(
+    client: &str,
+    info: &DelegateInfo,
+    description_cid: &Cid,
+    errand_id: &ErrandId,
+    net_address: &NetAddress,
+    delegation_proof: &Vec<u8>,
+    action: &str,
+) -> anyhow::Result<String> {
+    Ok(format!(
+        "{}?content={}&proof={}",
+        task_service_url(client, info, errand_id, net_address, action)?,
+        hex::encode(description_cid),
+        &hex::encode(delegation_proof),
+    ))
+}
+
+fn task_service_url(
+    client: &str,
+    info: &DelegateInfo,
+    errand_id: &ErrandId,
+    net_address: &NetAddress,
+    action: &str,
+) -> anyhow::Result<String> {
+    let service_url = get_url(net_address)?;
+    Ok(format!(
+        "{}{}/{}/{}/{}",
+        service_url,
+        action_path(action),
+        client,
+        hex::encode(errand_id),
+        &hex::encode(&info.sig),
+    ))
+}
+
+/// Idempotency key of the `attempt`th send of the task of `errand_id`, counting from 1.
+pub fn idempotency_key(errand_id: &ErrandId, attempt: u32) -> String {
+    format!("{}-{}", hex::encode(errand_id), attempt)
+}
+
+fn next_send_attempt(errand_id: &ErrandId) -> u32 {
+    let attempts = StorageValueRef::persistent(&send_attempts_key(errand_id));
+    let attempt = match attempts.get::<u32>() {
+        Some(Some(sent)) => sent.saturating_add(1),
+        _ => 1,
+    };
+    attempts.set(&attempt);
+    attempt
+}
+
+/// Resets the sends of the task of `errand_id` once its errand is initialized.
+pub fn clear_send_attempts(errand_id: &ErrandId) {
+    StorageValueRef::persistent(&send_attempts_key(errand_id)).set(&0u32);
+}
+
+fn send_attempts_key(errand_id: &ErrandId) -> Vec<u8> {
+    [LOCAL_STORAGE_SEND_ATTEMPTS_PREFIX.as_bytes(), errand_id].concat()
+}
+
+fn sends_idempotency_key() -> bool {
+    let flag = StorageValueRef::persistent(LOCAL_STORAGE_IDEMPOTENCY_KEY_KEY.as_bytes());
+    match flag.get::<bool>() {
+        Some(Some(enabled)) => enabled,
+        _ => false,
+    }
+}
+
+fn service_auth_token() -> Option<String> {
+    let token = StorageValueRef::persistent(LOCAL_STORAGE_SERVICE_AUTH_TOKEN_KEY.as_bytes());
+    match token.get::<Vec<u8>>() {
+        Some(Some(token)) if !token.is_empty() => String::from_utf8(token).ok(),
+        _ => None,
+    }
+}
+
+fn is_keep_alive() -> bool {
+    let flag = StorageValueRef::persistent(LOCAL_STORAGE_KEEP_ALIVE_KEY.as_bytes());
+    match flag.get::<bool>() {
+        Some(Some(keep_alive)) => keep_alive,
+        _ => false,
+    }
+}
+
+fn is_dry_run() -> bool {
+    let flag = StorageValueRef::persistent(LOCAL_STORAGE_DRY_RUN_KEY.as_bytes());
+    match flag.get::<bool>() {
+        Some(Some(dry_run)) => dry_run,
+        _ => false,
+    }
+}
+
 pub fn account_from_seed_in_accounts(seed: &str, accounts: Vec<AccountId32>) -> bool {
-    let account = account_from_seed(seed);
+    let sr25519_account = account_from_seed::<sp_core::sr25519::Pair>(seed);
+    let ed25519_account = account_from_seed::<sp_core::ed25519::Pair>(seed);
     for ac in accounts {
-        if ac.eq(&account) {
+        if ac.eq(&sr25519_account) || ac.eq(&ed25519_account) {
             return true;
         }
     }
     false
 }
 
-fn account_from_seed(seed: &str) -> AccountId32 {
-    let public: [u8; 32] = sp_core::sr25519::Pair::from_string(&format!("//{}", seed), None)
+fn account_from_seed<TPair: Pair>(seed: &str) -> AccountId32
+where
+    TPair::Public: Into<[u8; 32]>,
+{
+    let public: [u8; 32] = TPair::from_string(&format!("//{}", seed), None)
         .expect("static values are valid; qed")
         .public()
         .into();
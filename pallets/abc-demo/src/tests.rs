@@ -1,10 +1,8 @@
 use super::*;
 use crate::mock::*;
-use frame_support::assert_ok;
+use frame_support::{assert_noop, assert_ok, weights::WithPostDispatchInfo};
 use sp_core::crypto::{AccountId32, Ss58Codec};
-
-pub const ACCOUNT1: &str = "5G97JLuuT1opraWvfS6Smt4jaAZuyDquP9GjamKVcPC366qU";
-pub const ACCOUNT2: &str = "5EPhGXBymJfrcvT2apo6E9tAm2xpNf8Y77zLxd4zjCBdVJeS";
+use sp_core::Pair;
 
 #[test]
 fn generate_errand_id_test() {
@@ -21,6 +19,22 @@ fn generate_errand_id_test() {
     });
 }
 
+#[test]
+fn generate_errand_id_matches_fixed_vector() {
+    use sp_core::H256;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_random_seed(H256::repeat_byte(1));
+        set_extrinsic_index(2);
+        // blake2_128 of (seed, ACCOUNT1, Some(2u32)) with the uuid v4 bits set
+        assert_eq!(
+            b"6359d94c-83be-412b-8351-c0978cf7eb19".to_vec(),
+            TemplateModule::generate_errand_id(&AccountId32::from_string(ACCOUNT1).unwrap())
+        );
+    });
+}
+
 #[test]
 fn begin_task_works() {
     let a = vec![1];
@@ -39,6 +53,7 @@ fn begin_task_works() {
         assert_ok!(TemplateModule::request_delegate(
             sender.clone(),
             client_account.clone(),
+            sender_account.clone(),
             vec![0u8],
             fee.into()
         ));
@@ -51,7 +66,12 @@ fn begin_task_works() {
             sender.clone(),
             client_account.clone(),
             cid.clone(),
-            fee
+            fee,
+            Vec::new(),
+            false,
+            None,
+            DEFAULT_KIND,
+            None
         ));
 
         let task_array = Tasks::<Test>::get(&block_number);
@@ -76,7 +96,12 @@ fn begin_task_works() {
             sender.clone(),
             client_account.clone(),
             cid.clone(),
-            fee
+            fee,
+            Vec::new(),
+            false,
+            None,
+            DEFAULT_KIND,
+            None
         ));
         let task_array = Tasks::<Test>::get(&block_number);
         assert_eq!(2, task_array.len());
@@ -91,7 +116,12 @@ fn begin_task_works() {
             sender.clone(),
             client_account.clone(),
             cid.clone(),
-            fee
+            fee,
+            Vec::new(),
+            false,
+            None,
+            DEFAULT_KIND,
+            None
         ));
         let task_array2 = Tasks::<Test>::get(&block_number);
         let mut sender_bytes_2 = [0u8; 32];
@@ -104,7 +134,7 @@ fn begin_task_works() {
         assert_eq!(&cid, &task_array2[0].description_cid);
         assert_eq!(&fee, &task_array2[0].fee);
 
-        let errand_id = vec![3u8, 4u8];
+        let errand_id = task_array2[0].errand_id.clone();
         assert_ok!(TemplateModule::init_errand(
             sender.clone(),
             client_account.clone(),
@@ -118,3 +148,4485 @@ fn begin_task_works() {
         }
     })
 }
+
+fn parse_result_info(status: &str) -> ErrandResultInfo {
+    let json = format!(
+        r#"{{"completed":true,"result_cid":"result","failed_count":0{}}}"#,
+        status
+    );
+    serde_json::from_str::<ErrandResultInfo>(&json).unwrap()
+}
+
+#[test]
+fn result_status_success_maps_to_done() {
+    let info = parse_result_info(r#","status":"success""#);
+    assert_eq!(ErrandResultStatus::Success, info.status);
+    assert_eq!(Some(ErrandStatus::Done), info.status.errand_status());
+}
+
+#[test]
+fn result_status_error_maps_to_failed() {
+    let info = parse_result_info(r#","status":"error""#);
+    assert_eq!(ErrandResultStatus::Error, info.status);
+    assert_eq!(Some(ErrandStatus::Failed), info.status.errand_status());
+}
+
+#[test]
+fn result_status_partial_keeps_processing() {
+    let info = parse_result_info(r#","status":"partial""#);
+    assert_eq!(ErrandResultStatus::Partial, info.status);
+    assert_eq!(None, info.status.errand_status());
+}
+
+#[test]
+fn result_status_defaults_to_success() {
+    let info = parse_result_info("");
+    assert_eq!(ErrandResultStatus::Success, info.status);
+    assert_eq!(b"result".to_vec(), info.result_cid);
+}
+
+#[test]
+fn listed_errands_are_claimed_by_a_pallet_account() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let seller = AccountId32::from_string(ACCOUNT1).unwrap();
+        let errand_id = init_processing_errand(&cid, 10);
+        assert_eq!(1, TemplateModule::active_claims(&seller));
+
+        assert_noop!(
+            TemplateModule::list_errand(Origin::signed(alice()), errand_id.clone(), 50),
+            Error::<Test>::NotErrandWorker
+        );
+        assert_ok!(TemplateModule::list_errand(
+            Origin::signed(seller.clone()),
+            errand_id.clone(),
+            50
+        ));
+        let listing_account = TemplateModule::listing_account(&errand_id);
+        assert_ne!(seller, listing_account);
+        assert_eq!(Some(listing_account), TemplateModule::errand_worker(&cid));
+        assert_eq!(
+            Some((seller.clone(), 50)),
+            TemplateModule::errand_listing(&cid)
+        );
+        assert_eq!(0, TemplateModule::active_claims(&seller));
+        // the seller gave up the claim, it can't list it again
+        assert_noop!(
+            TemplateModule::list_errand(Origin::signed(seller.clone()), errand_id.clone(), 60),
+            Error::<Test>::NotErrandWorker
+        );
+
+        // completing an unsold errand pays the seller
+        let seller_balance = Balances::free_balance(&seller);
+        complete(&cid, b"result");
+        assert_eq!(seller_balance + 10, Balances::free_balance(&seller));
+        assert_eq!(None, TemplateModule::errand_listing(&cid));
+    });
+}
+
+#[test]
+fn bought_listings_move_the_claim_and_its_fee_to_the_buyer() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let seller = AccountId32::from_string(ACCOUNT1).unwrap();
+        let buyer = alice();
+        Balances::make_free_balance_be(&buyer, 100);
+        let errand_id = init_processing_errand(&cid, 10);
+
+        assert_noop!(
+            TemplateModule::buy_errand_listing(Origin::signed(buyer.clone()), errand_id.clone()),
+            Error::<Test>::ErrandNotListed
+        );
+        assert_ok!(TemplateModule::list_errand(
+            Origin::signed(seller.clone()),
+            errand_id.clone(),
+            50
+        ));
+        let seller_balance = Balances::free_balance(&seller);
+        assert_ok!(TemplateModule::buy_errand_listing(
+            Origin::signed(buyer.clone()),
+            errand_id.clone()
+        ));
+        assert_eq!(50, Balances::free_balance(&buyer));
+        assert_eq!(seller_balance + 50, Balances::free_balance(&seller));
+        assert_eq!(Some(buyer.clone()), TemplateModule::errand_worker(&cid));
+        assert_eq!(1, TemplateModule::active_claims(&buyer));
+        assert_eq!(None, TemplateModule::errand_listing(&cid));
+
+        complete(&cid, b"result");
+        assert_eq!(60, Balances::free_balance(&buyer));
+        assert_eq!(seller_balance + 50, Balances::free_balance(&seller));
+        assert_noop!(
+            TemplateModule::buy_errand_listing(Origin::signed(buyer.clone()), errand_id.clone()),
+            Error::<Test>::ErrandAlreadyExecuted
+        );
+    });
+}
+
+#[test]
+fn task_fee_is_released_at_reported_milestones() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let errand_id = init_processing_errand(&cid, 10);
+        let worker_balance = Balances::free_balance(&worker);
+        // delegate fee and task fee are both reserved
+        assert_eq!(20, Balances::reserved_balance(&client));
+
+        assert_noop!(
+            TemplateModule::report_progress(Origin::signed(client.clone()), errand_id.clone(), 30),
+            Error::<Test>::NotErrandWorker
+        );
+        assert_ok!(TemplateModule::report_progress(
+            Origin::signed(worker.clone()),
+            errand_id.clone(),
+            30
+        ));
+        assert_eq!(worker_balance + 3, Balances::free_balance(&worker));
+        assert_eq!(17, Balances::reserved_balance(&client));
+
+        for pct in vec![30, 20, 101] {
+            assert_noop!(
+                TemplateModule::report_progress(
+                    Origin::signed(worker.clone()),
+                    errand_id.clone(),
+                    pct
+                ),
+                Error::<Test>::InvalidProgress
+            );
+        }
+        assert_ok!(TemplateModule::report_progress(
+            Origin::signed(worker.clone()),
+            errand_id.clone(),
+            60
+        ));
+        assert_eq!(worker_balance + 6, Balances::free_balance(&worker));
+        assert_eq!((60, 6), TemplateModule::errand_progress(&errand_id));
+
+        // completion pays what the milestones did not release
+        complete(&cid, b"result");
+        assert_eq!(worker_balance + 10, Balances::free_balance(&worker));
+        assert_eq!(10, Balances::reserved_balance(&client));
+    });
+}
+
+#[test]
+fn errands_keep_their_fee_when_the_client_queues_more_tasks() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let errand_id = init_processing_errand(&cid, 10);
+        queue_task(&vec![1u8, 2u8], 2, false);
+        assert_eq!(10, TemplateModule::errand_fee(&errand_id));
+        let worker_balance = Balances::free_balance(&worker);
+        assert_eq!(22, Balances::reserved_balance(&client));
+
+        assert_ok!(TemplateModule::report_progress(
+            Origin::signed(worker.clone()),
+            errand_id.clone(),
+            50
+        ));
+        assert_eq!(worker_balance + 5, Balances::free_balance(&worker));
+        assert_ok!(TemplateModule::fail_errand(
+            Origin::signed(worker.clone()),
+            cid.clone(),
+            b"timeout".to_vec(),
+            Outcome::Error
+        ));
+        // the delegate fee and the fee of the other task stay reserved
+        assert_eq!(12, Balances::reserved_balance(&client));
+    });
+}
+
+#[test]
+fn fail_errand_refunds_fee() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        let fee = 5u32;
+        let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+        init_processing_errand(&cid, fee);
+        // delegate fee and task fee are both reserved
+        assert_eq!(10, Balances::reserved_balance(&client_account));
+
+        let reason = b"service refused".to_vec();
+        assert_ok!(TemplateModule::fail_errand(
+            Origin::signed(alice()),
+            cid.clone(),
+            reason.clone(),
+            Outcome::Error
+        ));
+
+        assert_eq!(5, Balances::reserved_balance(&client_account));
+        assert_eq!(ErrandStatus::Failed, Errands::get(&cid).unwrap().status);
+        assert_eq!(reason, ErrandFailReasons::get(&cid));
+        assert!(!ProcessingErrands::get().contains(&cid));
+    });
+}
+
+#[test]
+fn errands_fail_only_once() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        let fail = || {
+            TemplateModule::fail_errand(
+                Origin::signed(alice()),
+                cid.clone(),
+                Vec::new(),
+                Outcome::Error,
+            )
+        };
+        assert_ok!(fail());
+        let reserved = Balances::reserved_balance(&client_account);
+
+        assert_noop!(fail(), Error::<Test>::ErrandAlreadyExecuted);
+        assert_eq!(reserved, Balances::reserved_balance(&client_account));
+
+        let done_cid = vec![1u8, 2u8];
+        let errand_id = queue_task(&done_cid, 5, false);
+        init_queued_errand(&done_cid, &errand_id);
+        complete(&done_cid, b"result");
+        assert_noop!(
+            TemplateModule::fail_errand(
+                Origin::signed(alice()),
+                done_cid,
+                Vec::new(),
+                Outcome::Error
+            ),
+            Error::<Test>::ErrandAlreadyExecuted
+        );
+    });
+}
+
+#[test]
+fn errands_complete_only_once() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let done_cid = vec![1u8, 1u8];
+        let failed_cid = vec![1u8, 2u8];
+        init_processing_errand(&done_cid, 5);
+        let errand_id = queue_task(&failed_cid, 5, false);
+        init_queued_errand(&failed_cid, &errand_id);
+        queue_task(&vec![1u8, 3u8], 5, false);
+        complete(&done_cid, b"result");
+        assert_ok!(TemplateModule::fail_errand(
+            Origin::signed(worker.clone()),
+            failed_cid.clone(),
+            Vec::new(),
+            Outcome::Error
+        ));
+        let worker_balance = Balances::free_balance(&worker);
+        // delegate fee and the fee of the queued task
+        assert_eq!(10, Balances::reserved_balance(&client));
+
+        let update = |cid: &Vec<u8>| {
+            TemplateModule::update_errand(
+                Origin::signed(alice()),
+                cid.clone(),
+                b"again".to_vec(),
+                Vec::new(),
+                false,
+                None,
+            )
+        };
+        assert_noop!(update(&done_cid), Error::<Test>::ErrandAlreadyExecuted);
+        assert_noop!(update(&failed_cid), Error::<Test>::ErrandAlreadyExecuted);
+        assert_eq!(worker_balance, Balances::free_balance(&worker));
+        assert_eq!(10, Balances::reserved_balance(&client));
+        assert_eq!(ErrandStatus::Failed, Errands::get(&failed_cid).unwrap().status);
+    });
+}
+
+#[test]
+fn only_the_claiming_or_registered_workers_fail_errands() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        let fail = |sender: &AccountId32| {
+            TemplateModule::fail_errand(
+                Origin::signed(sender.clone()),
+                cid.clone(),
+                Vec::new(),
+                Outcome::Refused,
+            )
+        };
+
+        assert_noop!(fail(&client_account), Error::<Test>::NoRightToUpdateErrand);
+        assert_noop!(fail(&alice()), Error::<Test>::NoRightToUpdateErrand);
+        assert_ok!(fail(&worker));
+        assert_eq!(ErrandStatus::Failed, Errands::get(&cid).unwrap().status);
+    });
+}
+
+#[test]
+fn fail_errand_rejects_long_reason() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+
+        assert_noop!(
+            TemplateModule::fail_errand(
+                Origin::signed(alice()),
+                cid,
+                vec![0u8; MAX_FAIL_REASON_LENGTH + 1],
+                Outcome::Error
+            ),
+            Error::<Test>::FailReasonTooLong
+        );
+    });
+}
+
+#[test]
+fn errands_remain_retrievable_after_hasher_migration() {
+    use codec::Encode;
+    use frame_support::{storage::migration::put_storage_value, StorageHasher, Twox64Concat};
+
+    new_test_ext().execute_with(|| {
+        let cid = vec![1u8, 1u8];
+        let errand = Errand::new(
+            AccountId32::from_string(ACCOUNT2).unwrap().encode(),
+            vec![3u8, 4u8],
+            cid.clone(),
+        );
+        // write the errand the way the old `twox_64_concat` map did
+        put_storage_value(
+            b"Abc",
+            b"Errands",
+            &Twox64Concat::hash(&cid.encode()),
+            errand.clone(),
+        );
+        assert_eq!(None, Errands::get(&cid));
+        StorageVersion::put(Releases::V1_0_0);
+
+        migration::migrate_errands_to_blake2::<Test>();
+
+        assert_eq!(Some(errand), Errands::get(&cid));
+        assert_eq!(Releases::V2_0_0, StorageVersion::get());
+    });
+}
+
+#[test]
+fn new_chains_need_no_migration() {
+    use frame_support::traits::OnRuntimeUpgrade;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_eq!(Releases::V4_0_0, StorageVersion::get());
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        register_kind(1, 4);
+        let errand = Errands::get(&cid);
+        let kind = TemplateModule::kind_info(1);
+
+        TemplateModule::on_runtime_upgrade();
+
+        assert!(errand.is_some());
+        assert_eq!(errand, Errands::get(&cid));
+        assert_eq!(kind, TemplateModule::kind_info(1));
+        assert_eq!(Releases::V4_0_0, StorageVersion::get());
+    });
+}
+
+#[test]
+fn errands_by_cid_returns_all_errands_of_cid() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        register_client(5);
+        let first = queue_task(&cid, 5, false);
+        System::set_block_number(2);
+        set_extrinsic_index(1);
+        let second = queue_task(&cid, 5, false);
+        init_queued_errand(&cid, &first);
+        init_queued_errand(&cid, &second);
+
+        assert_eq!(vec![first, second], TemplateModule::errands_by_cid(&cid));
+    });
+}
+
+#[test]
+fn errand_new_is_processing_without_result() {
+    let errand = Errand::new(vec![1u8], vec![2u8], vec![3u8]);
+    assert_eq!(vec![1u8], errand.account_id);
+    assert_eq!(vec![2u8], errand.errand_id);
+    assert_eq!(vec![3u8], errand.description_cid);
+    assert_eq!(ErrandStatus::Processing, errand.status);
+    assert!(errand.result.is_empty());
+}
+
+#[test]
+fn dry_run_sends_no_http_request() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::{testing, OffchainExt};
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let (offchain, offchain_state) = testing::TestOffchainExt::new();
+    let mut t = new_test_ext();
+    t.register_extension(OffchainExt::new(offchain));
+
+    t.execute_with(|| {
+        let account = AccountId32::from_string(ACCOUNT2).unwrap();
+        save_delegate_info(
+            &format!("{}", account),
+            &DelegateInfo {
+                delegator_tea_id: vec![1u8],
+                delegator_ephemeral_id: vec![2u8],
+                sig: vec![3u8],
+                key3_rsa_pub_key: "key".into(),
+            },
+        )
+        .unwrap();
+        StorageValueRef::persistent(task::LOCAL_STORAGE_DRY_RUN_KEY.as_bytes()).set(&true);
+
+        assert!(task::send_task_to_tea_network(
+            &account,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            &TemplateModule::registered_kind(DEFAULT_KIND).unwrap(),
+        ));
+    });
+
+    assert!(offchain_state.read().requests.is_empty());
+}
+
+#[test]
+fn ed25519_keys_can_sign_offchain() {
+    use frame_system::offchain::Signer;
+    use sp_core::testing::KeyStore;
+    use sp_core::traits::KeystoreExt;
+
+    let keystore = KeyStore::new();
+    keystore
+        .write()
+        .ed25519_generate_new(crate::KEY_TYPE, Some("//Alice"))
+        .unwrap();
+    let mut t = new_test_ext();
+    t.register_extension(KeystoreExt(keystore));
+
+    t.execute_with(|| {
+        assert!(Signer::<Test, ed25519_crypto::AuthId>::all_accounts().can_sign());
+        assert!(!Signer::<Test, crypto::AuthId>::all_accounts().can_sign());
+
+        let accounts: Vec<AccountId32> = ed25519_crypto::Public::all()
+            .into_iter()
+            .map(|key| {
+                let public: [u8; 32] = sp_core::ed25519::Public::from(key).into();
+                AccountId32::from(public)
+            })
+            .collect();
+        assert!(task::account_from_seed_in_accounts("Alice", accounts));
+    });
+}
+
+#[test]
+fn reconcile_reserves_releases_orphaned_reserve() {
+    use frame_support::traits::ReservableCurrency;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+        init_processing_errand(&cid, 5);
+        assert_eq!(10, TemplateModule::client_reserved(&client_account));
+
+        // a fee the pallet reserved and lost track of, next to a reserve of another pallet
+        assert_ok!(Balances::reserve(&client_account, 7));
+        ClientReserved::<Test>::insert(&client_account, 17);
+        assert_ok!(Balances::reserve(&client_account, 3));
+        assert_eq!(20, Balances::reserved_balance(&client_account));
+
+        assert_noop!(
+            TemplateModule::reconcile_reserves(
+                Origin::signed(alice()),
+                vec![client_account.clone()]
+            ),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(TemplateModule::reconcile_reserves(
+            Origin::root(),
+            vec![client_account.clone()]
+        ));
+        assert_eq!(13, Balances::reserved_balance(&client_account));
+        assert_eq!(10, TemplateModule::client_reserved(&client_account));
+    });
+}
+
+#[test]
+fn reconcile_reserves_keeps_the_fees_still_due() {
+    use frame_support::traits::ReservableCurrency;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let errand_id = init_processing_errand(&vec![1u8, 1u8], 5);
+        assert_ok!(TemplateModule::report_progress(
+            Origin::signed(worker),
+            errand_id,
+            40
+        ));
+        queue_task(&vec![1u8, 2u8], 4, false);
+        // delegate fee, unreleased fee of the errand and fee of the queued task
+        assert_eq!(12, Balances::reserved_balance(&client));
+
+        // a drifted counter no longer hides reserves nothing is due for
+        assert_ok!(Balances::reserve(&client, 7));
+        ClientReserved::<Test>::insert(&client, 19);
+        assert_ok!(TemplateModule::reconcile_reserves(Origin::root(), vec![client.clone()]));
+        assert_eq!(12, Balances::reserved_balance(&client));
+        assert_eq!(12, TemplateModule::client_reserved(&client));
+    });
+}
+
+#[test]
+fn init_errand_emits_task_dispatched() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let errand_id = init_processing_errand(&cid, 5);
+
+        let dispatched = TestEvent::abc(RawEvent::TaskDispatched(errand_id));
+        assert!(System::events().iter().any(|r| r.event == dispatched));
+    });
+}
+
+#[test]
+fn empty_task_arrays_send_nothing() {
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(1);
+        register_client(10);
+        queue_task(&vec![1u8, 1u8], 10, false);
+        // the task is gone but its height keeps an empty array and the bid
+        frame_support::storage::unhashed::put(
+            &Tasks::<Test>::hashed_key_for(1),
+            &Vec::<TaskInfo>::new(),
+        );
+        assert_eq!(None, TemplateModule::load_tasks(&1));
+
+        let block_number = 1 + ConfirmationDepth::get();
+        assert!(TemplateModule::tick_tasks(block_number).is_empty());
+        TemplateModule::send_errand_tasks(block_number);
+        assert!(offchain_state.read().requests.is_empty());
+    });
+}
+
+#[test]
+fn workers_only_send_tasks_of_their_shards() {
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+
+    t.ext.execute_with(|| {
+        System::set_block_number(1);
+        register_client(10);
+        let mut errand_ids = Vec::new();
+        for i in 0..4u8 {
+            set_extrinsic_index(i as u32);
+            errand_ids.push(queue_task(&vec![1u8, i], 10, false));
+        }
+        let block_number = 1 + ConfirmationDepth::get();
+        let tick_errands = || {
+            let mut ids: Vec<Vec<u8>> = TemplateModule::tick_tasks(block_number)
+                .into_iter()
+                .map(|task| task.errand_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        errand_ids.sort();
+        assert_eq!(errand_ids, tick_errands());
+
+        let shards = StorageValueRef::persistent(LOCAL_STORAGE_TASK_SHARDS_KEY.as_bytes());
+        let mut sent = Vec::new();
+        for shard in 0..2u32 {
+            shards.set(&(2u32, vec![shard]));
+            let ids = tick_errands();
+            assert!(ids
+                .iter()
+                .all(|id| TemplateModule::shard_of(id, 2) == shard));
+            sent.extend(ids);
+        }
+        // together the two fleets send every task exactly once
+        sent.sort();
+        assert_eq!(errand_ids, sent);
+
+        shards.set(&(2u32, vec![0u32, 1u32]));
+        assert_eq!(errand_ids, tick_errands());
+    });
+}
+
+#[test]
+fn send_jitter_stays_within_its_window() {
+    new_test_ext().execute_with(|| {
+        let alice = sp_core::sr25519::Pair::from_string("//Alice", None).unwrap().public();
+        let bob = sp_core::sr25519::Pair::from_string("//Bob", None).unwrap().public();
+        let mut delays = Vec::new();
+        for block_number in 0..50u64 {
+            for public in &[alice, bob] {
+                let delay = TemplateModule::send_jitter(public.as_ref(), block_number, 2000);
+                assert!(delay < 2000);
+                assert_eq!(
+                    delay,
+                    TemplateModule::send_jitter(public.as_ref(), block_number, 2000)
+                );
+                delays.push(delay);
+            }
+        }
+        delays.sort();
+        delays.dedup();
+        assert!(delays.len() > 50);
+
+        assert_eq!(0, TemplateModule::send_jitter(alice.as_ref(), 1, 0));
+    });
+}
+
+#[test]
+fn only_the_pinned_key_signs() {
+    use frame_system::offchain::SendSignedTransaction;
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let mut t = new_offchain_test_ext(&["//Alice", "//Bob"]);
+    t.ext.execute_with(|| {
+        let bob_key = sp_core::sr25519::Pair::from_string("//Bob", None)
+            .unwrap()
+            .public();
+        let bob = AccountId32::from(bob_key.0);
+        register_worker(&alice());
+        register_worker(&bob);
+        let signers = || {
+            TemplateModule::worker_signer()
+                .send_signed_transaction(|_acct| Call::<Test>::set_recovery_account(None))
+                .into_iter()
+                .map(|(account, _)| account.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(2, signers().len());
+
+        let pin = StorageValueRef::persistent(LOCAL_STORAGE_SIGNER_PUBKEY_KEY.as_bytes());
+        pin.set(&bob_key.0.to_vec());
+        assert_eq!(vec![bob.clone()], signers());
+
+        // unknown keys fall back to all keys
+        pin.set(&vec![7u8; 32]);
+        assert_eq!(2, signers().len());
+    });
+}
+
+#[test]
+fn unsigned_inits_of_the_same_errand_conflict_in_the_pool() {
+    use frame_support::unsigned::ValidateUnsigned;
+    use frame_system::offchain::SignedPayload;
+    use sp_runtime::transaction_validity::{InvalidTransaction, TransactionSource};
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    t.ext.execute_with(|| {
+        System::set_block_number(1);
+        let worker = alice();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        assert_ok!(TemplateModule::request_delegate(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            AccountId32::from_string(ACCOUNT1).unwrap(),
+            b"localhost:8000".to_vec(),
+            5
+        ));
+        assert_ok!(TemplateModule::update_delegate_status(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            worker.clone()
+        ));
+        let cid = vec![1u8, 1u8];
+        let errand_id = queue_task_of(&worker, &client, &cid, 5, false);
+
+        let payload = InitErrandPayload {
+            public: sp_core::sr25519::Pair::from_string("//Alice", None)
+                .unwrap()
+                .public()
+                .into(),
+            client: client.clone(),
+            errand_id: errand_id.clone(),
+            description_cid: cid.clone(),
+        };
+        let sign = || SignedPayload::<Test>::sign::<crypto::AuthId>(&payload).unwrap();
+        let validate = |call: &Call<Test>| {
+            TemplateModule::validate_unsigned(TransactionSource::External, call)
+        };
+        // sr25519 signatures are randomized, the two transactions differ in their bytes
+        let first_signature = sign();
+        let second_signature = sign();
+        assert_ne!(first_signature, second_signature);
+        let first = validate(&Call::init_errand_unsigned(payload.clone(), first_signature.clone()))
+            .unwrap();
+        let second = validate(&Call::init_errand_unsigned(payload.clone(), second_signature))
+            .unwrap();
+        assert_eq!(first.provides, second.provides);
+        assert_eq!(UnsignedPriority::get(), first.priority);
+        assert_eq!(UNSIGNED_INIT_LONGEVITY, first.longevity);
+
+        let forged = InitErrandPayload {
+            description_cid: vec![1u8, 2u8],
+            ..payload.clone()
+        };
+        assert_eq!(
+            validate(&Call::init_errand_unsigned(forged, first_signature.clone())),
+            InvalidTransaction::BadProof.into()
+        );
+
+        assert_ok!(TemplateModule::init_errand_unsigned(
+            Origin::none(),
+            payload.clone(),
+            first_signature.clone()
+        ));
+        assert_eq!(
+            ErrandStatus::Processing,
+            TemplateModule::errand(&cid).unwrap().status
+        );
+        assert_eq!(
+            validate(&Call::init_errand_unsigned(payload, first_signature)),
+            InvalidTransaction::Stale.into()
+        );
+    });
+}
+
+#[test]
+fn initialized_errands_are_reported_once_per_block() {
+    use frame_support::traits::OnFinalize;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        set_batch_init_events(true);
+        for (index, cid) in vec![vec![1u8, 1u8], vec![1u8, 2u8]].iter().enumerate() {
+            set_extrinsic_index(index as u32);
+            let errand_id = queue_task(cid, 5, false);
+            init_queued_errand(cid, &errand_id);
+        }
+        assert_eq!(2, TemplateModule::errands_inited_in_block());
+        assert!(!System::events().iter().any(|r| match r.event {
+            TestEvent::abc(RawEvent::ErrandInited(..)) => true,
+            _ => false,
+        }));
+
+        TemplateModule::on_finalize(1);
+        assert_eq!(
+            System::events().last().unwrap().event,
+            TestEvent::abc(RawEvent::ErrandsBatchProcessed(1, 2))
+        );
+        assert_eq!(0, TemplateModule::errands_inited_in_block());
+
+        // blocks without initialized errands report nothing
+        let event_count = System::events().len();
+        TemplateModule::on_finalize(2);
+        assert_eq!(event_count, System::events().len());
+    });
+}
+
+#[test]
+fn registered_delegation_is_included_in_service_request() {
+    use crate::delegate::DelegateInfo;
+
+    new_offchain_test_ext(&[]).ext.execute_with(|| {
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        assert!(TemplateModule::delegation_proof(&client, &worker).is_err());
+
+        Delegations::<Test>::insert(&client, &worker, vec![0xabu8, 0xcdu8]);
+        let proof = TemplateModule::delegation_proof(&client, &worker).unwrap();
+
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let url = task::task_request_url(
+            "client",
+            &info,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &proof,
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        assert_eq!(
+            "http://localhost:8000/api/service/client/657272616e64/03?content=636964&proof=abcd",
+            url
+        );
+    });
+}
+
+#[test]
+fn task_request_url_accepts_non_utf8_bytes() {
+    use crate::delegate::DelegateInfo;
+
+    new_offchain_test_ext(&[]).ext.execute_with(|| {
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![0xffu8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let url = task::task_request_url(
+            "client",
+            &info,
+            &vec![0xc3u8, 0x28u8],
+            &vec![0xffu8, 0xfeu8],
+            &b"localhost:8000".to_vec(),
+            &vec![0x80u8],
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        assert_eq!(
+            "http://localhost:8000/api/service/client/fffe/ff?content=c328&proof=80",
+            url
+        );
+    });
+}
+
+fn delegation_proof_of(seed: &str, employer: &AccountId32, worker: &AccountId32) -> Vec<u8> {
+    let pair = sp_core::sr25519::Pair::from_string(seed, None).unwrap();
+    let message = TemplateModule::delegation_message(employer, worker);
+    pair.sign(&message).0.to_vec()
+}
+
+#[test]
+fn register_delegation_with_valid_proof() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let proof = delegation_proof_of("//Alice", &alice(), &worker);
+
+        assert_ok!(TemplateModule::register_delegation(
+            Origin::signed(alice()),
+            worker.clone(),
+            proof.clone()
+        ));
+        assert_eq!(Some(proof), Delegations::<Test>::get(&alice(), &worker));
+    });
+}
+
+#[test]
+fn register_delegation_rejects_invalid_proof() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        // signed by someone other than the employer
+        let proof = delegation_proof_of("//Bob", &alice(), &worker);
+
+        assert_noop!(
+            TemplateModule::register_delegation(Origin::signed(alice()), worker.clone(), proof),
+            Error::<Test>::InvalidDelegationProof
+        );
+        assert_noop!(
+            TemplateModule::register_delegation(Origin::signed(alice()), worker, vec![0u8; 3]),
+            Error::<Test>::InvalidDelegationProof
+        );
+    });
+}
+
+#[test]
+fn offchain_worker_skips_malformed_tasks() {
+    use frame_support::traits::OffchainWorker;
+    use sp_core::testing::KeyStore;
+    use sp_core::traits::KeystoreExt;
+
+    let mut t = new_test_ext();
+    t.register_extension(KeystoreExt(KeyStore::new()));
+
+    t.execute_with(|| {
+        let block_number = 1u64;
+        System::set_block_number(block_number);
+        // a vector length prefix promising more items than stored
+        frame_support::storage::unhashed::put_raw(
+            &Tasks::<Test>::hashed_key_for(&block_number),
+            &[0xffu8, 0xff, 0xff],
+        );
+
+        assert_eq!(None, TemplateModule::load_tasks(&block_number));
+        TemplateModule::offchain_worker(block_number);
+    });
+}
+
+#[test]
+fn completed_errands_of_returns_only_done_errands() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let done_cid = vec![1u8, 1u8];
+        let processing_cid = vec![1u8, 2u8];
+        let failed_cid = vec![1u8, 3u8];
+        let done_id = init_processing_errand(&done_cid, 5);
+        for cid in vec![processing_cid.clone(), failed_cid.clone()] {
+            let errand_id = queue_task(&cid, 5, false);
+            init_queued_errand(&cid, &errand_id);
+        }
+        assert_ok!(TemplateModule::update_errand(
+            Origin::signed(alice()),
+            done_cid.clone(),
+            b"result".to_vec(),
+            Vec::new(),
+            false,
+            None
+        ));
+        assert_ok!(TemplateModule::fail_errand(
+            Origin::signed(alice()),
+            failed_cid,
+            Vec::new(),
+            Outcome::Error
+        ));
+
+        assert_eq!(
+            vec![(done_id, b"result".to_vec())],
+            TemplateModule::completed_errands_of(client_account.clone(), 10)
+        );
+        assert!(TemplateModule::completed_errands_of(client_account, 0).is_empty());
+    });
+}
+
+#[test]
+fn errands_by_label_returns_labeled_errands() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender = Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap());
+        let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+        init_processing_errand(&vec![1u8, 1u8], 5);
+
+        assert_ok!(TemplateModule::begin_task(
+            sender.clone(),
+            client_account.clone(),
+            vec![1u8, 2u8],
+            5,
+            vec![b"images".to_vec(), b"urgent".to_vec()],
+            false,
+            None,
+            DEFAULT_KIND,
+            None
+        ));
+        assert_ok!(TemplateModule::begin_task(
+            sender.clone(),
+            client_account.clone(),
+            vec![1u8, 3u8],
+            5,
+            vec![b"images".to_vec()],
+            false,
+            None,
+            DEFAULT_KIND,
+            None
+        ));
+
+        // the initialized errand left the queue
+        let task_array = Tasks::<Test>::get(&1);
+        assert_eq!(
+            vec![
+                task_array[0].errand_id.clone(),
+                task_array[1].errand_id.clone()
+            ],
+            TemplateModule::errands_by_label(b"images".to_vec())
+        );
+        assert_eq!(
+            vec![task_array[0].errand_id.clone()],
+            TemplateModule::errands_by_label(b"urgent".to_vec())
+        );
+        assert_eq!(
+            vec![b"images".to_vec(), b"urgent".to_vec()],
+            TemplateModule::errand_labels(&task_array[0].errand_id)
+        );
+    });
+}
+
+#[test]
+fn begin_task_validates_labels() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender = Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap());
+        let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+        init_processing_errand(&vec![1u8, 1u8], 5);
+
+        assert_noop!(
+            TemplateModule::begin_task(
+                sender.clone(),
+                client_account.clone(),
+                vec![1u8, 2u8],
+                5,
+                vec![b"label".to_vec(); MAX_LABELS + 1],
+                false,
+                None,
+                DEFAULT_KIND,
+                None
+            ),
+            Error::<Test>::TooManyLabels.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+        );
+        assert_noop!(
+            TemplateModule::begin_task(
+                sender,
+                client_account,
+                vec![1u8, 2u8],
+                5,
+                vec![vec![0u8; MAX_LABEL_LENGTH + 1]],
+                false,
+                None,
+                DEFAULT_KIND,
+                None
+            ),
+            Error::<Test>::LabelTooLong.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+        );
+    });
+}
+
+#[test]
+fn poll_only_fires_on_interval_blocks() {
+    new_test_ext().execute_with(|| {
+        assert!(TemplateModule::should_poll(0));
+        assert!(!TemplateModule::should_poll(1));
+        assert!(!TemplateModule::should_poll(9));
+        assert!(TemplateModule::should_poll(10));
+        assert!(!TemplateModule::should_poll(15));
+        assert!(TemplateModule::should_poll(20));
+    });
+}
+
+#[test]
+fn offchain_metrics_count_sent_tasks() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::{testing, OffchainExt};
+
+    let (offchain, offchain_state) = testing::TestOffchainExt::new();
+    let mut t = new_test_ext();
+    t.register_extension(OffchainExt::new(offchain));
+
+    t.execute_with(|| {
+        let account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let url = task::task_request_url(
+            &format!("{}", account),
+            &info,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        expect_post(&offchain_state, url, "ok");
+        save_delegate_info(&format!("{}", account), &info).unwrap();
+
+        assert!(task::send_task_to_tea_network(
+            &account,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            &TemplateModule::registered_kind(DEFAULT_KIND).unwrap(),
+        ));
+        // no delegate info saved for ACCOUNT1, so sending fails
+        assert!(!task::send_task_to_tea_network(
+            &AccountId32::from_string(ACCOUNT1).unwrap(),
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            &TemplateModule::registered_kind(DEFAULT_KIND).unwrap(),
+        ));
+
+        let metrics = metrics::load_metrics();
+        assert_eq!(1, metrics.sent_ok);
+        assert_eq!(1, metrics.sent_err);
+        assert_eq!(0, metrics.polled_ok);
+    });
+}
+
+#[test]
+fn keep_alive_adds_the_connection_header_to_task_requests() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::{testing, OffchainExt};
+
+    let (offchain, offchain_state) = testing::TestOffchainExt::new();
+    let mut t = new_test_ext();
+    t.register_extension(OffchainExt::new(offchain));
+
+    t.execute_with(|| {
+        let account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let url = task::task_request_url(
+            &format!("{}", account),
+            &info,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        offchain_state
+            .write()
+            .expect_request(testing::PendingRequest {
+                method: "POST".into(),
+                uri: url,
+                body: b"post body".to_vec(),
+                headers: vec![
+                    ("User-Agent".into(), "tearust".into()),
+                    ("Connection".into(), "keep-alive".into()),
+                ],
+                response: Some(br#"{"data":"ok"}"#.to_vec()),
+                sent: true,
+                ..Default::default()
+            });
+        save_delegate_info(&format!("{}", account), &info).unwrap();
+        StorageValueRef::persistent(task::LOCAL_STORAGE_KEEP_ALIVE_KEY.as_bytes()).set(&true);
+
+        assert!(task::send_task_to_tea_network(
+            &account,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            &TemplateModule::registered_kind(DEFAULT_KIND).unwrap(),
+        ));
+    });
+}
+
+#[test]
+fn idempotency_keys_count_send_attempts() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::{testing, OffchainExt};
+
+    let (offchain, offchain_state) = testing::TestOffchainExt::new();
+    let mut t = new_test_ext();
+    t.register_extension(OffchainExt::new(offchain));
+
+    t.execute_with(|| {
+        let account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let errand_id = vec![0xabu8, 0xcdu8];
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let url = task::task_request_url(
+            &format!("{}", account),
+            &info,
+            &b"cid".to_vec(),
+            &errand_id,
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        let expect_send = |key: &str, response: &[u8]| {
+            offchain_state
+                .write()
+                .expect_request(testing::PendingRequest {
+                    method: "POST".into(),
+                    uri: url.clone(),
+                    body: b"post body".to_vec(),
+                    headers: vec![
+                        ("User-Agent".into(), "tearust".into()),
+                        ("Idempotency-Key".into(), key.into()),
+                    ],
+                    response: Some(response.to_vec()),
+                    sent: true,
+                    ..Default::default()
+                });
+        };
+        let send = || {
+            task::send_task_to_tea_network(
+                &account,
+                &b"cid".to_vec(),
+                &errand_id,
+                &b"localhost:8000".to_vec(),
+                &Vec::new(),
+                &TemplateModule::registered_kind(DEFAULT_KIND).unwrap(),
+            )
+        };
+        save_delegate_info(&format!("{}", account), &info).unwrap();
+        StorageValueRef::persistent(task::LOCAL_STORAGE_IDEMPOTENCY_KEY_KEY.as_bytes())
+            .set(&true);
+
+        expect_send("abcd-1", b"service unavailable");
+        assert!(!send());
+        // the retry is a new attempt of the same errand
+        expect_send("abcd-2", br#"{"data":"ok"}"#);
+        assert!(send());
+
+        task::clear_send_attempts(&errand_id);
+        expect_send("abcd-1", br#"{"data":"ok"}"#);
+        assert!(send());
+    });
+}
+
+#[test]
+fn canonical_json_is_byte_stable() {
+    let body = http::canonical_json(&[("status", "done"), ("description_cid", "0101")]);
+    assert_eq!(
+        br#"{"description_cid":"0101","status":"done"}"#.to_vec(),
+        body
+    );
+    assert_eq!(
+        body,
+        http::canonical_json(&[("description_cid", "0101"), ("status", "done")])
+    );
+    assert_eq!(body, webhook::completion_body(&vec![1u8, 1u8]));
+    assert_eq!(
+        br#"{"reason":"a \"quoted\" \\ line\u000a"}"#.to_vec(),
+        http::canonical_json(&[("reason", "a \"quoted\" \\ line\n")])
+    );
+}
+
+#[test]
+fn result_hash_algorithms_give_their_known_hashes() {
+    assert_eq!(
+        "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319",
+        hex::encode(HashAlgo::Blake2_256.hash(b"abc"))
+    );
+    assert_eq!(
+        "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+        hex::encode(HashAlgo::Keccak256.hash(b"abc"))
+    );
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 10);
+        complete(&cid, b"abc");
+        assert_eq!(HashAlgo::Blake2_256, TemplateModule::result_hash_algo(&cid));
+        assert_eq!(
+            HashAlgo::Blake2_256.hash(b"abc"),
+            TemplateModule::errand_result_hash(&cid).unwrap().0
+        );
+    });
+}
+
+#[test]
+fn errand_json_is_stable() {
+    let mut errand = Errand::new(vec![1u8, 2u8], vec![0xabu8, 0xcdu8], vec![1u8, 1u8]);
+    assert_eq!(
+        r#"{"errand_id":"abcd","description_cid":"0101","account_id":"0102","status":"processing","result":""}"#,
+        rpc::errand_json(&errand)
+    );
+
+    errand.status = ErrandStatus::Done;
+    errand.result = b"ok".to_vec();
+    assert_eq!(
+        r#"{"errand_id":"abcd","description_cid":"0101","account_id":"0102","status":"done","result":"6f6b"}"#,
+        rpc::errand_json(&errand)
+    );
+}
+
+#[test]
+fn get_configured_kinds_send_tasks_with_get() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::{testing, OffchainExt};
+
+    let (offchain, offchain_state) = testing::TestOffchainExt::new();
+    let mut t = new_test_ext();
+    t.register_extension(OffchainExt::new(offchain));
+
+    t.execute_with(|| {
+        let lookup = KindInfo {
+            action_path: b"/lookup".to_vec(),
+            default_fee: 0,
+            fee_multiplier_percent: 0,
+            http_method: HttpMethod::Get,
+            accept: Vec::new(),
+        };
+        assert_ok!(TemplateModule::register_kind(Origin::root(), 1, lookup.clone()));
+        assert_eq!(Some(lookup.clone()), TemplateModule::kind_info(1));
+        let default_kind = TemplateModule::registered_kind(DEFAULT_KIND).unwrap();
+        assert_eq!(HttpMethod::Post, default_kind.http_method);
+
+        let account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let url = task::task_request_url(
+            &format!("{}", account),
+            &info,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            "/lookup",
+        )
+        .unwrap();
+        offchain_state
+            .write()
+            .expect_request(testing::PendingRequest {
+                method: "GET".into(),
+                uri: url,
+                headers: vec![("User-Agent".into(), "tearust".into())],
+                response: Some(br#"{"data":"ok"}"#.to_vec()),
+                sent: true,
+                ..Default::default()
+            });
+        save_delegate_info(&format!("{}", account), &info).unwrap();
+
+        assert!(task::send_task_to_tea_network(
+            &account,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            &lookup,
+        ));
+    });
+}
+
+#[test]
+fn errand_result_is_cleared_after_retention() {
+    use frame_support::traits::OnInitialize;
+    use sp_runtime::traits::{BlakeTwo256, Hash};
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        assert_ok!(TemplateModule::update_errand(
+            Origin::signed(alice()),
+            cid.clone(),
+            b"result".to_vec(),
+            Vec::new(),
+            false,
+            None
+        ));
+
+        TemplateModule::on_initialize(5);
+        assert_eq!(b"result".to_vec(), TemplateModule::errand(&cid).unwrap().result);
+
+        TemplateModule::on_initialize(6);
+        let errand = TemplateModule::errand(&cid).unwrap();
+        assert!(errand.result.is_empty());
+        assert_eq!(ErrandStatus::Done, errand.status);
+        assert_eq!(
+            Some(BlakeTwo256::hash(b"result")),
+            TemplateModule::errand_result_hash(&cid)
+        );
+        assert!(TemplateModule::result_retention_queue(6).is_empty());
+    });
+}
+
+#[test]
+fn pinned_errands_keep_their_result_past_retention() {
+    use frame_support::traits::OnInitialize;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let employer = AccountId32::from_string(ACCOUNT2).unwrap();
+        let pinned = vec![1u8, 1u8];
+        let unpinned = vec![1u8, 2u8];
+        init_processing_errand(&pinned, 5);
+        set_extrinsic_index(1);
+        let errand_id = queue_task(&unpinned, 5, false);
+        init_queued_errand(&unpinned, &errand_id);
+        complete(&pinned, b"result");
+        complete(&unpinned, b"result");
+
+        assert_noop!(
+            TemplateModule::pin_errand(
+                Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+                pinned.clone()
+            ),
+            Error::<Test>::NotErrandOwner
+        );
+        assert_ok!(TemplateModule::pin_errand(
+            Origin::signed(employer.clone()),
+            pinned.clone()
+        ));
+
+        TemplateModule::on_initialize(6);
+        assert_eq!(b"result".to_vec(), TemplateModule::errand(&pinned).unwrap().result);
+        assert!(TemplateModule::errand(&unpinned).unwrap().result.is_empty());
+
+        // unpinning starts a new retention period
+        System::set_block_number(6);
+        assert_ok!(TemplateModule::unpin_errand(
+            Origin::signed(employer.clone()),
+            pinned.clone()
+        ));
+        assert_noop!(
+            TemplateModule::unpin_errand(Origin::signed(employer.clone()), pinned.clone()),
+            Error::<Test>::ErrandNotPinned
+        );
+        TemplateModule::on_initialize(11);
+        assert!(TemplateModule::errand(&pinned).unwrap().result.is_empty());
+    });
+}
+
+#[test]
+fn future_block_saturates_at_max_block_number() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        assert_eq!(15, TemplateModule::future_block(5));
+
+        System::set_block_number(u64::max_value() - 1);
+        assert_eq!(u64::max_value(), TemplateModule::future_block(1));
+        assert_eq!(u64::max_value(), TemplateModule::future_block(u32::max_value()));
+    });
+}
+
+#[test]
+fn private_errand_results_are_omitted_until_revealed() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let client_account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        let errand_id = init_errand_with_privacy(&cid, 5, true);
+        assert_ok!(TemplateModule::update_errand(
+            Origin::signed(alice()),
+            cid.clone(),
+            b"result".to_vec(),
+            Vec::new(),
+            false,
+            None
+        ));
+
+        assert_eq!(
+            vec![(errand_id.clone(), Vec::new())],
+            TemplateModule::completed_errands_of(client_account.clone(), 10)
+        );
+
+        assert_noop!(
+            TemplateModule::reveal_errand_result(
+                Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+                cid.clone()
+            ),
+            Error::<Test>::NotErrandOwner
+        );
+        assert_ok!(TemplateModule::reveal_errand_result(
+            Origin::signed(client_account.clone()),
+            cid
+        ));
+        assert_eq!(
+            vec![(errand_id, b"result".to_vec())],
+            TemplateModule::completed_errands_of(client_account, 10)
+        );
+    });
+}
+
+#[test]
+fn errand_statuses_reports_missing_errands_as_zero() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let done_cid = vec![1u8, 1u8];
+        let failed_cid = vec![1u8, 2u8];
+        init_processing_errand(&done_cid, 5);
+        let failed_id = queue_task(&failed_cid, 5, false);
+        init_queued_errand(&failed_cid, &failed_id);
+        assert_eq!(
+            vec![1u8, 0u8, 1u8],
+            TemplateModule::errand_statuses(vec![done_cid.clone(), vec![9u8], failed_cid.clone()])
+        );
+
+        assert_ok!(TemplateModule::update_errand(
+            Origin::signed(alice()),
+            done_cid.clone(),
+            b"result".to_vec(),
+            Vec::new(),
+            false,
+            None
+        ));
+        assert_ok!(TemplateModule::fail_errand(
+            Origin::signed(alice()),
+            failed_cid.clone(),
+            Vec::new(),
+            Outcome::Error
+        ));
+        assert_eq!(
+            vec![2u8, 0u8, 3u8],
+            TemplateModule::errand_statuses(vec![done_cid, vec![9u8], failed_cid])
+        );
+        assert_eq!(
+            MAX_STATUS_BATCH,
+            TemplateModule::errand_statuses(vec![vec![9u8]; MAX_STATUS_BATCH + 1]).len()
+        );
+    });
+}
+
+#[test]
+fn service_auth_token_is_sent_as_bearer_header() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::{testing, OffchainExt};
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let (offchain, offchain_state) = testing::TestOffchainExt::new();
+    let mut t = new_test_ext();
+    t.register_extension(OffchainExt::new(offchain));
+
+    t.execute_with(|| {
+        let account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let url = task::task_request_url(
+            &format!("{}", account),
+            &info,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        save_delegate_info(&format!("{}", account), &info).unwrap();
+        let send = || {
+            task::send_task_to_tea_network(
+                &account,
+                &b"cid".to_vec(),
+                &b"errand".to_vec(),
+                &b"localhost:8000".to_vec(),
+                &Vec::new(),
+                &TemplateModule::registered_kind(DEFAULT_KIND).unwrap(),
+            )
+        };
+
+        // without token only the user agent is sent
+        expect_post(&offchain_state, url.clone(), "ok");
+        assert!(send());
+
+        StorageValueRef::persistent(task::LOCAL_STORAGE_SERVICE_AUTH_TOKEN_KEY.as_bytes())
+            .set(&b"secret".to_vec());
+        offchain_state
+            .write()
+            .expect_request(testing::PendingRequest {
+                method: "POST".into(),
+                uri: url,
+                body: b"post body".to_vec(),
+                headers: vec![
+                    ("User-Agent".into(), "tearust".into()),
+                    ("Authorization".into(), "Bearer secret".into()),
+                ],
+                response: Some(br#"{"data":"ok"}"#.to_vec()),
+                sent: true,
+                ..Default::default()
+            });
+        assert!(send());
+    });
+}
+
+#[test]
+fn init_errand_requires_task_queued_by_employer() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender_account = AccountId32::from_string(ACCOUNT1).unwrap();
+        let sender = Origin::signed(sender_account.clone());
+        let cid = vec![1u8, 1u8];
+        register_client(5);
+        let errand_id = queue_task(&cid, 5, false);
+
+        assert_noop!(
+            TemplateModule::init_errand(
+                sender.clone(),
+                AccountId32::from_string(ACCOUNT2).unwrap(),
+                vec![9u8],
+                cid.clone()
+            ),
+            Error::<Test>::UnknownErrand
+        );
+
+        // ACCOUNT1 is a client sending its own tasks, but did not queue `errand_id`
+        assert_ok!(TemplateModule::request_delegate(
+            sender.clone(),
+            sender_account.clone(),
+            sender_account.clone(),
+            vec![0u8],
+            5
+        ));
+        assert_noop!(
+            TemplateModule::init_errand(
+                sender.clone(),
+                sender_account,
+                errand_id.clone(),
+                cid.clone()
+            ),
+            Error::<Test>::EmployerMismatch
+        );
+
+        System::set_block_number(1 + MAX_INIT_ERRAND_DELAY as u64 + 1);
+        assert_noop!(
+            TemplateModule::init_errand(
+                sender.clone(),
+                AccountId32::from_string(ACCOUNT2).unwrap(),
+                errand_id.clone(),
+                cid.clone()
+            ),
+            Error::<Test>::UnknownErrand
+        );
+
+        System::set_block_number(1 + MAX_INIT_ERRAND_DELAY as u64);
+        init_queued_errand(&cid, &errand_id);
+        assert_eq!(errand_id, TemplateModule::errand(&cid).unwrap().errand_id);
+    });
+}
+
+#[test]
+fn ensure_bounded_accepts_data_up_to_max() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(TemplateModule::ensure_bounded(&[], 0, Error::<Test>::CidTooLong));
+        assert_ok!(TemplateModule::ensure_bounded(&[0u8; 4], 4, Error::<Test>::CidTooLong));
+        assert_eq!(
+            Err(Error::<Test>::CidTooLong.into()),
+            TemplateModule::ensure_bounded(&[0u8; 5], 4, Error::<Test>::CidTooLong)
+        );
+    });
+}
+
+#[test]
+fn begin_task_rejects_long_description_cid() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        assert_noop!(
+            TemplateModule::begin_task(
+                Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+                AccountId32::from_string(ACCOUNT2).unwrap(),
+                vec![1u8; MAX_CID_LENGTH + 1],
+                5,
+                Vec::new(),
+                false,
+                None,
+                DEFAULT_KIND,
+                None
+            ),
+            Error::<Test>::CidTooLong.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+        );
+    });
+}
+
+#[test]
+fn cid_validation_agrees_with_begin_task() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        let cids = vec![
+            vec![1u8],
+            vec![2u8; 46],
+            vec![3u8; MAX_CID_LENGTH],
+            vec![4u8; MAX_CID_LENGTH + 1],
+            vec![5u8; 4 * MAX_CID_LENGTH],
+        ];
+        for (i, cid) in cids.iter().enumerate() {
+            set_extrinsic_index(i as u32);
+            let accepted = TemplateModule::begin_task(
+                Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+                AccountId32::from_string(ACCOUNT2).unwrap(),
+                cid.clone(),
+                5,
+                Vec::new(),
+                false,
+                None,
+                DEFAULT_KIND,
+                None,
+            )
+            .is_ok();
+            assert_eq!(
+                accepted,
+                TemplateModule::is_valid_cid(cid),
+                "cid of {} bytes",
+                cid.len()
+            );
+        }
+        assert!(TemplateModule::is_valid_cid(&cids[2]));
+        assert!(!TemplateModule::is_valid_cid(&cids[3]));
+    });
+}
+
+#[test]
+fn errand_lifecycle_through_offchain_worker() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        let worker = alice();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let delegator = AccountId32::from_string(ACCOUNT1).unwrap();
+        let cid = vec![1u8, 1u8];
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+
+        System::set_block_number(1);
+        register_worker(&worker);
+        // the worker is paid on completion, so it needs an account
+        <Balances as frame_support::traits::Currency<_>>::make_free_balance_be(&worker, 100);
+        assert_ok!(TemplateModule::request_delegate(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            delegator.clone(),
+            b"localhost:8000".to_vec(),
+            5
+        ));
+        assert_ok!(TemplateModule::update_delegate_status(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            worker.clone()
+        ));
+        Delegations::<Test>::insert(&client, &worker, vec![0xabu8]);
+        save_delegate_info(&format!("{}", delegator), &info).unwrap();
+
+        System::set_block_number(2);
+        let errand_id = queue_task_of(&worker, &client, &cid, 5, false);
+        let url = task::task_request_url(
+            &format!("{}", delegator),
+            &info,
+            &cid,
+            &errand_id,
+            &b"localhost:8000".to_vec(),
+            &vec![0xabu8],
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        expect_post(&offchain_state, url, "ok");
+
+        run_offchain_at(2 + ConfirmationDepth::get());
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(1, results.len());
+        assert!(results[0].is_ok());
+        assert_eq!(
+            ErrandStatus::Processing,
+            TemplateModule::errand(&cid).unwrap().status
+        );
+
+        complete(&cid, b"result");
+        assert_eq!(ErrandStatus::Done, TemplateModule::errand(&cid).unwrap().status);
+    });
+}
+
+#[test]
+fn unsigned_pool_transactions_are_dispatched() {
+    use frame_support::traits::ReservableCurrency;
+    use frame_system::offchain::SubmitTransaction;
+
+    let mut t = new_offchain_test_ext(&[]);
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+        assert_eq!(5, Balances::reserved_balance(&client));
+
+        SubmitTransaction::<Test, Call<Test>>::submit_unsigned_transaction(
+            Call::unreserve(client.clone(), 5).into(),
+        )
+        .unwrap();
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(1, results.len());
+        assert!(results[0].is_ok());
+        assert_eq!(0, Balances::reserved_balance(&client));
+    });
+}
+
+#[test]
+fn offchain_config_reflects_runtime_values() {
+    new_test_ext().execute_with(|| {
+        let config = TemplateModule::offchain_config();
+        assert_eq!(TEA_SEND_TASK_TIMEOUT_PERIOD, config.send_task_timeout);
+        assert_eq!(b"http://".to_vec(), config.service_url_prefix);
+        assert_eq!(10, config.poll_interval);
+        assert_eq!(MAX_INIT_ERRAND_DELAY, config.max_init_errand_delay);
+        assert!(config.polling_enabled);
+    });
+}
+
+#[test]
+fn reserved_aggregate_saturates() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+        assert_eq!(5, TemplateModule::total_reserved());
+
+        for _ in 0..8 {
+            TemplateModule::track_reserve(&client, u64::max_value() / 4);
+        }
+        assert_eq!(u64::max_value(), TemplateModule::total_reserved());
+        assert_eq!(u64::max_value(), TemplateModule::client_reserved(&client));
+
+        TemplateModule::track_release(&client, u64::max_value());
+        assert_eq!(0, TemplateModule::total_reserved());
+        TemplateModule::track_release(&client, 1);
+        assert_eq!(0, TemplateModule::total_reserved());
+    });
+}
+
+#[test]
+fn employer_and_worker_can_comment_errand() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let employer = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+
+        assert_ok!(TemplateModule::add_comment(
+            Origin::signed(employer.clone()),
+            cid.clone(),
+            b"question".to_vec()
+        ));
+        System::set_block_number(2);
+        assert_ok!(TemplateModule::add_comment(
+            Origin::signed(worker.clone()),
+            cid.clone(),
+            b"answer".to_vec()
+        ));
+        assert_noop!(
+            TemplateModule::add_comment(Origin::signed(alice()), cid.clone(), b"spam".to_vec()),
+            Error::<Test>::NoRightToComment
+        );
+
+        assert_eq!(
+            vec![
+                (employer, b"question".to_vec(), 1),
+                (worker, b"answer".to_vec(), 2)
+            ],
+            TemplateModule::errand_comments(&cid)
+        );
+    });
+}
+
+#[test]
+fn long_task_content_moves_to_post_body() {
+    use crate::delegate::DelegateInfo;
+
+    new_offchain_test_ext(&[]).ext.execute_with(|| {
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let short = task::task_request(
+            "client",
+            &info,
+            &b"cid".to_vec(),
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        assert!(short.url.contains("content=636964"));
+        assert!(short.body.is_none());
+
+        let long_cid = vec![0xabu8; task::MAX_URL_LENGTH / 2];
+        let long = task::task_request(
+            "client",
+            &info,
+            &long_cid,
+            &b"errand".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        assert!(long.url.len() <= task::MAX_URL_LENGTH);
+        assert_eq!(
+            "http://localhost:8000/api/service/client/657272616e64/03?proof=",
+            long.url
+        );
+        assert_eq!(
+            Some(format!("content={}", hex::encode(&long_cid)).into_bytes()),
+            long.body
+        );
+    });
+}
+
+#[test]
+fn task_content_in_the_post_body_is_form_encoded() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::testing::PendingRequest;
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+
+    t.ext.execute_with(|| {
+        let account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        save_delegate_info(&format!("{}", account), &info).unwrap();
+        let errand_id = b"errand".to_vec();
+        let long_cid = vec![0xabu8; task::MAX_URL_LENGTH / 2];
+        let request = task::task_request(
+            &format!("{}", account),
+            &info,
+            &long_cid,
+            &errand_id,
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        offchain_state.write().expect_request(PendingRequest {
+            method: "POST".into(),
+            uri: request.url,
+            body: request.body.unwrap(),
+            headers: vec![
+                ("User-Agent".into(), "tearust".into()),
+                ("Content-Type".into(), task::FORM_CONTENT_TYPE.into()),
+            ],
+            response: Some(br#"{"data":"ok"}"#.to_vec()),
+            sent: true,
+            ..Default::default()
+        });
+        assert!(task::send_task_to_tea_network(
+            &account,
+            &long_cid,
+            &errand_id,
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            &TemplateModule::registered_kind(DEFAULT_KIND).unwrap(),
+        ));
+    });
+}
+
+#[test]
+fn block_hash_randomness_follows_parent_hash() {
+    use codec::Encode;
+    use frame_support::traits::Randomness;
+    use sp_core::H256;
+    use sp_runtime::traits::{BlakeTwo256, Hash};
+
+    new_test_ext().execute_with(|| {
+        System::set_parent_hash(H256::repeat_byte(1));
+        let first = BlockHashRandomness::<Test>::random_seed();
+        assert_eq!(
+            BlakeTwo256::hash(&(H256::repeat_byte(1), &[0u8; 0][..]).encode()),
+            first
+        );
+        assert_ne!(first, BlockHashRandomness::<Test>::random(b"subject"));
+
+        System::set_parent_hash(H256::repeat_byte(2));
+        assert_ne!(first, BlockHashRandomness::<Test>::random_seed());
+    });
+}
+
+#[test]
+fn flush_tasks_fails_without_queued_tasks() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        assert_noop!(
+            TemplateModule::flush_tasks(Origin::root()),
+            Error::<Test>::NoTasksToFlush
+        );
+    });
+}
+
+#[test]
+fn flush_tasks_keeps_failed_tasks_queued() {
+    use sp_std::convert::TryFrom;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let ok_cid = vec![1u8, 1u8];
+        let full_cid = vec![1u8, 2u8];
+        register_client(5);
+        let ok_id = queue_task(&ok_cid, 5, false);
+        let full_id = queue_task(&full_cid, 5, false);
+        let full =
+            BoundedVec::<ErrandId, MaxErrandsPerCid>::try_from(vec![vec![0u8]; MAX_ERRANDS_PER_CID])
+                .unwrap();
+        ErrandsByCid::insert(&full_cid, full);
+
+        assert_ok!(TemplateModule::flush_tasks(Origin::root()));
+
+        assert_eq!(ok_id, TemplateModule::errand(&ok_cid).unwrap().errand_id);
+        assert_eq!(None, TemplateModule::errand(&full_cid));
+        let remaining = Tasks::<Test>::get(1);
+        assert_eq!(1, remaining.len());
+        assert_eq!(full_id, remaining[0].errand_id);
+        assert_eq!(full_cid, remaining[0].description_cid);
+        let flushed = TestEvent::abc(RawEvent::TasksFlushed(1, 1));
+        assert!(System::events().iter().any(|r| r.event == flushed));
+    });
+}
+
+#[test]
+fn initialized_tasks_leave_the_queue() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        let cid = vec![1u8, 1u8];
+        let errand_id = queue_task(&cid, 5, false);
+        queue_task(&vec![1u8, 2u8], 5, false);
+        assert_eq!(2, TemplateModule::total_pending_tasks());
+
+        init_queued_errand(&cid, &errand_id);
+        assert_eq!(1, TemplateModule::total_pending_tasks());
+        assert_eq!(None, TemplateModule::task_location(&errand_id));
+        assert!(!Tasks::<Test>::get(1).iter().any(|task| task.errand_id == errand_id));
+    });
+}
+
+#[test]
+fn pending_task_counter_tracks_queued_and_removed_tasks() {
+    use frame_support::IterableStorageMap;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        let sender = Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap());
+        let first_id = queue_task(&vec![1u8, 1u8], 5, false);
+        queue_task(&vec![1u8, 2u8], 5, false);
+        System::set_block_number(2);
+        queue_task(&vec![1u8, 3u8], 5, false);
+        assert_eq!(3, TemplateModule::total_pending_tasks());
+
+        assert_ok!(TemplateModule::cancel_task_by_id(sender, first_id));
+        assert_eq!(2, TemplateModule::total_pending_tasks());
+        // only the task queued at the current height is flushed
+        assert_ok!(TemplateModule::flush_tasks(Origin::root()));
+        assert_eq!(1, TemplateModule::total_pending_tasks());
+        let stored: u64 = Tasks::<Test>::iter_values()
+            .map(|tasks| tasks.len() as u64)
+            .sum();
+        assert_eq!(stored, TemplateModule::total_pending_tasks());
+
+        // chains queueing tasks before the counter existed count them once on upgrade
+        PendingTaskCount::kill();
+        StorageVersion::put(Releases::V2_0_0);
+        migration::count_pending_tasks::<Test>();
+        assert_eq!(1, TemplateModule::total_pending_tasks());
+        assert_eq!(Releases::V3_0_0, StorageVersion::get());
+    });
+}
+
+#[test]
+fn worker_claims_are_limited() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let cids: Vec<Vec<u8>> = (1u8..=4).map(|i| vec![1u8, i]).collect();
+        let first_id = init_processing_errand(&cids[0], 5);
+        assert_eq!(first_id, TemplateModule::errand(&cids[0]).unwrap().errand_id);
+        let ids: Vec<Vec<u8>> = cids[1..].iter().map(|cid| queue_task(cid, 5, false)).collect();
+        init_queued_errand(&cids[1], &ids[0]);
+        init_queued_errand(&cids[2], &ids[1]);
+        assert_eq!(3, TemplateModule::active_claims(&worker));
+
+        assert_noop!(
+            TemplateModule::init_errand(
+                Origin::signed(worker.clone()),
+                AccountId32::from_string(ACCOUNT2).unwrap(),
+                ids[2].clone(),
+                cids[3].clone()
+            ),
+            Error::<Test>::TooManyClaims
+        );
+
+        complete(&cids[0], b"result");
+        assert_eq!(2, TemplateModule::active_claims(&worker));
+        assert_eq!(None, TemplateModule::errand_worker(&cids[0]));
+        init_queued_errand(&cids[3], &ids[2]);
+        assert_eq!(3, TemplateModule::active_claims(&worker));
+    });
+}
+
+#[test]
+fn expired_claims_are_reaped_by_the_offchain_worker() {
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let cid = vec![1u8, 1u8];
+        let errand_id = init_processing_errand(&cid, 5);
+        assert_eq!(Some(21), TemplateModule::claim_expiry(&cid));
+
+        // block 20 polls results, which would need http expectations
+        run_offchain_at(19);
+        assert!(pool_state.read().transactions.is_empty());
+        System::set_block_number(20);
+        assert_noop!(
+            TemplateModule::expire_claim(Origin::signed(alice()), cid.clone()),
+            Error::<Test>::ClaimNotExpired
+        );
+
+        run_offchain_at(21);
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(1, results.len());
+        assert!(results[0].is_ok());
+        assert_eq!(None, TemplateModule::errand_worker(&cid));
+        assert_eq!(0, TemplateModule::active_claims(&worker));
+        assert!(!TemplateModule::processing_errands().contains(&cid));
+        assert_eq!(
+            ErrandStatus::Processing,
+            TemplateModule::errand(&cid).unwrap().status
+        );
+
+        // the task is queued again and can be claimed
+        assert_eq!(errand_id, Tasks::<Test>::get(21)[0].errand_id);
+        init_queued_errand(&cid, &errand_id);
+        assert_eq!(Some(41), TemplateModule::claim_expiry(&cid));
+    });
+}
+
+#[test]
+fn errands_claimed_again_are_indexed_once() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let errand_id = init_processing_errand(&cid, 5);
+        System::set_block_number(21);
+        assert_ok!(TemplateModule::expire_claim(Origin::signed(alice()), cid.clone()));
+        init_queued_errand(&cid, &errand_id);
+
+        assert_eq!(vec![errand_id.clone()], TemplateModule::errands_by_cid(&cid));
+        assert_eq!(
+            vec![(errand_id, 1)],
+            TemplateModule::errands_created_between(0, 30, 10)
+        );
+    });
+}
+
+#[test]
+fn service_path_prefix_is_used_in_request_url() {
+    use crate::delegate::{DelegateInfo, LOCAL_STORAGE_SERVICE_PATH_PREFIX_KEY};
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    new_offchain_test_ext(&[]).ext.execute_with(|| {
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        let url = || {
+            task::task_request_url(
+                "client",
+                &info,
+                &b"cid".to_vec(),
+                &b"errand".to_vec(),
+                &b"localhost:8000".to_vec(),
+                &Vec::new(),
+                DEFAULT_KIND_ACTION_PATH,
+            )
+            .unwrap()
+        };
+        assert!(url().starts_with("http://localhost:8000/api/service/client/"));
+
+        StorageValueRef::persistent(LOCAL_STORAGE_SERVICE_PATH_PREFIX_KEY.as_bytes())
+            .set(&b"/tea/v2".to_vec());
+        assert!(url().starts_with("http://localhost:8000/tea/v2/service/client/"));
+    });
+}
+
+#[test]
+fn fee_events_carry_amounts() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let failed_cid = vec![1u8, 1u8];
+        let done_cid = vec![1u8, 2u8];
+        init_processing_errand(&failed_cid, 5);
+        let errand_id = queue_task(&done_cid, 5, false);
+        init_queued_errand(&done_cid, &errand_id);
+
+        assert_ok!(TemplateModule::fail_errand(
+            Origin::signed(alice()),
+            failed_cid,
+            Vec::new(),
+            Outcome::Error
+        ));
+        complete(&done_cid, b"result");
+
+        let events: Vec<TestEvent> = System::events().into_iter().map(|r| r.event).collect();
+        assert!(events.contains(&TestEvent::abc(RawEvent::FeeReserved(client.clone(), 5))));
+        assert!(events.contains(&TestEvent::abc(RawEvent::FeeRefunded(client.clone(), 5))));
+        assert!(events.contains(&TestEvent::abc(RawEvent::FeeSettled(client, worker, 5))));
+    });
+}
+
+#[test]
+fn repeated_idempotency_key_queues_one_task() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+
+        let begin = || {
+            TemplateModule::begin_task(
+                Origin::signed(sender.clone()),
+                client.clone(),
+                vec![1u8, 1u8],
+                5,
+                Vec::new(),
+                false,
+                Some(b"key".to_vec()),
+                DEFAULT_KIND,
+                None,
+            )
+        };
+        assert_ok!(begin());
+        set_extrinsic_index(1);
+        assert_ok!(begin());
+
+        let tasks = Tasks::<Test>::get(1);
+        assert_eq!(tasks.len(), 1);
+        let errand_id = tasks[0].errand_id.clone();
+        assert_eq!(TemplateModule::client_reserved(&client), 10);
+        assert_eq!(
+            System::events().last().unwrap().event,
+            TestEvent::abc(RawEvent::TaskDeduplicated(sender.clone(), errand_id.clone()))
+        );
+
+        let expiry = 1 + IDEMPOTENCY_KEY_LIFETIME as u64;
+        assert_eq!(
+            TemplateModule::idempotency_key(&sender, &b"key".to_vec()),
+            Some(errand_id)
+        );
+        TemplateModule::sweep_idempotency_keys(expiry);
+        assert_eq!(TemplateModule::idempotency_key(&sender, &b"key".to_vec()), None);
+    });
+}
+
+#[test]
+fn errands_created_between_returns_sub_range() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        let mut ids = Vec::new();
+        for block in 1..=4u64 {
+            System::set_block_number(block);
+            set_extrinsic_index(block as u32);
+            let cid = vec![1u8, block as u8];
+            let errand_id = queue_task(&cid, 5, false);
+            init_queued_errand(&cid, &errand_id);
+            complete(&cid, b"result");
+            ids.push((errand_id, block));
+        }
+
+        assert_eq!(
+            TemplateModule::errands_created_between(2, 3, 10),
+            ids[1..3].to_vec()
+        );
+        assert_eq!(
+            TemplateModule::errands_created_between(2, 3, 1),
+            ids[1..2].to_vec()
+        );
+        assert_eq!(TemplateModule::errands_created_between(0, u64::max_value(), 10), ids);
+        assert!(TemplateModule::errands_created_between(3, 2, 10).is_empty());
+    });
+}
+
+#[test]
+fn errand_outcomes_round_trip_through_storage() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        register_client(5);
+        let queue = |cid: &Vec<u8>| {
+            let errand_id = queue_task(cid, 5, false);
+            init_queued_errand(cid, &errand_id);
+        };
+
+        let done_cid = vec![1u8, 0u8];
+        queue(&done_cid);
+        assert_eq!(TemplateModule::errand_outcome(&done_cid), None);
+        complete(&done_cid, b"result");
+        assert_eq!(TemplateModule::errand_outcome(&done_cid), Some(Outcome::Success));
+
+        for (i, outcome) in [Outcome::Timeout, Outcome::Refused, Outcome::Error]
+            .iter()
+            .enumerate()
+        {
+            let cid = vec![1u8, i as u8 + 1];
+            queue(&cid);
+            assert_ok!(TemplateModule::fail_errand(
+                Origin::signed(alice()),
+                cid.clone(),
+                Vec::new(),
+                *outcome
+            ));
+            assert_eq!(TemplateModule::errand_outcome(&cid), Some(*outcome));
+        }
+    });
+}
+
+#[test]
+fn fail_errand_rejects_success_outcome() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+
+        assert_noop!(
+            TemplateModule::fail_errand(Origin::signed(alice()), cid, Vec::new(), Outcome::Success),
+            Error::<Test>::InvalidOutcome
+        );
+    });
+}
+
+#[test]
+fn stored_results_of_vanished_errands_are_dropped() {
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(10);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        let results = vec![(cid.clone(), parse_result_info(r#","status":"success""#))];
+        let value_ref = StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes());
+        value_ref.set(&results);
+
+        // the block which initialized the errand is retracted before the next tick
+        Errands::remove(&cid);
+        ProcessingErrands::kill();
+        TemplateModule::update_errand_task_results(10);
+
+        assert!(pool_state.read().transactions.is_empty());
+        assert_eq!(
+            value_ref.get::<Vec<(Cid, ErrandResultInfo)>>(),
+            Some(Some(Vec::new()))
+        );
+    });
+}
+
+#[test]
+fn unacceptable_results_leave_the_errand_processing() {
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(10);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        let value_ref = StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes());
+        let mut empty = parse_result_info(r#","status":"success""#);
+        empty.result_cid = Vec::new();
+        value_ref.set(&vec![(cid.clone(), empty)]);
+
+        // the mock runtime rejects empty results
+        TemplateModule::update_errand_task_results(10);
+        assert!(pool_state.read().transactions.is_empty());
+        assert_eq!(
+            ErrandStatus::Processing,
+            TemplateModule::errand(&cid).unwrap().status
+        );
+
+        let results = vec![(cid.clone(), parse_result_info(r#","status":"success""#))];
+        value_ref.set(&results);
+        TemplateModule::update_errand_task_results(10);
+        assert!(apply_pool_transactions(&pool_state).iter().all(|r| r.is_ok()));
+        let errand = TemplateModule::errand(&cid).unwrap();
+        assert_eq!(ErrandStatus::Done, errand.status);
+        assert_eq!(b"result".to_vec(), errand.result);
+    });
+}
+
+#[test]
+fn failed_completions_are_submitted_again_on_later_ticks() {
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(10);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        let results = vec![(cid.clone(), parse_result_info(r#","status":"success""#))];
+        StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes()).set(&results);
+        let buffer = StorageValueRef::persistent(LOCAL_STORAGE_PENDING_COMPLETIONS_KEY.as_bytes());
+
+        set_pool_unavailable(true);
+        TemplateModule::update_errand_task_results(10);
+        assert!(pool_state.read().transactions.is_empty());
+        let pending = buffer.get::<Vec<PendingCompletion>>().unwrap().unwrap();
+        assert_eq!(1, pending.len());
+        assert_eq!(cid, pending[0].description_cid);
+
+        // a later tick without new results drains the buffer
+        set_pool_unavailable(false);
+        TemplateModule::update_errand_task_results(11);
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(1, results.len());
+        assert!(results[0].is_ok());
+        assert_eq!(ErrandStatus::Done, TemplateModule::errand(&cid).unwrap().status);
+        assert_eq!(Some(Some(Vec::new())), buffer.get::<Vec<PendingCompletion>>());
+    });
+}
+
+#[test]
+fn result_content_type_is_stored_from_response_header() {
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(10);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        let errand_id = init_processing_errand(&cid, 5);
+        offchain_state
+            .write()
+            .expect_request(sp_core::offchain::testing::PendingRequest {
+                method: "POST".into(),
+                uri: format!(
+                    "http://localhost:8000/api/query_errand_execution_result_by_uuid/{}",
+                    hex::encode(&errand_id)
+                ),
+                body: b"post body".to_vec(),
+                headers: vec![("User-Agent".into(), "tearust".into())],
+                response: Some(
+                    br#"{"data":"{\"completed\":true,\"result_cid\":\"result\",\"failed_count\":0}"}"#
+                        .to_vec(),
+                ),
+                response_headers: vec![("content-type".into(), "application/json".into())],
+                sent: true,
+                ..Default::default()
+            });
+
+        assert!(task::fetch_single_task_result(
+            &errand_id,
+            &cid,
+            &b"localhost:8000".to_vec(),
+            &[]
+        ));
+        TemplateModule::update_errand_task_results(10);
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(1, results.len());
+        assert!(results[0].is_ok());
+        assert_eq!(
+            TemplateModule::errand_content_type(&cid),
+            b"application/json".to_vec()
+        );
+
+        let other_cid = vec![1u8, 2u8];
+        set_extrinsic_index(1);
+        let other_id = queue_task(&other_cid, 5, false);
+        init_queued_errand(&other_cid, &other_id);
+        complete(&other_cid, b"result");
+        assert_eq!(
+            TemplateModule::errand_content_type(&other_cid),
+            DEFAULT_CONTENT_TYPE.to_vec()
+        );
+    });
+}
+
+#[test]
+fn employer_extends_errand_deadline() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let employer = Origin::signed(AccountId32::from_string(ACCOUNT2).unwrap());
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        assert_eq!(TemplateModule::claim_expiry(&cid), Some(21));
+
+        assert_noop!(
+            TemplateModule::extend_deadline(Origin::signed(alice()), cid.clone(), 10),
+            Error::<Test>::NotErrandOwner
+        );
+        assert_ok!(TemplateModule::extend_deadline(employer.clone(), cid.clone(), 10));
+        assert_eq!(TemplateModule::claim_expiry(&cid), Some(31));
+        assert_eq!(
+            System::events().last().unwrap().event,
+            TestEvent::abc(RawEvent::DeadlineExtended(
+                AccountId32::from_string(ACCOUNT2).unwrap(),
+                cid.clone(),
+                31
+            ))
+        );
+
+        // clamped to `MaxDeadline` blocks from now
+        assert_ok!(TemplateModule::extend_deadline(employer.clone(), cid.clone(), 100));
+        assert_eq!(TemplateModule::claim_expiry(&cid), Some(51));
+
+        System::set_block_number(51);
+        assert_noop!(
+            TemplateModule::extend_deadline(employer.clone(), cid.clone(), 10),
+            Error::<Test>::DeadlinePassed
+        );
+
+        System::set_block_number(50);
+        complete(&cid, b"result");
+        assert_noop!(
+            TemplateModule::extend_deadline(employer, cid, 10),
+            Error::<Test>::ErrandAlreadyExecuted
+        );
+    });
+}
+
+#[test]
+fn unregistered_local_keys_do_not_dispatch() {
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+
+        run_offchain_at(21);
+        assert!(pool_state.read().transactions.is_empty());
+
+        assert_noop!(
+            TemplateModule::register_worker(Origin::signed(alice()), alice()),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        register_worker(&alice());
+        assert_noop!(
+            TemplateModule::register_worker(Origin::root(), alice()),
+            Error::<Test>::WorkerAlreadyRegistered
+        );
+        run_offchain_at(21);
+        assert_eq!(1, pool_state.read().transactions.len());
+    });
+}
+
+#[test]
+fn archiving_errands_makes_room_past_the_account_cap() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender = Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap());
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+        for i in 0..MaxErrandsPerAccount::get() {
+            let cid = vec![1u8, i as u8];
+            set_extrinsic_index(i);
+            let errand_id = queue_task(&cid, 5, false);
+            init_queued_errand(&cid, &errand_id);
+            complete(&cid, b"result");
+        }
+
+        let new_cid = vec![2u8];
+        assert_noop!(
+            TemplateModule::begin_task(
+                sender,
+                client.clone(),
+                new_cid.clone(),
+                5,
+                Vec::new(),
+                false,
+                None,
+                DEFAULT_KIND,
+                None
+            ),
+            Error::<Test>::TooManyErrandsForAccount.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+        );
+
+        let archived = vec![1u8, 0u8];
+        assert_noop!(
+            TemplateModule::archive_errand(Origin::signed(alice()), archived.clone()),
+            Error::<Test>::NotErrandOwner
+        );
+        assert_ok!(TemplateModule::archive_errand(
+            Origin::signed(client.clone()),
+            archived.clone()
+        ));
+        assert_eq!(None, TemplateModule::errand(&archived));
+        assert!(!TemplateModule::account_errands(&client).contains(&archived));
+        assert!(TemplateModule::errand_result_hash(&archived).is_some());
+
+        set_extrinsic_index(100);
+        let errand_id = queue_task(&new_cid, 5, false);
+        init_queued_errand(&new_cid, &errand_id);
+        assert_noop!(
+            TemplateModule::archive_errand(Origin::signed(client), new_cid),
+            Error::<Test>::ErrandStillProcessing
+        );
+    });
+}
+
+#[test]
+fn u64_to_ascii_formats_decimal_digits() {
+    assert_eq!(b"0".to_vec(), u64_to_ascii(0));
+    assert_eq!(b"4096".to_vec(), u64_to_ascii(4096));
+    assert_eq!(
+        u64::max_value().to_string().into_bytes(),
+        u64_to_ascii(u64::max_value())
+    );
+}
+
+#[test]
+fn failed_settlement_leaves_errand_and_reserve_untouched() {
+    use frame_support::traits::Currency;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        // the fee would overflow the free balance of the worker
+        Balances::make_free_balance_be(&worker, u64::max_value() - 1);
+
+        assert_noop!(
+            TemplateModule::update_errand(
+                Origin::signed(alice()),
+                cid.clone(),
+                b"result".to_vec(),
+                Vec::new(),
+                false,
+                None
+            ),
+            pallet_balances::Error::<Test, pallet_balances::DefaultInstance>::Overflow
+        );
+        assert_eq!(10, Balances::reserved_balance(&client));
+        assert_eq!(
+            ErrandStatus::Processing,
+            TemplateModule::errand(&cid).unwrap().status
+        );
+
+        Balances::make_free_balance_be(&worker, 1000);
+        complete(&cid, b"result");
+        assert_eq!(1005, Balances::free_balance(&worker));
+        assert_eq!(5, Balances::reserved_balance(&client));
+    });
+}
+
+#[test]
+fn salted_errand_id_is_reproducible() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+
+        let errand_id = TemplateModule::derive_errand_id(&sender, b"salt");
+        assert_eq!(errand_id, TemplateModule::derive_errand_id(&sender, b"salt"));
+        assert_ne!(errand_id, TemplateModule::derive_errand_id(&sender, b"other"));
+        assert_ne!(errand_id, TemplateModule::derive_errand_id(&client, b"salt"));
+
+        let begin = |cid: Vec<u8>| {
+            TemplateModule::begin_task_with_salt(
+                Origin::signed(sender.clone()),
+                client.clone(),
+                cid,
+                5,
+                Vec::new(),
+                false,
+                b"salt".to_vec(),
+                DEFAULT_KIND,
+            )
+        };
+        assert_ok!(begin(vec![1u8, 1u8]));
+        assert_eq!(errand_id, Tasks::<Test>::get(1)[0].errand_id);
+        assert_noop!(
+            begin(vec![1u8, 2u8]),
+            Error::<Test>::DuplicateErrandId.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+        );
+    });
+}
+
+#[test]
+fn draining_submits_fetched_results_without_polling() {
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(10);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        let results = vec![(cid.clone(), parse_result_info(r#","status":"success""#))];
+        StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes()).set(&results);
+        StorageValueRef::persistent(LOCAL_STORAGE_DRAINING_KEY.as_bytes()).set(&true);
+
+        run_offchain_at(10);
+        assert!(offchain_state.read().requests.is_empty());
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(1, results.len());
+        assert!(results[0].is_ok());
+        assert_eq!(ErrandStatus::Done, TemplateModule::errand(&cid).unwrap().status);
+    });
+}
+
+#[test]
+fn status_transitions_are_logged_in_order() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        register_client(5);
+        let errand_id = queue_task(&cid, 5, false);
+        assert!(TemplateModule::status_log(&errand_id).is_empty());
+
+        System::set_block_number(2);
+        init_queued_errand(&cid, &errand_id);
+        System::set_block_number(4);
+        complete(&cid, b"result");
+        assert_eq!(
+            vec![(1u8, 2u64), (2u8, 4u64)],
+            TemplateModule::status_log(&errand_id)
+        );
+
+        let failed_cid = vec![1u8, 2u8];
+        set_extrinsic_index(1);
+        let failed_id = queue_task(&failed_cid, 5, false);
+        init_queued_errand(&failed_cid, &failed_id);
+        System::set_block_number(7);
+        assert_ok!(TemplateModule::fail_errand(
+            Origin::signed(alice()),
+            failed_cid,
+            Vec::new(),
+            Outcome::Error
+        ));
+        assert_eq!(
+            vec![(1u8, 4u64), (3u8, 7u64)],
+            TemplateModule::status_log(&failed_id)
+        );
+    });
+}
+
+#[test]
+fn queued_task_is_cancelled_by_errand_id() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(3);
+        let sender = Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap());
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+        let errand_id = queue_task(&vec![1u8, 1u8], 5, false);
+        assert_eq!(Some(3), TemplateModule::task_location(&errand_id));
+        assert_eq!(10, TemplateModule::client_reserved(&client));
+
+        System::set_block_number(9);
+        assert_noop!(
+            TemplateModule::cancel_task_by_id(Origin::signed(alice()), errand_id.clone()),
+            Error::<Test>::NoRightToCancelTask
+        );
+        assert_ok!(TemplateModule::cancel_task_by_id(sender.clone(), errand_id.clone()));
+        assert!(Tasks::<Test>::get(3).is_empty());
+        assert_eq!(None, TemplateModule::task_location(&errand_id));
+        assert_eq!(5, TemplateModule::client_reserved(&client));
+        assert_noop!(
+            TemplateModule::cancel_task_by_id(sender.clone(), errand_id),
+            Error::<Test>::TaskNotQueued
+        );
+
+        // initialized tasks leave the queue
+        let cid = vec![1u8, 2u8];
+        let errand_id = queue_task(&cid, 5, false);
+        init_queued_errand(&cid, &errand_id);
+        assert_noop!(
+            TemplateModule::cancel_task_by_id(sender, errand_id),
+            Error::<Test>::TaskNotQueued
+        );
+    });
+}
+
+#[test]
+fn begin_task_validation_failure_charges_reduced_weight() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender = Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap());
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+
+        let rejected = TemplateModule::begin_task(
+            sender.clone(),
+            client.clone(),
+            vec![1u8, 1u8],
+            0,
+            Vec::new(),
+            false,
+            None,
+            DEFAULT_KIND,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            Some(BEGIN_TASK_VALIDATION_WEIGHT),
+            rejected.post_info.actual_weight
+        );
+        assert_eq!(rejected.error, Error::<Test>::InsufficientFee.into());
+
+        let queued =
+            TemplateModule::begin_task(sender, client, vec![1u8, 1u8], 5, Vec::new(), false, None, DEFAULT_KIND, None)
+                .unwrap();
+        assert_eq!(None, queued.actual_weight);
+    });
+}
+
+#[test]
+fn errand_completion_posts_to_employer_webhook() {
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let mut t = new_offchain_test_ext(&[]);
+    let offchain_state = t.offchain_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(1);
+        let employer = AccountId32::from_string(ACCOUNT2).unwrap();
+        let url = b"http://hooks.example.com/errands".to_vec();
+        assert_noop!(
+            TemplateModule::register_webhook(
+                Origin::signed(employer.clone()),
+                b"ftp://hooks.example.com".to_vec()
+            ),
+            Error::<Test>::InvalidWebhookUrl
+        );
+        assert_ok!(TemplateModule::register_webhook(
+            Origin::signed(employer.clone()),
+            url.clone()
+        ));
+        // the test externalities don't apply offchain indexing
+        StorageValueRef::persistent(&TemplateModule::webhook_key(&employer)).set(&url);
+
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        System::set_block_number(5);
+        complete(&cid, b"result");
+
+        offchain_state
+            .write()
+            .expect_request(sp_core::offchain::testing::PendingRequest {
+                method: "POST".into(),
+                uri: String::from_utf8(url).unwrap(),
+                body: webhook::completion_body(&cid),
+                headers: vec![
+                    ("User-Agent".into(), "tearust".into()),
+                    ("Content-Type".into(), "application/json".into()),
+                ],
+                response: Some(b"ok".to_vec()),
+                sent: true,
+                ..Default::default()
+            });
+        // unfulfilled expected requests fail the test when the offchain state drops
+        run_offchain_at(5);
+    });
+}
+
+#[test]
+fn employers_are_warned_once_before_a_claim_expires() {
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let mut t = new_offchain_test_ext(&[]);
+    let offchain_state = t.offchain_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(1);
+        let employer = AccountId32::from_string(ACCOUNT2).unwrap();
+        let url = b"http://hooks.example.com/errands".to_vec();
+        assert_ok!(TemplateModule::register_webhook(
+            Origin::signed(employer.clone()),
+            url.clone()
+        ));
+        StorageValueRef::persistent(&TemplateModule::webhook_key(&employer)).set(&url);
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        let expires_at = 1 + ClaimTimeout::get();
+
+        // too early, no request is expected
+        run_offchain_at(expires_at - ExpiryWarningBlocks::get() - 1);
+
+        offchain_state
+            .write()
+            .expect_request(sp_core::offchain::testing::PendingRequest {
+                method: "POST".into(),
+                uri: String::from_utf8(url).unwrap(),
+                body: webhook::expiry_body(&cid, expires_at),
+                headers: vec![
+                    ("User-Agent".into(), "tearust".into()),
+                    ("Content-Type".into(), "application/json".into()),
+                ],
+                response: Some(b"ok".to_vec()),
+                sent: true,
+                ..Default::default()
+            });
+        run_offchain_at(expires_at - ExpiryWarningBlocks::get());
+        // an unexpected second request would panic
+        run_offchain_at(expires_at - 1);
+    });
+}
+
+#[test]
+fn results_above_inline_threshold_are_stored_as_ipfs_cid() {
+    use sp_runtime::offchain::storage::StorageValueRef;
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(10);
+        register_worker(&alice());
+        let large_cid = vec![1u8, 1u8];
+        let small_cid = vec![1u8, 2u8];
+        init_processing_errand(&large_cid, 5);
+        let errand_id = queue_task(&small_cid, 5, false);
+        init_queued_errand(&small_cid, &errand_id);
+
+        let large_result = vec![7u8; InlineResultThreshold::get() as usize + 1];
+        assert_noop!(
+            TemplateModule::update_errand(
+                Origin::signed(alice()),
+                large_cid.clone(),
+                large_result.clone(),
+                Vec::new(),
+                false,
+                None
+            ),
+            Error::<Test>::ResultAboveInlineThreshold
+        );
+
+        let mut large = parse_result_info(r#","status":"success""#);
+        large.result_cid = large_result.clone();
+        let small = parse_result_info(r#","status":"success""#);
+        let results = vec![(large_cid.clone(), large), (small_cid.clone(), small)];
+        StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes()).set(&results);
+
+        offchain_state
+            .write()
+            .expect_request(sp_core::offchain::testing::PendingRequest {
+                method: "POST".into(),
+                uri: format!("{}/api/v0/add", ipfs::DEFAULT_IPFS_API_URL),
+                body: ipfs::upload_body(&large_result),
+                headers: vec![
+                    ("User-Agent".into(), "tearust".into()),
+                    (
+                        "Content-Type".into(),
+                        "multipart/form-data; boundary=abc-demo-boundary".into(),
+                    ),
+                ],
+                response: Some(br#"{"Name":"file","Hash":"QmLargeResult","Size":"25"}"#.to_vec()),
+                sent: true,
+                ..Default::default()
+            });
+        TemplateModule::update_errand_task_results(10);
+
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(2, results.len());
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(
+            b"QmLargeResult".to_vec(),
+            TemplateModule::errand(&large_cid).unwrap().result
+        );
+        assert!(TemplateModule::result_is_cid(&large_cid));
+        assert_eq!(
+            b"result".to_vec(),
+            TemplateModule::errand(&small_cid).unwrap().result
+        );
+        assert!(!TemplateModule::result_is_cid(&small_cid));
+    });
+}
+
+#[test]
+fn higher_bids_are_sent_first_across_heights() {
+    use frame_support::traits::OnInitialize;
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        let low_cid = vec![1u8, 1u8];
+        let equal_cid = vec![1u8, 2u8];
+        let high_cid = vec![1u8, 3u8];
+        let low_id = queue_task(&low_cid, 5, false);
+        set_extrinsic_index(1);
+        let equal_id = queue_task(&equal_cid, 5, false);
+
+        System::set_block_number(2);
+        let high_id = queue_task(&high_cid, 9, false);
+        assert_eq!(
+            vec![(9, high_id.clone()), (5, low_id.clone()), (5, equal_id.clone())],
+            TemplateModule::pending_bids()
+        );
+        let sent: Vec<ErrandId> = TemplateModule::bid_ordered_tasks(2 + ConfirmationDepth::get())
+            .into_iter()
+            .map(|task| task.errand_id)
+            .collect();
+        assert_eq!(vec![high_id.clone(), low_id.clone(), equal_id.clone()], sent);
+
+        init_queued_errand(&high_cid, &high_id);
+        assert_ok!(TemplateModule::cancel_task_by_id(
+            Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+            low_id
+        ));
+        assert_eq!(vec![(5, equal_id)], TemplateModule::pending_bids());
+
+        TemplateModule::on_initialize(1 + MAX_INIT_ERRAND_DELAY as u64);
+        assert_eq!(1, TemplateModule::pending_bids().len());
+        TemplateModule::on_initialize(2 + MAX_INIT_ERRAND_DELAY as u64);
+        assert!(TemplateModule::pending_bids().is_empty());
+    });
+}
+
+#[test]
+fn tasks_are_sent_after_confirmation_depth() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(3);
+        register_client(5);
+        let cid = vec![1u8, 1u8];
+        let errand_id = queue_task(&cid, 5, false);
+
+        for block_number in 3..3 + ConfirmationDepth::get() {
+            assert!(TemplateModule::bid_ordered_tasks(block_number).is_empty());
+        }
+        let confirmed = TemplateModule::bid_ordered_tasks(3 + ConfirmationDepth::get());
+        assert_eq!(1, confirmed.len());
+        assert_eq!(errand_id, confirmed[0].errand_id);
+    });
+}
+
+#[test]
+fn completion_hook_is_called_with_errand_id_and_result() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        let cid = vec![1u8, 1u8];
+        let errand_id = init_processing_errand(&cid, 5);
+        assert!(completions().is_empty());
+
+        complete(&cid, b"result");
+        assert_eq!(vec![(errand_id, b"result".to_vec())], completions());
+        // failures are no completions
+        let failed_cid = vec![1u8, 2u8];
+        let failed_id = queue_task(&failed_cid, 5, false);
+        init_queued_errand(&failed_cid, &failed_id);
+        assert_ok!(TemplateModule::fail_errand(
+            Origin::signed(alice()),
+            failed_cid,
+            b"reason".to_vec(),
+            Outcome::Error
+        ));
+        assert_eq!(1, completions().len());
+    });
+}
+
+#[test]
+fn task_arrays_exceeding_their_bound_fail_cleanly() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(1);
+        let bound = MaxTasksPerBlock::get();
+        for i in 0..bound {
+            set_extrinsic_index(i);
+            queue_task(&vec![1u8, i as u8], 1, false);
+        }
+        assert_noop!(
+            TemplateModule::begin_task(
+                Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+                AccountId32::from_string(ACCOUNT2).unwrap(),
+                vec![2u8],
+                1,
+                Vec::new(),
+                false,
+                None,
+                DEFAULT_KIND,
+                None
+            ),
+            Error::<Test>::TooManyTasksInBlock.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+        );
+
+        // values written before the bound, or by a buggy migration, read as empty
+        let oversized = vec![Tasks::<Test>::get(1)[0].clone(); bound as usize + 1];
+        frame_support::storage::unhashed::put(&Tasks::<Test>::hashed_key_for(2), &oversized);
+        assert!(Tasks::<Test>::get(2).is_empty());
+        assert_eq!(None, TemplateModule::load_tasks(&2));
+
+        let mut tasks = Tasks::<Test>::get(1);
+        let extra = tasks[0].clone();
+        assert_eq!(Err(extra.clone()), tasks.try_push(extra));
+    });
+}
+
+#[test]
+fn completion_proofs_are_verified_against_the_worker() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = alice();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        <Balances as frame_support::traits::Currency<_>>::make_free_balance_be(&worker, 100);
+        assert_ok!(TemplateModule::request_delegate(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            AccountId32::from_string(ACCOUNT1).unwrap(),
+            vec![0u8],
+            5
+        ));
+        assert_ok!(TemplateModule::update_delegate_status(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            worker.clone()
+        ));
+        let errand_id = queue_task_of(&worker, &client, &cid, 5, false);
+        assert_ok!(TemplateModule::init_errand(
+            Origin::signed(worker.clone()),
+            client,
+            errand_id.clone(),
+            cid.clone()
+        ));
+
+        let result = b"result".to_vec();
+        let message = TemplateModule::completion_message(
+            &errand_id,
+            &<Test as frame_system::Trait>::Hashing::hash(&result),
+        );
+        let sign = |seed: &str| {
+            sp_core::sr25519::Pair::from_string(seed, None)
+                .unwrap()
+                .sign(&message)
+                .0
+                .to_vec()
+        };
+        assert_noop!(
+            TemplateModule::update_errand(
+                Origin::signed(alice()),
+                cid.clone(),
+                result.clone(),
+                Vec::new(),
+                false,
+                Some(sign("//Bob"))
+            ),
+            Error::<Test>::InvalidCompletionProof
+        );
+
+        let proof = sign("//Alice");
+        assert_ok!(TemplateModule::update_errand(
+            Origin::signed(alice()),
+            cid.clone(),
+            result,
+            Vec::new(),
+            false,
+            Some(proof.clone())
+        ));
+        assert_eq!(Some((worker, proof)), TemplateModule::completion_proof(&cid));
+    });
+}
+
+#[test]
+fn completion_proofs_of_bought_claims_are_signed_by_the_buyer() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = alice();
+        let buyer: [u8; 32] = sp_core::sr25519::Pair::from_string("//Bob", None)
+            .unwrap()
+            .public()
+            .into();
+        let buyer = AccountId32::from(buyer);
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        Balances::make_free_balance_be(&worker, 100);
+        Balances::make_free_balance_be(&buyer, 100);
+        assert_ok!(TemplateModule::request_delegate(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            AccountId32::from_string(ACCOUNT1).unwrap(),
+            vec![0u8],
+            5
+        ));
+        assert_ok!(TemplateModule::update_delegate_status(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            worker.clone()
+        ));
+        let errand_id = queue_task_of(&worker, &client, &cid, 5, false);
+        assert_ok!(TemplateModule::init_errand(
+            Origin::signed(worker.clone()),
+            client,
+            errand_id.clone(),
+            cid.clone()
+        ));
+        assert_ok!(TemplateModule::list_errand(
+            Origin::signed(worker.clone()),
+            errand_id.clone(),
+            10
+        ));
+        assert_ok!(TemplateModule::buy_errand_listing(
+            Origin::signed(buyer.clone()),
+            errand_id.clone()
+        ));
+
+        let result = b"result".to_vec();
+        let message = TemplateModule::completion_message(
+            &errand_id,
+            &<Test as frame_system::Trait>::Hashing::hash(&result),
+        );
+        let sign = |seed: &str| {
+            sp_core::sr25519::Pair::from_string(seed, None)
+                .unwrap()
+                .sign(&message)
+                .0
+                .to_vec()
+        };
+        let update = |proof: Vec<u8>| {
+            TemplateModule::update_errand(
+                Origin::signed(alice()),
+                cid.clone(),
+                result.clone(),
+                Vec::new(),
+                false,
+                Some(proof),
+            )
+        };
+        // the sender of the client gave up the claim
+        assert_noop!(update(sign("//Alice")), Error::<Test>::InvalidCompletionProof);
+        let proof = sign("//Bob");
+        assert_ok!(update(proof.clone()));
+        assert_eq!(Some((buyer, proof)), TemplateModule::completion_proof(&cid));
+    });
+}
+
+#[test]
+fn ed25519_workers_sign_and_verify_completions() {
+    use sp_core::testing::KeyStore;
+    use sp_core::traits::KeystoreExt;
+
+    let keystore = KeyStore::new();
+    let public = keystore
+        .write()
+        .ed25519_generate_new(crate::KEY_TYPE, Some("//Alice"))
+        .unwrap();
+    let mut t = new_test_ext();
+    t.register_extension(KeystoreExt(keystore));
+
+    t.execute_with(|| {
+        let worker = AccountId32::from(<[u8; 32]>::from(public));
+        let errand_id = vec![3u8, 4u8];
+        let result = b"result".to_vec();
+        let proof = TemplateModule::sign_completion(&worker, &errand_id, &result).unwrap();
+
+        let message =
+            TemplateModule::completion_message(&errand_id, &TemplateModule::result_hash(&result));
+        assert!(TemplateModule::is_signed_by(&worker, &message, &proof));
+        assert!(!TemplateModule::is_signed_by(&alice(), &message, &proof));
+    });
+}
+
+#[test]
+fn unhealthy_kind_does_not_hold_up_other_kinds() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        let worker = alice();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let delegator = AccountId32::from_string(ACCOUNT1).unwrap();
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+
+        System::set_block_number(1);
+        register_worker(&worker);
+        assert_ok!(TemplateModule::request_delegate(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            delegator.clone(),
+            b"localhost:8000".to_vec(),
+            5
+        ));
+        assert_ok!(TemplateModule::update_delegate_status(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            worker.clone()
+        ));
+        Delegations::<Test>::insert(&client, &worker, vec![0xabu8]);
+        save_delegate_info(&format!("{}", delegator), &info).unwrap();
+
+        System::set_block_number(2);
+        let slow_cid = vec![1u8, 1u8];
+        let fast_cid = vec![1u8, 2u8];
+        let slow_id = queue_task_of_kind(&worker, &client, &slow_cid, 9, false, 1);
+        set_extrinsic_index(1);
+        let fast_id = queue_task_of_kind(&worker, &client, &fast_cid, 5, false, 2);
+        let url = |cid: &Vec<u8>, errand_id: &Vec<u8>| {
+            task::task_request_url(
+                &format!("{}", delegator),
+                &info,
+                cid,
+                errand_id,
+                &b"localhost:8000".to_vec(),
+                &vec![0xabu8],
+                DEFAULT_KIND_ACTION_PATH,
+            )
+            .unwrap()
+        };
+        // the higher bid of kind 1 goes first and its service answers garbage
+        offchain_state
+            .write()
+            .expect_request(sp_core::offchain::testing::PendingRequest {
+                method: "POST".into(),
+                uri: url(&slow_cid, &slow_id),
+                body: b"post body".to_vec(),
+                headers: vec![("User-Agent".into(), "tearust".into())],
+                response: Some(b"service unavailable".to_vec()),
+                sent: true,
+                ..Default::default()
+            });
+        expect_post(&offchain_state, url(&fast_cid, &fast_id), "ok");
+
+        let block_number = 2 + ConfirmationDepth::get();
+        run_offchain_at(block_number);
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(1, results.len());
+        assert!(results[0].is_ok());
+        assert_eq!(None, TemplateModule::errand(&slow_cid));
+        assert_eq!(
+            ErrandStatus::Processing,
+            TemplateModule::errand(&fast_cid).unwrap().status
+        );
+
+        assert!(!TemplateModule::is_kind_healthy(1, block_number));
+        assert!(TemplateModule::is_kind_healthy(2, block_number));
+        assert!(TemplateModule::tick_tasks(block_number).is_empty());
+        let retry_at = block_number + KIND_BACKOFF_BLOCKS as u64;
+        assert_eq!(slow_id, TemplateModule::tick_tasks(retry_at)[0].errand_id);
+    });
+}
+
+#[test]
+fn resubmitted_errand_results_are_kept_as_versions() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        complete(&cid, b"first");
+        assert_eq!(Some((1, b"first".to_vec())), TemplateModule::latest_result(&cid));
+
+        assert_ok!(TemplateModule::archive_errand(Origin::signed(client), cid.clone()));
+        System::set_block_number(2);
+        let errand_id = queue_task(&cid, 5, false);
+        init_queued_errand(&cid, &errand_id);
+        complete(&cid, b"second");
+
+        assert_eq!(
+            vec![(1, b"first".to_vec()), (2, b"second".to_vec())],
+            TemplateModule::result_versions(&cid)
+        );
+        assert_eq!(Some((2, b"second".to_vec())), TemplateModule::latest_result(&cid));
+    });
+}
+
+#[test]
+fn private_errand_results_show_no_versions() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        init_errand_with_privacy(&cid, 5, true);
+        complete(&cid, b"secret");
+
+        assert_eq!(1, ResultVersions::<Test>::get(&cid).len());
+        assert!(TemplateModule::public_result_versions(&cid).is_empty());
+        assert_eq!(None, TemplateModule::latest_result(&cid));
+    });
+}
+
+#[test]
+fn errand_id_length_matches_id_mode() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender = AccountId32::from_string(ACCOUNT1).unwrap();
+        assert_eq!(
+            ERRAND_ID_MODE.id_length(),
+            TemplateModule::generate_errand_id(&sender).len()
+        );
+        assert_eq!(
+            ERRAND_ID_MODE.id_length(),
+            TemplateModule::derive_errand_id(&sender, b"salt").len()
+        );
+
+        for mode in vec![ErrandIdMode::Uuid, ErrandIdMode::Wide] {
+            let id = TemplateModule::errand_id_from_seed(b"seed", mode);
+            assert_eq!(mode.id_length(), id.len());
+        }
+        assert_eq!(
+            hex::encode(sp_io::hashing::blake2_256(b"seed")).into_bytes(),
+            TemplateModule::errand_id_from_seed(b"seed", ErrandIdMode::Wide)
+        );
+    });
+}
+
+#[test]
+fn paused_kinds_reject_new_tasks_and_finish_in_flight_ones() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        let sender = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let processing_cid = vec![1u8, 1u8];
+        let queued_cid = vec![1u8, 2u8];
+        let processing_id = queue_task_of_kind(&sender, &client, &processing_cid, 5, false, 1);
+        init_queued_errand(&processing_cid, &processing_id);
+        set_extrinsic_index(1);
+        let queued_id = queue_task_of_kind(&sender, &client, &queued_cid, 5, false, 1);
+
+        assert_noop!(
+            TemplateModule::pause_kind(Origin::signed(sender.clone()), 1),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(TemplateModule::pause_kind(Origin::root(), 1));
+        assert_noop!(
+            TemplateModule::begin_task(
+                Origin::signed(sender.clone()),
+                client.clone(),
+                vec![1u8, 3u8],
+                5,
+                Vec::new(),
+                false,
+                None,
+                1,
+                None
+            ),
+            Error::<Test>::KindPaused.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+        );
+        // other kinds are not affected
+        set_extrinsic_index(2);
+        queue_task_of_kind(&sender, &client, &vec![1u8, 4u8], 5, false, 2);
+
+        complete(&processing_cid, b"result");
+        assert_eq!(
+            ErrandStatus::Done,
+            TemplateModule::errand(&processing_cid).unwrap().status
+        );
+        init_queued_errand(&queued_cid, &queued_id);
+        assert_eq!(
+            ErrandStatus::Processing,
+            TemplateModule::errand(&queued_cid).unwrap().status
+        );
+
+        assert_ok!(TemplateModule::resume_kind(Origin::root(), 1));
+        assert_noop!(
+            TemplateModule::resume_kind(Origin::root(), 1),
+            Error::<Test>::KindNotPaused
+        );
+        set_extrinsic_index(3);
+        queue_task_of_kind(&sender, &client, &vec![1u8, 3u8], 5, false, 1);
+    });
+}
+
+#[test]
+fn restarted_workers_skip_resending_in_flight_tasks() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::{testing, OffchainExt};
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let pool_state = t.pool_state.clone();
+    let worker = alice();
+    let client = AccountId32::from_string(ACCOUNT2).unwrap();
+    let cid = vec![1u8, 1u8];
+
+    let errand_id = t.ext.execute_with(|| {
+        let delegator = AccountId32::from_string(ACCOUNT1).unwrap();
+        System::set_block_number(1);
+        register_worker(&worker);
+        assert_ok!(TemplateModule::request_delegate(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            delegator.clone(),
+            b"localhost:8000".to_vec(),
+            5
+        ));
+        assert_ok!(TemplateModule::update_delegate_status(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            worker.clone()
+        ));
+        Delegations::<Test>::insert(&client, &worker, vec![0xabu8]);
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        save_delegate_info(&format!("{}", delegator), &info).unwrap();
+
+        System::set_block_number(2);
+        let errand_id = queue_task_of(&worker, &client, &cid, 5, false);
+        // the worker marked the task and stopped while it was being sent
+        TemplateModule::set_in_flight(&errand_id, true);
+        errand_id
+    });
+
+    // the restarted node keeps its persistent local storage
+    let (offchain, offchain_state) = testing::TestOffchainExt::new();
+    offchain_state.write().persistent_storage =
+        t.offchain_state.read().persistent_storage.clone();
+    t.ext.register_extension(OffchainExt::new(offchain));
+
+    t.ext.execute_with(|| {
+        // no request is expected, the task is not sent again
+        run_offchain_at(2 + ConfirmationDepth::get());
+        assert!(apply_pool_transactions(&pool_state).iter().all(|r| r.is_ok()));
+        let errand = TemplateModule::errand(&cid).unwrap();
+        assert_eq!(errand_id, errand.errand_id);
+        assert_eq!(ErrandStatus::Processing, errand.status);
+        assert!(!TemplateModule::is_in_flight(&errand_id));
+    });
+}
+
+#[test]
+fn tasks_are_only_queued_for_registered_kinds() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        let sender = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let begin = |cid: Vec<u8>, fee: u32| {
+            TemplateModule::begin_task(
+                Origin::signed(sender.clone()),
+                client.clone(),
+                cid,
+                fee,
+                Vec::new(),
+                false,
+                None,
+                7,
+                None,
+            )
+        };
+
+        assert_noop!(
+            begin(vec![1u8, 1u8], 5),
+            Error::<Test>::UnknownKind.with_weight(BEGIN_TASK_VALIDATION_WEIGHT)
+        );
+        let info = KindInfo {
+            action_path: b"/translate".to_vec(),
+            default_fee: 8,
+            fee_multiplier_percent: 0,
+            http_method: HttpMethod::Put,
+            accept: Vec::new(),
+        };
+        assert_noop!(
+            TemplateModule::register_kind(Origin::signed(sender.clone()), 7, info.clone()),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_noop!(
+            TemplateModule::register_kind(
+                Origin::root(),
+                7,
+                KindInfo {
+                    action_path: b"translate".to_vec(),
+                    ..info.clone()
+                }
+            ),
+            Error::<Test>::InvalidKindInfo
+        );
+        assert_ok!(TemplateModule::register_kind(
+            Origin::root(),
+            7,
+            info.clone()
+        ));
+        assert_eq!(Some(info), TemplateModule::kind_info(7));
+
+        // a zero fee falls back to the default fee of the kind
+        assert_ok!(begin(vec![1u8, 1u8], 0));
+        assert_eq!(5 + 8, Balances::reserved_balance(&client));
+        let task = &Tasks::<Test>::get(1)[0];
+        assert_eq!(7, TemplateModule::task_kind(&task.errand_id));
+    });
+}
+
+#[test]
+fn identical_cids_complete_from_the_result_cache() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+    let pool_state = t.pool_state.clone();
+
+    t.ext.execute_with(|| {
+        let worker = alice();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let delegator = AccountId32::from_string(ACCOUNT1).unwrap();
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+
+        System::set_block_number(1);
+        register_worker(&worker);
+        assert_ok!(TemplateModule::request_delegate(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            delegator.clone(),
+            b"localhost:8000".to_vec(),
+            5
+        ));
+        assert_ok!(TemplateModule::update_delegate_status(
+            Origin::signed(worker.clone()),
+            client.clone(),
+            worker.clone()
+        ));
+        Delegations::<Test>::insert(&client, &worker, vec![0xabu8]);
+        save_delegate_info(&format!("{}", delegator), &info).unwrap();
+
+        System::set_block_number(2);
+        let cid = vec![1u8, 1u8];
+        let first_id = queue_task_of_kind(&worker, &client, &cid, 5, false, DEFAULT_KIND);
+        let url = task::task_request_url(
+            &format!("{}", delegator),
+            &info,
+            &cid,
+            &first_id,
+            &b"localhost:8000".to_vec(),
+            &vec![0xabu8],
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        expect_post(&offchain_state, url, "ok");
+        let block_number = 2 + ConfirmationDepth::get();
+        run_offchain_at(block_number);
+        assert!(apply_pool_transactions(&pool_state).iter().all(|r| r.is_ok()));
+
+        let results = vec![(cid.clone(), parse_result_info(r#","status":"success""#))];
+        StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes()).set(&results);
+        TemplateModule::update_errand_task_results(block_number);
+        assert!(apply_pool_transactions(&pool_state).iter().all(|r| r.is_ok()));
+        assert_eq!(ErrandStatus::Done, TemplateModule::errand(&cid).unwrap().status);
+        assert_ok!(TemplateModule::archive_errand(
+            Origin::signed(client.clone()),
+            cid.clone()
+        ));
+
+        // no request is expected, the service is not hit for the second errand
+        System::set_block_number(block_number + 1);
+        let second_id = queue_task_of_kind(&worker, &client, &cid, 5, false, DEFAULT_KIND);
+        run_offchain_at(block_number + 1 + ConfirmationDepth::get());
+        let results = apply_pool_transactions(&pool_state);
+        assert_eq!(2, results.len());
+        assert!(results.iter().all(|r| r.is_ok()));
+        let errand = TemplateModule::errand(&cid).unwrap();
+        assert_eq!(second_id, errand.errand_id);
+        assert_eq!(ErrandStatus::Done, errand.status);
+        assert_eq!(b"result".to_vec(), errand.result);
+    });
+}
+
+#[test]
+fn begin_task_appends_to_the_tasks_of_the_block() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(5);
+        let first_id = queue_task(&vec![1u8, 1u8], 5, false);
+        set_extrinsic_index(1);
+        let second_id = queue_task(&vec![1u8, 2u8], 5, false);
+
+        let ids: Vec<Vec<u8>> = Tasks::<Test>::get(1)
+            .into_iter()
+            .map(|task| task.errand_id)
+            .collect();
+        assert_eq!(vec![first_id, second_id], ids);
+
+        // a rejected task leaves the queued ones in place
+        assert_noop!(
+            TemplateModule::begin_task(
+                Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+                AccountId32::from_string(ACCOUNT2).unwrap(),
+                vec![1u8, 1u8],
+                5,
+                Vec::new(),
+                false,
+                None,
+                DEFAULT_KIND,
+                None
+            ),
+            Error::<Test>::ErrandAlreadyExecuted
+        );
+        assert_eq!(2, Tasks::<Test>::get(1).len());
+    });
+}
+
+#[test]
+fn reserved_fees_are_capped_per_account() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_client(100);
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        queue_task(&vec![1u8, 1u8], 300, false);
+        assert_eq!(AccountReserveCap::get(), TemplateModule::client_reserved(&client) + 100);
+
+        set_extrinsic_index(1);
+        queue_task(&vec![1u8, 2u8], 100, false);
+        assert_eq!(AccountReserveCap::get(), TemplateModule::client_reserved(&client));
+
+        set_extrinsic_index(2);
+        assert_noop!(
+            TemplateModule::begin_task(
+                Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+                client.clone(),
+                vec![1u8, 3u8],
+                1,
+                Vec::new(),
+                false,
+                None,
+                DEFAULT_KIND,
+                None
+            ),
+            Error::<Test>::ReserveCapExceeded
+        );
+        assert_eq!(Balances::reserved_balance(&client), AccountReserveCap::get());
+    });
+}
+
+#[test]
+fn errands_are_indexed_by_their_extrinsic() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(3);
+        register_client(5);
+        set_extrinsic_index(1);
+        let first_id = queue_task(&vec![1u8, 1u8], 5, false);
+        set_extrinsic_index(4);
+        let second_id = queue_task(&vec![1u8, 2u8], 5, false);
+
+        assert_eq!(Some(first_id), TemplateModule::errand_by_extrinsic(3, 1));
+        assert_eq!(Some(second_id), TemplateModule::errand_by_extrinsic(3, 4));
+        assert_eq!(None, TemplateModule::errand_by_extrinsic(3, 2));
+        assert_eq!(None, TemplateModule::errand_by_extrinsic(2, 1));
+    });
+}
+
+#[test]
+fn only_the_assigned_worker_claims_and_completes_an_errand() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let worker = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(10);
+        let begin_assigned = |cid: &Vec<u8>, assigned: &AccountId32| {
+            assert_ok!(TemplateModule::begin_task(
+                Origin::signed(worker.clone()),
+                client.clone(),
+                cid.clone(),
+                5,
+                Vec::new(),
+                false,
+                None,
+                DEFAULT_KIND,
+                Some(assigned.clone())
+            ));
+            Tasks::<Test>::get(1)
+                .into_iter()
+                .find(|task| &task.description_cid == cid)
+                .unwrap()
+                .errand_id
+        };
+
+        let others = vec![1u8, 1u8];
+        let errand_id = begin_assigned(&others, &alice());
+        assert_eq!(Some(alice()), TemplateModule::assigned_worker(&errand_id));
+        assert_noop!(
+            TemplateModule::init_errand(
+                Origin::signed(worker.clone()),
+                client.clone(),
+                errand_id,
+                others.clone()
+            ),
+            Error::<Test>::NotAssignedWorker
+        );
+
+        set_extrinsic_index(1);
+        let own = vec![1u8, 2u8];
+        let errand_id = begin_assigned(&own, &worker);
+        init_queued_errand(&own, &errand_id);
+        // the errand updater isn't the assigned worker
+        assert_noop!(
+            TemplateModule::update_errand(
+                Origin::signed(alice()),
+                own.clone(),
+                b"result".to_vec(),
+                Vec::new(),
+                false,
+                None
+            ),
+            Error::<Test>::NotAssignedWorker
+        );
+        register_worker(&worker);
+        assert_ok!(TemplateModule::require_confirmations(
+            Origin::signed(client.clone()),
+            own.clone(),
+            2
+        ));
+        assert_ok!(TemplateModule::update_errand(
+            Origin::signed(worker.clone()),
+            own.clone(),
+            b"result".to_vec(),
+            Vec::new(),
+            false,
+            None
+        ));
+        assert_eq!(1, TemplateModule::result_submissions(&own).len());
+    });
+}
+
+// registers alice and ACCOUNT1 as workers and requires both to confirm errand `cid`
+fn init_quorum_errand(cid: &Vec<u8>) {
+    init_processing_errand(cid, 5);
+    register_worker(&alice());
+    register_worker(&AccountId32::from_string(ACCOUNT1).unwrap());
+    assert_noop!(
+        TemplateModule::require_confirmations(
+            Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+            cid.clone(),
+            2
+        ),
+        Error::<Test>::NotErrandOwner
+    );
+    assert_noop!(
+        TemplateModule::require_confirmations(
+            Origin::signed(AccountId32::from_string(ACCOUNT2).unwrap()),
+            cid.clone(),
+            MAX_REQUIRED_CONFIRMATIONS + 1
+        ),
+        Error::<Test>::InvalidConfirmations
+    );
+    assert_ok!(TemplateModule::require_confirmations(
+        Origin::signed(AccountId32::from_string(ACCOUNT2).unwrap()),
+        cid.clone(),
+        2
+    ));
+}
+
+fn submit_result(
+    worker: &AccountId32,
+    cid: &Vec<u8>,
+    result: &[u8],
+) -> dispatch::DispatchResult {
+    TemplateModule::update_errand(
+        Origin::signed(worker.clone()),
+        cid.clone(),
+        result.to_vec(),
+        Vec::new(),
+        false,
+        None,
+    )
+}
+
+#[test]
+fn quorum_errands_are_done_once_enough_workers_agree() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let second = AccountId32::from_string(ACCOUNT1).unwrap();
+        init_quorum_errand(&cid);
+
+        assert_ok!(submit_result(&alice(), &cid, b"result"));
+        assert_eq!(ErrandStatus::Processing, TemplateModule::errand(&cid).unwrap().status);
+        assert_eq!(1, TemplateModule::result_submissions(&cid).len());
+        assert_noop!(
+            submit_result(&alice(), &cid, b"result"),
+            Error::<Test>::ResultAlreadySubmitted
+        );
+        assert_noop!(
+            submit_result(&AccountId32::new([9u8; 32]), &cid, b"result"),
+            Error::<Test>::NotRegisteredWorker
+        );
+
+        assert_ok!(submit_result(&second, &cid, b"result"));
+        let errand = TemplateModule::errand(&cid).unwrap();
+        assert_eq!(ErrandStatus::Done, errand.status);
+        assert_eq!(b"result".to_vec(), errand.result);
+        assert!(TemplateModule::result_submissions(&cid).is_empty());
+        assert!(!TemplateModule::processing_errands().contains(&cid));
+        // only the delegate fee stays reserved
+        assert_eq!(5, Balances::reserved_balance(&client));
+    });
+}
+
+#[test]
+fn conflicting_quorum_results_dispute_the_errand() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        init_quorum_errand(&cid);
+        let free = Balances::free_balance(&client);
+
+        assert_ok!(submit_result(&alice(), &cid, b"result"));
+        assert_ok!(submit_result(
+            &AccountId32::from_string(ACCOUNT1).unwrap(),
+            &cid,
+            b"other"
+        ));
+
+        let errand = TemplateModule::errand(&cid).unwrap();
+        assert_eq!(ErrandStatus::Disputed, errand.status);
+        assert!(errand.result.is_empty());
+        assert_eq!(vec![4u8], TemplateModule::errand_statuses(vec![cid.clone()]));
+        assert!(TemplateModule::result_submissions(&cid).is_empty());
+        assert!(!TemplateModule::processing_errands().contains(&cid));
+        // the task fee is refunded
+        assert_eq!(free + 5, Balances::free_balance(&client));
+        assert_noop!(
+            submit_result(&alice(), &cid, b"result"),
+            Error::<Test>::ErrandAlreadyExecuted
+        );
+    });
+}
+
+#[test]
+fn recovery_account_claims_finished_errands_after_inactivity() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let employer = AccountId32::from_string(ACCOUNT2).unwrap();
+        let recovery = AccountId32::new([9u8; 32]);
+        let done_cid = vec![1u8, 1u8];
+        let processing_cid = vec![1u8, 2u8];
+        let done_id = init_processing_errand(&done_cid, 5);
+        complete(&done_cid, b"result");
+        set_extrinsic_index(1);
+        let errand_id = queue_task(&processing_cid, 5, false);
+        init_queued_errand(&processing_cid, &errand_id);
+        assert_ok!(TemplateModule::set_recovery_account(
+            Origin::signed(employer.clone()),
+            Some(recovery.clone())
+        ));
+
+        assert_noop!(
+            TemplateModule::recover_errands(
+                Origin::signed(AccountId32::from_string(ACCOUNT1).unwrap()),
+                employer.clone()
+            ),
+            Error::<Test>::NotRecoveryAccount
+        );
+        System::set_block_number(RecoveryDelay::get());
+        assert_noop!(
+            TemplateModule::recover_errands(Origin::signed(recovery.clone()), employer.clone()),
+            Error::<Test>::AccountStillActive
+        );
+
+        System::set_block_number(1 + RecoveryDelay::get());
+        assert_ok!(TemplateModule::recover_errands(
+            Origin::signed(recovery.clone()),
+            employer.clone()
+        ));
+        assert_eq!(vec![done_cid.clone()], TemplateModule::account_errands(&recovery));
+        assert_eq!(vec![processing_cid.clone()], TemplateModule::account_errands(&employer));
+        assert_eq!(
+            vec![(done_id, b"result".to_vec())],
+            TemplateModule::completed_errands_of(recovery.clone(), 10)
+        );
+        assert_ok!(TemplateModule::archive_errand(Origin::signed(recovery), done_cid));
+    });
+}
+
+#[test]
+fn account_status_summary_counts_errands_by_status() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        register_client(5);
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cids: Vec<Vec<u8>> = (0..6u8).map(|i| vec![1u8, i]).collect();
+        for (i, cid) in cids.iter().enumerate() {
+            set_extrinsic_index(i as u32);
+            let errand_id = queue_task(cid, 5, false);
+            init_queued_errand(cid, &errand_id);
+        }
+        complete(&cids[1], b"result");
+        complete(&cids[2], b"result");
+        let fail = |cid: &Vec<u8>, outcome: Outcome| {
+            assert_ok!(TemplateModule::fail_errand(
+                Origin::signed(alice()),
+                cid.clone(),
+                Vec::new(),
+                outcome
+            ));
+        };
+        fail(&cids[3], Outcome::Error);
+        fail(&cids[4], Outcome::Timeout);
+        fail(&cids[5], Outcome::Timeout);
+
+        assert_eq!((1, 2, 1, 2), TemplateModule::account_status_summary(client));
+        assert_eq!(
+            (0, 0, 0, 0),
+            TemplateModule::account_status_summary(AccountId32::new([9u8; 32]))
+        );
+    });
+}
+
+#[test]
+fn http_responses_are_capped_whatever_the_caller_allows() {
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+
+    t.ext.execute_with(|| {
+        StorageValueRef::persistent(http::LOCAL_STORAGE_MAX_HTTP_RESPONSE_BYTES_KEY.as_bytes())
+            .set(&1000u32);
+        let small = "http://localhost:8000/small";
+        expect_post(&offchain_state, small.into(), &"s".repeat(500));
+        assert_eq!(vec![b's'; 500], http::http_post(small).unwrap());
+
+        // the caller allows any length
+        let huge = "http://localhost:8000/huge";
+        expect_post(&offchain_state, huge.into(), &"h".repeat(5000));
+        let err = http::http_post(huge).unwrap_err();
+        assert_eq!(
+            "response body is longer than 1000 bytes",
+            format!("{}", err)
+        );
+    });
+}
+
+#[test]
+fn chunked_result_responses_are_read_up_to_their_limit() {
+    use sp_core::offchain::testing::PendingRequest;
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(10);
+        let response = |result_length: usize| {
+            format!(
+                r#"{{"data":"{{\"completed\":true,\"result_cid\":\"{}\",\"failed_count\":0}}"}}"#,
+                "r".repeat(result_length)
+            )
+            .into_bytes()
+        };
+        let expect_query = |errand_id: &Vec<u8>, response: Vec<u8>| {
+            offchain_state.write().expect_request(PendingRequest {
+                method: "POST".into(),
+                uri: format!(
+                    "http://localhost:8000/api/query_errand_execution_result_by_uuid/{}",
+                    hex::encode(errand_id)
+                ),
+                body: b"post body".to_vec(),
+                headers: vec![("User-Agent".into(), "tearust".into())],
+                response: Some(response),
+                sent: true,
+                ..Default::default()
+            });
+        };
+
+        // bodies are read in chunks of 4096 bytes
+        let long = response(5000);
+        assert!(long.len() > 4096 && long.len() <= task::MAX_RESULT_RESPONSE_LENGTH);
+        expect_query(&b"long".to_vec(), long);
+        assert!(task::fetch_single_task_result(
+            &b"long".to_vec(),
+            &vec![1u8, 1u8],
+            &b"localhost:8000".to_vec(),
+            &[]
+        ));
+        let results = StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes())
+            .get::<Vec<(Cid, ErrandResultInfo)>>()
+            .unwrap()
+            .unwrap();
+        assert_eq!(1, results.len());
+        assert_eq!(vec![b'r'; 5000], results[0].1.result_cid);
+
+        let oversized = response(task::MAX_RESULT_RESPONSE_LENGTH);
+        expect_query(&b"oversized".to_vec(), oversized);
+        assert!(!task::fetch_single_task_result(
+            &b"oversized".to_vec(),
+            &vec![1u8, 2u8],
+            &b"localhost:8000".to_vec(),
+            &[]
+        ));
+        let results = StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes())
+            .get::<Vec<(Cid, ErrandResultInfo)>>()
+            .unwrap()
+            .unwrap();
+        assert_eq!(1, results.len());
+    });
+}
+
+#[test]
+fn fee_breakdown_sums_to_the_reserved_fee() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let sender = AccountId32::from_string(ACCOUNT1).unwrap();
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(100);
+        register_kind(1, 4);
+        let scaled = KindInfo {
+            fee_multiplier_percent: 50,
+            ..TemplateModule::registered_kind(1).unwrap()
+        };
+        assert_noop!(
+            TemplateModule::register_kind(
+                Origin::root(),
+                2,
+                KindInfo {
+                    fee_multiplier_percent: MAX_FEE_MULTIPLIER_PERCENT + 1,
+                    ..scaled.clone()
+                }
+            ),
+            Error::<Test>::InvalidKindInfo
+        );
+        assert_ok!(TemplateModule::register_kind(Origin::root(), 2, scaled.clone()));
+        let breakdown = |errand_id: &Vec<u8>| {
+            System::events()
+                .into_iter()
+                .find_map(|r| match r.event {
+                    TestEvent::abc(RawEvent::FeeBreakdown(id, base, surcharge, kind))
+                        if &id == errand_id =>
+                    {
+                        Some((base, surcharge, kind))
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        for (i, (fee, kind_id)) in [(10u32, 1), (0, 1), (3, 1), (10, 2)].iter().enumerate() {
+            let reserved = Balances::reserved_balance(&client);
+            let cid = vec![1u8, i as u8];
+            let errand_id = queue_task_of_kind(&sender, &client, &cid, *fee, false, *kind_id);
+            let (base, surcharge, kind) = breakdown(&errand_id);
+            assert_eq!(
+                (base + surcharge + kind) as u64,
+                Balances::reserved_balance(&client) - reserved
+            );
+        }
+
+        // bids above the default fee of the kind are priority surcharges
+        let kind_info = TemplateModule::registered_kind(1).unwrap();
+        assert_eq!((4, 6, 0), TemplateModule::fee_breakdown(10, &kind_info));
+        assert_eq!((4, 0, 0), TemplateModule::fee_breakdown(0, &kind_info));
+        assert_eq!((3, 0, 0), TemplateModule::fee_breakdown(3, &kind_info));
+        // kinds scale the whole fee
+        assert_eq!((4, 6, 5), TemplateModule::fee_breakdown(10, &scaled));
+        assert_eq!((4, 0, 2), TemplateModule::fee_breakdown(0, &scaled));
+    });
+}
+
+#[test]
+fn expired_claims_retry_at_jittered_heights() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_max_retry_jitter(1000);
+        let first_cid = vec![1u8, 1u8];
+        let second_cid = vec![1u8, 2u8];
+        let first_id = init_processing_errand(&first_cid, 5);
+        let second_id = init_processing_errand(&second_cid, 5);
+
+        System::set_block_number(21);
+        assert_ok!(TemplateModule::expire_claim(Origin::signed(alice()), first_cid));
+        assert_ok!(TemplateModule::expire_claim(Origin::signed(alice()), second_cid));
+
+        let first_at = TemplateModule::task_location(&first_id).unwrap();
+        let second_at = TemplateModule::task_location(&second_id).unwrap();
+        assert_ne!(first_at, second_at);
+        for (errand_id, retry_at) in &[(&first_id, first_at), (&second_id, second_at)] {
+            let jitter = TemplateModule::retry_jitter(errand_id, 21, 1000);
+            assert_eq!(21 + jitter as u64, *retry_at);
+            assert_eq!(**errand_id, Tasks::<Test>::get(retry_at)[0].errand_id);
+        }
+    });
+}
+
+#[test]
+fn employers_reclaim_the_reserves_of_stuck_errands() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        let cid = vec![1u8, 1u8];
+        init_processing_errand(&cid, 5);
+        assert_eq!(Some(21), TemplateModule::claim_expiry(&cid));
+        let reserved = Balances::reserved_balance(&client);
+
+        System::set_block_number(20);
+        assert_noop!(
+            TemplateModule::reclaim_stuck_reserves(Origin::signed(client.clone())),
+            Error::<Test>::NoStuckErrands
+        );
+
+        // nobody expired the claim after its deadline
+        System::set_block_number(21);
+        assert_ok!(TemplateModule::reclaim_stuck_reserves(Origin::signed(client.clone())));
+        assert_eq!(reserved - 5, Balances::reserved_balance(&client));
+        assert_eq!(ErrandStatus::Failed, TemplateModule::errand(&cid).unwrap().status);
+        assert_eq!((0, 0, 0, 1), TemplateModule::account_status_summary(client.clone()));
+        assert_eq!(None, TemplateModule::claim_expiry(&cid));
+        assert_eq!(
+            System::events().last().unwrap().event,
+            TestEvent::abc(RawEvent::StuckReservesReclaimed(client.clone(), 5))
+        );
+
+        assert_noop!(
+            TemplateModule::reclaim_stuck_reserves(Origin::signed(client)),
+            Error::<Test>::NoStuckErrands
+        );
+    });
+}
+
+#[test]
+fn reported_latency_updates_the_rolling_average() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        register_worker(&alice());
+        assert_eq!((0, 0), TemplateModule::service_latency());
+
+        assert_ok!(TemplateModule::report_latency(Origin::signed(alice()), 100, 10));
+        assert_eq!((100, 10), TemplateModule::service_latency());
+        assert_ok!(TemplateModule::report_latency(Origin::signed(alice()), 200, 30));
+        assert_eq!((175, 40), TemplateModule::service_latency());
+
+        // older samples lose weight once the window is full
+        assert_ok!(TemplateModule::report_latency(Origin::signed(alice()), 175, 960));
+        assert_ok!(TemplateModule::report_latency(Origin::signed(alice()), 1175, 1000));
+        assert_eq!((675, LATENCY_WINDOW_SAMPLES), TemplateModule::service_latency());
+
+        assert_noop!(
+            TemplateModule::report_latency(Origin::signed(alice()), 100, 0),
+            Error::<Test>::NoLatencySamples
+        );
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        assert_noop!(
+            TemplateModule::report_latency(Origin::signed(client), 100, 10),
+            Error::<Test>::NotRegisteredWorker
+        );
+    });
+}
+
+#[test]
+fn tasks_accept_the_result_format_of_their_kind() {
+    use crate::delegate::{save_delegate_info, DelegateInfo};
+    use sp_core::offchain::testing::PendingRequest;
+
+    let mut t = new_offchain_test_ext(&["//Alice"]);
+    let offchain_state = t.offchain_state.clone();
+
+    t.ext.execute_with(|| {
+        System::set_block_number(10);
+        let plain = KindInfo {
+            action_path: DEFAULT_KIND_ACTION_PATH.as_bytes().to_vec(),
+            default_fee: 0,
+            fee_multiplier_percent: 0,
+            http_method: HttpMethod::Post,
+            accept: b"text/plain".to_vec(),
+        };
+        assert_noop!(
+            TemplateModule::register_kind(
+                Origin::root(),
+                1,
+                KindInfo {
+                    accept: vec![b'a'; MAX_CONTENT_TYPE_LENGTH + 1],
+                    ..plain.clone()
+                }
+            ),
+            Error::<Test>::InvalidKindInfo
+        );
+        assert_ok!(TemplateModule::register_kind(Origin::root(), 1, plain.clone()));
+
+        let account = AccountId32::from_string(ACCOUNT2).unwrap();
+        let info = DelegateInfo {
+            delegator_tea_id: vec![1u8],
+            delegator_ephemeral_id: vec![2u8],
+            sig: vec![3u8],
+            key3_rsa_pub_key: "key".into(),
+        };
+        save_delegate_info(&format!("{}", account), &info).unwrap();
+        let errand_id = b"errand".to_vec();
+        let url = task::task_request_url(
+            &format!("{}", account),
+            &info,
+            &b"cid".to_vec(),
+            &errand_id,
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            DEFAULT_KIND_ACTION_PATH,
+        )
+        .unwrap();
+        offchain_state.write().expect_request(PendingRequest {
+            method: "POST".into(),
+            uri: url,
+            body: b"post body".to_vec(),
+            headers: vec![
+                ("User-Agent".into(), "tearust".into()),
+                ("Accept".into(), "text/plain".into()),
+            ],
+            response: Some(br#"{"data":"ok"}"#.to_vec()),
+            sent: true,
+            ..Default::default()
+        });
+        assert!(task::send_task_to_tea_network(
+            &account,
+            &b"cid".to_vec(),
+            &errand_id,
+            &b"localhost:8000".to_vec(),
+            &Vec::new(),
+            &plain,
+        ));
+
+        // a result without a content type is stored in the format the task asked for
+        offchain_state.write().expect_request(PendingRequest {
+            method: "POST".into(),
+            uri: format!(
+                "http://localhost:8000/api/query_errand_execution_result_by_uuid/{}",
+                hex::encode(&errand_id)
+            ),
+            body: b"post body".to_vec(),
+            headers: vec![("User-Agent".into(), "tearust".into())],
+            response: Some(
+                br#"{"data":"{\"completed\":true,\"result_cid\":\"result\",\"failed_count\":0}"}"#
+                    .to_vec(),
+            ),
+            sent: true,
+            ..Default::default()
+        });
+        assert!(task::fetch_single_task_result(
+            &errand_id,
+            &b"cid".to_vec(),
+            &b"localhost:8000".to_vec(),
+            &plain.accept
+        ));
+        let results = StorageValueRef::persistent(LOCAL_STORAGE_TASKS_RESULTS_KEY.as_bytes())
+            .get::<Vec<(Cid, ErrandResultInfo)>>()
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"text/plain".to_vec(), results[0].1.content_type);
+    });
+}
+
+#[test]
+fn fees_of_errands_queued_before_errand_fees_are_migrated() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let cid = vec![1u8, 1u8];
+        let processing_id = init_processing_errand(&cid, 10);
+        let queued_id = queue_task(&vec![1u8, 2u8], 2, false);
+        ErrandFees::remove(&processing_id);
+        ErrandFees::remove(&queued_id);
+        StorageVersion::put(Releases::V3_0_0);
+
+        migration::record_errand_fees::<Test>();
+        // processing errands only had the last task fee of their client
+        assert_eq!(2, TemplateModule::errand_fee(&processing_id));
+        assert_eq!(2, TemplateModule::errand_fee(&queued_id));
+        assert_eq!(Releases::V4_0_0, StorageVersion::get());
+    });
+}
+
+#[test]
+fn fees_are_reserved_on_submit() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+        let reserved = Balances::reserved_balance(&client);
+        let cid = vec![1u8, 1u8];
+        let errand_id = queue_task(&cid, 5, false);
+        assert_eq!(reserved + 5, Balances::reserved_balance(&client));
+        assert_eq!(None, TemplateModule::unreserved_task_fee(&errand_id));
+
+        init_queued_errand(&cid, &errand_id);
+        assert_eq!(reserved + 5, Balances::reserved_balance(&client));
+    });
+}
+
+#[test]
+fn fees_are_reserved_on_dispatch() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_reserve_mode(ReserveMode::OnDispatch);
+        let client = AccountId32::from_string(ACCOUNT2).unwrap();
+        register_client(5);
+        let reserved = Balances::reserved_balance(&client);
+        let cids: Vec<Vec<u8>> = (1u8..=3).map(|i| vec![1u8, i]).collect();
+        let ids: Vec<Vec<u8>> = cids.iter().map(|cid| queue_task(cid, 5, false)).collect();
+        assert_eq!(reserved, Balances::reserved_balance(&client));
+        assert_eq!(Some(5), TemplateModule::unreserved_task_fee(&ids[0]));
+
+        init_queued_errand(&cids[0], &ids[0]);
+        assert_eq!(reserved + 5, Balances::reserved_balance(&client));
+        assert_eq!(None, TemplateModule::unreserved_task_fee(&ids[0]));
+
+        // cancelling a task not dispatched yet has nothing to refund
+        assert_ok!(TemplateModule::cancel_task_by_id(
+            Origin::signed(client.clone()),
+            ids[1].clone()
+        ));
+        assert_eq!(reserved + 5, Balances::reserved_balance(&client));
+        assert_eq!(None, TemplateModule::unreserved_task_fee(&ids[1]));
+
+        // a client unable to pay by dispatch loses its task instead of failing the init
+        Balances::make_free_balance_be(&client, 3);
+        init_queued_errand(&cids[2], &ids[2]);
+        assert_eq!(None, TemplateModule::errand(&cids[2]));
+        assert_eq!(None, TemplateModule::task_location(&ids[2]));
+        assert_eq!(None, TemplateModule::unreserved_task_fee(&ids[2]));
+        assert!(!Tasks::<Test>::get(1).iter().any(|task| task.errand_id == ids[2]));
+        assert_eq!(
+            System::events().last().unwrap().event,
+            TestEvent::abc(RawEvent::TaskDroppedUnfunded(client.clone(), ids[2].clone()))
+        );
+        assert_eq!(reserved + 5, Balances::reserved_balance(&client));
+    });
+}
+
+#[test]
+fn accept_formats_survive_upgrades_of_new_chains() {
+    use frame_support::traits::OnRuntimeUpgrade;
+
+    new_test_ext().execute_with(|| {
+        let info = KindInfo {
+            action_path: DEFAULT_KIND_ACTION_PATH.as_bytes().to_vec(),
+            default_fee: 0,
+            fee_multiplier_percent: 0,
+            http_method: HttpMethod::Post,
+            accept: b"application/json".to_vec(),
+        };
+        assert_ok!(TemplateModule::register_kind(Origin::root(), 1, info.clone()));
+
+        TemplateModule::on_runtime_upgrade();
+        assert_eq!(Some(info), TemplateModule::kind_info(1));
+    });
+}
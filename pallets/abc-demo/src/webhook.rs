@@ -0,0 +1,44 @@
+use crate::http::{canonical_json, http_post_notification};
+use crate::Cid;
+use frame_support::debug;
+
+/// Posts the completion of errand `description_cid` to the employer webhook `url`.
+pub fn notify_completion(url: &[u8], description_cid: &Cid) -> bool {
+    notify(url, &completion_body(description_cid))
+}
+
+/// Warns the employer webhook `url` that the claim of errand `description_cid` expires at
+/// height `expires_at`.
+pub fn notify_expiry(url: &[u8], description_cid: &Cid, expires_at: u64) -> bool {
+    notify(url, &expiry_body(description_cid, expires_at))
+}
+
+fn notify(url: &[u8], body: &[u8]) -> bool {
+    match post_json(url, body) {
+        Ok(_) => true,
+        Err(e) => {
+            debug::error!("notify webhook got error: {}", e);
+            false
+        }
+    }
+}
+
+fn post_json(url: &[u8], body: &[u8]) -> anyhow::Result<()> {
+    let url = String::from_utf8(url.to_vec())?;
+    http_post_notification(&url, &[("Content-Type", "application/json")], body)
+}
+
+pub fn completion_body(description_cid: &Cid) -> Vec<u8> {
+    let cid = hex::encode(description_cid);
+    canonical_json(&[("description_cid", cid.as_str()), ("status", "done")])
+}
+
+pub fn expiry_body(description_cid: &Cid, expires_at: u64) -> Vec<u8> {
+    let cid = hex::encode(description_cid);
+    let expires_at = expires_at.to_string();
+    canonical_json(&[
+        ("description_cid", cid.as_str()),
+        ("expires_at", expires_at.as_str()),
+        ("status", "expiring"),
+    ])
+}
@@ -9,8 +9,8 @@ include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 use sp_std::prelude::*;
 use sp_core::{Encode, crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::{
-	ApplyExtrinsicResult, generic, create_runtime_str, impl_opaque_keys, MultiSignature,
-	transaction_validity::{TransactionValidity, TransactionSource},
+	ApplyExtrinsicResult, generic, create_runtime_str, impl_opaque_keys, ModuleId, MultiSignature,
+	transaction_validity::{TransactionPriority, TransactionValidity, TransactionSource},
 };
 use sp_runtime::traits::{
 	BlakeTwo256, Block as BlockT, IdentityLookup, Verify, IdentifyAccount, NumberFor, Saturating,
@@ -261,11 +261,59 @@ impl pallet_sudo::Trait for Runtime {
 	type Call = Call;
 }
 
+parameter_types! {
+	/// Poll errand results about every minute.
+	pub const PollInterval: BlockNumber = 10;
+	/// Keep inline errand results for a week after completion.
+	pub const RetentionBlocks: BlockNumber = 7 * DAYS;
+	pub const MaxClaimsPerWorker: u32 = 16;
+	pub const ClaimTimeout: BlockNumber = HOURS;
+	pub const MaxDeadline: BlockNumber = DAYS;
+	pub const MaxErrandsPerAccount: u32 = 1024;
+	pub const InlineResultThreshold: u32 = 256;
+	pub const ConfirmationDepth: BlockNumber = 2;
+	pub const MaxTasksPerBlock: u32 = 256;
+	pub const AccountReserveCap: Balance = 1_000_000_000_000;
+	pub const RecoveryDelay: BlockNumber = 180 * DAYS;
+	pub const BatchInitEvents: bool = false;
+	pub const AbcUnsignedPriority: TransactionPriority = TransactionPriority::max_value() / 2;
+	pub const ExpiryWarningBlocks: BlockNumber = 10 * MINUTES;
+	pub const AbcModuleId: ModuleId = ModuleId(*b"abc/demo");
+	pub const ResultHashAlgo: pallet_abc::HashAlgo = pallet_abc::HashAlgo::Blake2_256;
+	/// Spread task requests of validators over the first two seconds of a block.
+	pub const MaxSendJitter: u64 = 2000;
+	/// Spread the retries of tasks whose claims expired together over a minute.
+	pub const MaxRetryJitter: u32 = MINUTES;
+	pub const AbcReserveMode: pallet_abc::ReserveMode = pallet_abc::ReserveMode::OnSubmit;
+}
+
 impl pallet_abc::Trait for Runtime {
 	type Currency = Balances;
 	type Event = Event;
 	type AuthorityId = pallet_abc::crypto::AuthId;
 	type Call = Call;
+	type PollInterval = PollInterval;
+	type Randomness = RandomnessCollectiveFlip;
+	type RetentionBlocks = RetentionBlocks;
+	type MaxClaimsPerWorker = MaxClaimsPerWorker;
+	type ClaimTimeout = ClaimTimeout;
+	type MaxDeadline = MaxDeadline;
+	type MaxErrandsPerAccount = MaxErrandsPerAccount;
+	type InlineResultThreshold = InlineResultThreshold;
+	type ConfirmationDepth = ConfirmationDepth;
+	type OnErrandCompleted = ();
+	type MaxTasksPerBlock = MaxTasksPerBlock;
+	type AccountReserveCap = AccountReserveCap;
+	type RecoveryDelay = RecoveryDelay;
+	type ResultAcceptance = ();
+	type BatchInitEvents = BatchInitEvents;
+	type UnsignedPriority = AbcUnsignedPriority;
+	type ExpiryWarningBlocks = ExpiryWarningBlocks;
+	type ModuleId = AbcModuleId;
+	type ResultHashAlgo = ResultHashAlgo;
+	type MaxSendJitter = MaxSendJitter;
+	type MaxRetryJitter = MaxRetryJitter;
+	type ReserveMode = AbcReserveMode;
 }
 
 // ---------------------- Recipe Pallet Configurations ----------------------
@@ -349,7 +397,7 @@ construct_runtime!(
 		TransactionPayment: pallet_transaction_payment::{Module, Storage},
 		Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
 		// Include the custom logic from the template pallet in the runtime.
-		Abc: pallet_abc::{Module, Call, Storage, Event<T>},
+		Abc: pallet_abc::{Module, Call, Storage, Config, Event<T>, ValidateUnsigned},
 	}
 );
 
@@ -510,6 +558,84 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_abc_runtime_api::AbcApi<Block, AccountId, BlockNumber> for Runtime {
+		fn errands_by_cid(cid: Vec<u8>) -> Vec<Vec<u8>> {
+			Abc::errands_by_cid(cid).into_inner()
+		}
+
+		fn completed_errands_of(account: AccountId, limit: u32) -> Vec<(Vec<u8>, Vec<u8>)> {
+			Abc::completed_errands_of(account, limit)
+		}
+
+		fn errands_by_label(label: Vec<u8>) -> Vec<Vec<u8>> {
+			Abc::errands_by_label(label)
+		}
+
+		fn errand_statuses(cids: Vec<Vec<u8>>) -> Vec<u8> {
+			Abc::errand_statuses(cids)
+		}
+
+		fn offchain_config() -> pallet_abc_runtime_api::OffchainConfig<BlockNumber> {
+			Abc::offchain_config()
+		}
+
+		fn errand_comments(cid: Vec<u8>) -> Vec<(AccountId, Vec<u8>, BlockNumber)> {
+			Abc::errand_comments(cid)
+		}
+
+		fn errands_created_between(
+			from: BlockNumber,
+			to: BlockNumber,
+			limit: u32,
+		) -> Vec<(Vec<u8>, BlockNumber)> {
+			Abc::errands_created_between(from, to, limit)
+		}
+
+		fn errand_outcome(cid: Vec<u8>) -> Option<pallet_abc_runtime_api::Outcome> {
+			Abc::errand_outcome(cid)
+		}
+
+		fn derive_errand_id(account: AccountId, salt: Vec<u8>) -> Vec<u8> {
+			Abc::derive_errand_id(&account, &salt)
+		}
+
+		fn errand_status_log(errand_id: Vec<u8>) -> Vec<(u8, BlockNumber)> {
+			Abc::status_log(errand_id)
+		}
+
+		fn completion_proof(cid: Vec<u8>) -> Option<(AccountId, Vec<u8>)> {
+			Abc::completion_proof(cid)
+		}
+
+		fn latest_result(cid: Vec<u8>) -> Option<(BlockNumber, Vec<u8>)> {
+			Abc::latest_result(&cid)
+		}
+
+		fn result_versions(cid: Vec<u8>) -> Vec<(BlockNumber, Vec<u8>)> {
+			Abc::public_result_versions(&cid)
+		}
+
+		fn errand_by_extrinsic(block: BlockNumber, index: u32) -> Option<Vec<u8>> {
+			Abc::errand_by_extrinsic(block, index)
+		}
+
+		fn account_status_summary(account: AccountId) -> (u32, u32, u32, u32) {
+			Abc::account_status_summary(account)
+		}
+
+		fn total_pending_tasks() -> u64 {
+			Abc::total_pending_tasks()
+		}
+
+		fn is_valid_cid(cid: Vec<u8>) -> bool {
+			Abc::is_valid_cid(&cid)
+		}
+
+		fn service_latency() -> (u32, u32) {
+			Abc::service_latency()
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn dispatch_benchmark(